@@ -1,64 +1,213 @@
-use std::{marker::PhantomData, pin::Pin};
+use std::{
+    collections::{BTreeMap, HashSet},
+    marker::PhantomData,
+    pin::Pin,
+};
 
 use actix_session::SessionExt;
-use actix_web::{FromRequest, HttpRequest, dev::Payload};
-use apistos::ApiSecurity;
-use engelsystem_rs_db::role::RoleType;
+use actix_web::{
+    FromRequest, HttpMessage, HttpRequest,
+    dev::Payload,
+    http::header::{AUTHORIZATION, HeaderValue},
+    web::Data,
+};
+use apistos::{
+    ApiComponent, Schema,
+    reference_or::ReferenceOr,
+    security::{ApiKey, ApiKeyIn, Http, SecurityScheme, SecurityType},
+};
+use engelsystem_rs_db::{
+    Database,
+    api_token::authenticate_api_token,
+    permission::{PermissionType, get_role_permissions},
+    role::RoleId,
+    user::get_user_role_id,
+};
 use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
 use uuid::Uuid;
 
-use crate::{Error, generated::SessionDeserializeErr};
+use crate::{
+    Error,
+    generated::{DatabaseErr, SessionDeserializeErr},
+};
+
+/// Name of the security scheme for the session cookie in the OpenAPI spec
+pub(crate) const SESSION_SCHEME: &str = "session-id";
+/// Name of the security scheme for API tokens in the OpenAPI spec
+pub(crate) const BEARER_SCHEME: &str = "bearer";
+/// Name of the security scheme for operations that only accept the session cookie
+pub(crate) const LOGIN_SCHEME: &str = "login";
+
+/// The permissions an API token may use. Stored in the request, so [`has_permission`] can limit
+/// the permissions of the role to them.
+struct TokenScopes(HashSet<PermissionType>);
+
+/// The token of an `Authorization: Bearer` header. Other kinds of authorization are rejected, so
+/// a misconfigured script doesn't silently fall back to the cookie.
+fn bearer_token(header: &HeaderValue) -> crate::Result<&str> {
+    header
+        .to_str()
+        .ok()
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token.trim())
+        .ok_or(Error::InvalidApiToken)
+}
 
 trait BasicResolveSessionImpl {
-    fn basic_resolve_session<A: BasicAuthTrait>(
+    /// The user is identified by an API token or the session. Their role is read from the database
+    /// on every request, so role changes take effect immediately.
+    async fn basic_resolve_session<A: BasicAuthTrait>(
         req: &actix_web::HttpRequest,
     ) -> crate::Result<BasicUser<A>> {
-        let session = req.get_session();
-        let user_id: Uuid = session
-            .get("user_id")
-            .context(SessionDeserializeErr)?
+        let db = req
+            .app_data::<Data<Database>>()
+            .expect("The database is not registered as app data");
+
+        let (user_id, token_id) = match req.headers().get(AUTHORIZATION) {
+            Some(header) => {
+                let token = authenticate_api_token(bearer_token(header)?, db)
+                    .await
+                    .context(DatabaseErr)?
+                    .ok_or(Error::InvalidApiToken)?;
+                req.extensions_mut().insert(TokenScopes(token.scopes));
+
+                (token.user_id, Some(token.token_id))
+            }
+            None => {
+                let user_id: Uuid = req
+                    .get_session()
+                    .get("user_id")
+                    .context(SessionDeserializeErr)?
+                    .ok_or(Error::SessionUnauthenticated)?;
+
+                (user_id, None)
+            }
+        };
+
+        let role_id = get_user_role_id(user_id, db)
+            .await
+            .context(DatabaseErr)?
             .ok_or(Error::SessionUnauthenticated)?;
-        // TODO: This should be fairly safe. Though this should probably fail properly usually
-        let role_id: RoleType = RoleType::from_or_default(
-            session
-                .get::<u32>("role_id")
-                .context(SessionDeserializeErr)?
-                .ok_or(Error::SessionUnauthenticated)?,
-        );
-
-        Ok(BasicUser::new(user_id, role_id))
+
+        let mut user = BasicUser::new(user_id, role_id);
+        user.token_id = token_id;
+        Ok(user)
     }
 }
 
 impl<T: BasicAuthTrait> BasicResolveSessionImpl for T {}
 
 pub trait BasicAuthTrait: Sized + 'static {
+    /// Whether requests with an API token are let through, otherwise only the session cookie is
+    const ACCEPTS_TOKENS: bool = true;
+
     fn authenticate(
         user: BasicUser<Self>,
         req: actix_web::HttpRequest,
     ) -> impl Future<Output = crate::Result<BasicUser<Self>>>;
 }
 
-#[derive(Serialize, Deserialize, ApiSecurity)]
-#[openapi_security(scheme(security_type(api_key(name = "session-id", api_key_in = "cookie"))))]
+#[derive(Serialize, Deserialize)]
 pub struct BasicUser<AuthType: BasicAuthTrait> {
     pub uid: Uuid,
-    pub role: RoleType,
+    pub role_id: RoleId,
+    /// The API token of the request, unset for requests with the session cookie
+    pub token_id: Option<Uuid>,
 
     _auth_type: PhantomData<AuthType>,
 }
 
 impl<AuthType: BasicAuthTrait> BasicUser<AuthType> {
-    pub fn new(uid: Uuid, role: RoleType) -> BasicUser<AuthType> {
+    pub fn new(uid: Uuid, role_id: RoleId) -> BasicUser<AuthType> {
         BasicUser {
             uid,
-            role,
+            role_id,
+            token_id: None,
+            _auth_type: PhantomData,
+        }
+    }
+
+    fn with_auth_type<Other: BasicAuthTrait>(self) -> BasicUser<Other> {
+        BasicUser {
+            uid: self.uid,
+            role_id: self.role_id,
+            token_id: self.token_id,
             _auth_type: PhantomData,
         }
     }
 }
 
+/// Written by hand instead of derived, as the derive only supports one security scheme. Operations
+/// refer to the session cookie, the token is added as alternative to the served spec by
+/// [`crate::server`]. Operations that don't accept tokens refer to [`LOGIN_SCHEME`] instead.
+impl<AuthType: BasicAuthTrait> ApiComponent for BasicUser<AuthType> {
+    fn child_schemas() -> Vec<(String, ReferenceOr<Schema>)> {
+        vec![]
+    }
+
+    fn schema() -> Option<(String, ReferenceOr<Schema>)> {
+        None
+    }
+
+    fn securities() -> BTreeMap<String, SecurityScheme> {
+        BTreeMap::from([
+            (
+                SESSION_SCHEME.to_string(),
+                SecurityScheme {
+                    _type: SecurityType::ApiKey(ApiKey {
+                        name: "session-id".to_string(),
+                        _in: ApiKeyIn::Cookie,
+                    }),
+                    description: Some("The session cookie set by `/login`".to_string()),
+                    extensions: Default::default(),
+                },
+            ),
+            (
+                LOGIN_SCHEME.to_string(),
+                SecurityScheme {
+                    _type: SecurityType::ApiKey(ApiKey {
+                        name: "session-id".to_string(),
+                        _in: ApiKeyIn::Cookie,
+                    }),
+                    description: Some(
+                        "The session cookie set by `/login`, for changes to the account that API \
+                         tokens can't make"
+                            .to_string(),
+                    ),
+                    extensions: Default::default(),
+                },
+            ),
+            (
+                BEARER_SCHEME.to_string(),
+                SecurityScheme {
+                    _type: SecurityType::Http(Http {
+                        scheme: "bearer".to_string(),
+                        bearer_format: None,
+                    }),
+                    description: Some(
+                        "A personal or service token, accepted by the operations that list it. \
+                         The scopes of an operation are the permissions a token needs."
+                            .to_string(),
+                    ),
+                    extensions: Default::default(),
+                },
+            ),
+        ])
+    }
+
+    fn security_requirement_name() -> Option<String> {
+        let scheme = if AuthType::ACCEPTS_TOKENS {
+            SESSION_SCHEME
+        } else {
+            LOGIN_SCHEME
+        };
+
+        Some(scheme.to_string())
+    }
+}
+
 impl<A: BasicAuthTrait> FromRequest for BasicUser<A> {
     type Error = Error;
 
@@ -67,46 +216,173 @@ impl<A: BasicAuthTrait> FromRequest for BasicUser<A> {
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
         let req = req.clone();
         Box::pin(async move {
-            let user = A::basic_resolve_session(&req)?;
+            let user = A::basic_resolve_session(&req).await?;
             A::authenticate(user, req).await
         })
     }
 }
 
-pub struct BasicAdminAuth(());
-impl BasicAuthTrait for BasicAdminAuth {
+pub struct BasicGuestAuth(());
+impl BasicAuthTrait for BasicGuestAuth {
     async fn authenticate(
         user: BasicUser<Self>,
         _req: HttpRequest,
     ) -> crate::Result<BasicUser<Self>> {
-        if user.role.is_bypass() {
-            Ok(user)
-        } else {
-            Err(Error::SessionUnauthorized)
-        }
+        Ok(user)
     }
 }
 
-pub struct BasicUserAuth(());
-impl BasicAuthTrait for BasicUserAuth {
+/// Only lets users through that sent the session cookie, and then checks `A`. API tokens are
+/// rejected whatever their scopes, so a leaked token can't be used to take over the account, e.g.
+/// `BasicUser<RequireLogin>` or `BasicUser<RequireLogin<RequirePermission<P>>>`.
+pub struct RequireLogin<A: BasicAuthTrait = BasicGuestAuth>(PhantomData<A>);
+impl<A: BasicAuthTrait> BasicAuthTrait for RequireLogin<A> {
+    const ACCEPTS_TOKENS: bool = false;
+
     async fn authenticate(
         user: BasicUser<Self>,
-        _req: HttpRequest,
+        req: HttpRequest,
     ) -> crate::Result<BasicUser<Self>> {
-        if user.role.is_bypass() || user.role == RoleType::User {
-            Ok(user)
-        } else {
-            Err(Error::SessionUnauthorized)
+        if user.token_id.is_some() {
+            return Err(Error::LoginRequired);
         }
+
+        Ok(A::authenticate(user.with_auth_type(), req)
+            .await?
+            .with_auth_type())
     }
 }
 
-pub struct BasicGuestAuth(());
-impl BasicAuthTrait for BasicGuestAuth {
+/// Lists an API token as alternative to the session cookie in the security requirements of all
+/// operations of an OpenAPI spec that accept one
+pub(crate) fn add_token_alternatives(spec: &mut serde_json::Value) {
+    let Some(paths) = spec
+        .get_mut("paths")
+        .and_then(|paths| paths.as_object_mut())
+    else {
+        return;
+    };
+
+    let operations = paths
+        .values_mut()
+        .filter_map(|item| item.as_object_mut())
+        .flat_map(|item| item.values_mut());
+
+    for operation in operations {
+        let Some(security) = operation
+            .get_mut("security")
+            .and_then(|security| security.as_array_mut())
+        else {
+            continue;
+        };
+
+        let tokens: Vec<_> = security
+            .iter()
+            .filter_map(|requirement| requirement.get(SESSION_SCHEME))
+            .map(|scopes| serde_json::json!({ BEARER_SCHEME: scopes }))
+            .collect();
+        security.extend(tokens);
+    }
+}
+
+/// The permissions of the callers role, limited to the scopes of their API token. They are loaded
+/// on the first permission check of a request and reused by every further check of the same
+/// request.
+struct RolePermissions(HashSet<PermissionType>);
+
+/// The permissions the role of `user` has enabled in the database, limited to the ones the API
+/// token of the request may use.
+pub async fn user_permissions<A: BasicAuthTrait>(
+    user: &BasicUser<A>,
+    req: &HttpRequest,
+) -> crate::Result<HashSet<PermissionType>> {
+    if let Some(permissions) = req.extensions().get::<RolePermissions>() {
+        return Ok(permissions.0.clone());
+    }
+
+    let db = req
+        .app_data::<Data<Database>>()
+        .expect("The database is not registered as app data");
+
+    let mut permissions = get_role_permissions(user.role_id, db)
+        .await
+        .context(DatabaseErr)?;
+    if let Some(scopes) = req.extensions().get::<TokenScopes>() {
+        permissions.retain(|permission| scopes.0.contains(permission));
+    }
+    req.extensions_mut()
+        .insert(RolePermissions(permissions.clone()));
+
+    Ok(permissions)
+}
+
+/// Checks if the role of `user` has `permission` enabled in the database, and if the API token of
+/// the request may use it.
+pub async fn has_permission<A: BasicAuthTrait>(
+    user: &BasicUser<A>,
+    permission: PermissionType,
+    req: &HttpRequest,
+) -> crate::Result<bool> {
+    Ok(user_permissions(user, req).await?.contains(&permission))
+}
+
+/// Checks if the API token of the request may use `permission`, always true for requests with the
+/// session cookie. For access that comes from the relation of a user to a resource instead of
+/// their role, e.g. managing their own shift, which a token may only use with the matching scope.
+pub fn token_allows(permission: PermissionType, req: &HttpRequest) -> bool {
+    req.extensions()
+        .get::<TokenScopes>()
+        .is_none_or(|scopes| scopes.0.contains(&permission))
+}
+
+pub trait RequiredPermission: 'static {
+    const PERMISSION: PermissionType;
+}
+
+/// Marker types for [`RequirePermission`], one for each [`PermissionType`]
+pub mod permission {
+    use super::{PermissionType, RequiredPermission};
+
+    macro_rules! required_permissions {
+        ($( $permission:ident ),*) => {
+            $(
+                pub struct $permission(());
+                impl RequiredPermission for $permission {
+                    const PERMISSION: PermissionType = PermissionType::$permission;
+                }
+            )*
+        };
+    }
+
+    required_permissions!(
+        AddUser,
+        DeleteUser,
+        ViewUsers,
+        SignUpForShifts,
+        ManageShifts,
+        JoinAngelTypes,
+        ManageAngelTypes,
+        ManageRoles,
+        ManageLocations,
+        ViewAuditLog,
+        ViewLoginLockouts,
+        EndUserSessions,
+        ManageApiTokens
+    );
+}
+
+/// Only lets users through whose role has the permission `P` enabled, e.g.
+/// `BasicUser<RequirePermission<permission::ManageShifts>>`.
+pub struct RequirePermission<P: RequiredPermission>(PhantomData<P>);
+impl<P: RequiredPermission> BasicAuthTrait for RequirePermission<P> {
     async fn authenticate(
         user: BasicUser<Self>,
-        _req: HttpRequest,
+        req: HttpRequest,
     ) -> crate::Result<BasicUser<Self>> {
-        Ok(user)
+        if has_permission(&user, P::PERMISSION, &req).await? {
+            Ok(user)
+        } else {
+            Err(Error::SessionUnauthorized)
+        }
     }
 }
@@ -1,16 +1,20 @@
 use actix_session::SessionInsertError;
-use actix_web::{http::StatusCode, ResponseError};
+use actix_web::{ResponseError, http::StatusCode};
 use apistos::ApiErrorComponent;
 use snafu::Snafu;
 use tracing::error;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+#[allow(clippy::duplicated_attributes)]
 #[derive(Debug, Snafu, ApiErrorComponent)]
 #[openapi_error(
+    status(code = 400),
     status(code = 401),
     status(code = 403),
     status(code = 404),
+    status(code = 409),
+    status(code = 429),
     status(code = 500)
 )]
 #[snafu(context(suffix(Err)), module(generated), visibility(pub(crate)))]
@@ -20,6 +24,16 @@ pub enum Error {
         source: engelsystem_rs_db::Error,
     },
 
+    #[snafu(display("Mail Error: {source}"))]
+    Mail {
+        source: crate::mail::MailError,
+    },
+
+    #[snafu(display("Session Store Error: {source}"))]
+    SessionStore {
+        source: crate::session_store::SessionStoreError,
+    },
+
     #[snafu(display("Webserver Error: {source}"))]
     Webserver {
         source: std::io::Error,
@@ -48,8 +62,51 @@ pub enum Error {
 
     LoginFailed,
 
+    #[snafu(display(
+        "Zu viele fehlgeschlagene Anmeldeversuche. Bitte versuche es später noch einmal."
+    ))]
+    LoginBlocked,
+
     RegisterValidationFailed,
 
+    #[snafu(display("Das aktuelle Passwort ist falsch"))]
+    WrongPassword,
+
+    #[snafu(display("Die Passwörter stimmen nicht überein"))]
+    PasswordMismatch,
+
+    #[snafu(display(
+        "Der Link zum Zurücksetzen des Passworts ist ungültig, abgelaufen oder wurde bereits benutzt"
+    ))]
+    InvalidResetToken,
+
+    #[snafu(display(
+        "Der Link zum Bestätigen der E-Mail-Adresse ist ungültig, abgelaufen oder wurde bereits benutzt"
+    ))]
+    InvalidVerificationToken,
+
+    #[snafu(display("Bitte bestätige zuerst deine E-Mail-Adresse"))]
+    EmailNotVerified,
+
+    #[snafu(display("Deine E-Mail-Adresse ist bereits bestätigt"))]
+    EmailAlreadyVerified,
+
+    #[snafu(display("Die Benachrichtigung wurde nicht gefunden"))]
+    NotificationNotFound,
+
+    #[snafu(display("Die Sitzung wurde nicht gefunden"))]
+    UserSessionNotFound,
+
+    #[snafu(display(
+        "Die Vorlaufzeit für Erinnerungen muss zwischen 5 Minuten und 7 Tagen liegen"
+    ))]
+    InvalidReminderLeadTime,
+
+    #[snafu(display("{message}"))]
+    InvalidPassword {
+        message: String,
+    },
+
     #[snafu(display("Es existiert bereits ein Benutzer mit dieser Email"))]
     UserExists,
 
@@ -68,6 +125,142 @@ pub enum Error {
         name: String,
     },
 
+    #[snafu(display("Es konnte kein Engeltyp mit der ID {id} gefunden werden"))]
+    AngelTypeIdNotFound {
+        id: u32,
+    },
+
+    #[snafu(display("Ein Engeltyp mit diesem Namen existiert bereits"))]
+    AngelTypeExists,
+
+    #[snafu(display("Der Engeltyp wird noch von Schichten verwendet"))]
+    AngelTypeInUse,
+
+    #[snafu(display("Der Ort wurde nicht gefunden"))]
+    LocationNotFound,
+
+    #[snafu(display("Ein Ort mit diesem Namen existiert bereits"))]
+    LocationExists,
+
+    #[snafu(display("Der Ort wird noch von Schichten verwendet"))]
+    LocationInUse,
+
+    #[snafu(display(
+        "Koordinaten brauchen einen Breitengrad zwischen -90 und 90 und einen Längengrad zwischen -180 und 180"
+    ))]
+    InvalidCoordinates,
+
+    #[snafu(display("Ein Kartenlink muss eine http- oder https-URL sein"))]
+    InvalidMapUrl,
+
+    #[snafu(display("Du bist bereits Mitglied dieses Engeltyps oder hast es angefragt"))]
+    AlreadyMember,
+
+    #[snafu(display("Die Mitgliedschaft im Engeltyp wurde nicht gefunden"))]
+    MembershipNotFound,
+
+    #[snafu(display("Für diese Schicht musst du bestätigtes Mitglied des Engeltyps sein"))]
+    NotQualified,
+
+    #[snafu(display("Die Schicht wurde nicht gefunden"))]
+    ShiftNotFound,
+
+    #[snafu(display("Für diese Schicht sind keine Plätze mehr frei"))]
+    ShiftFull,
+
+    #[snafu(display("Du bist für diese Schicht bereits eingetragen"))]
+    AlreadySignedUp,
+
+    #[snafu(display("Die Schicht überschneidet sich mit deiner Schicht {name:?} ({shift_id})"))]
+    ShiftConflict {
+        shift_id: uuid::Uuid,
+        name: String,
+    },
+
+    #[snafu(display("Du bist für diese Schicht nicht eingetragen"))]
+    NotSignedUp,
+
+    #[snafu(display("Die Schicht hat noch nicht begonnen"))]
+    ShiftNotStarted,
+
+    #[snafu(display(
+        "Die Schicht hat schon begonnen oder wurde Engeln angerechnet und kann nicht mehr abgesagt werden"
+    ))]
+    ShiftAlreadyStarted,
+
+    #[snafu(display(
+        "Geleistete Stunden müssen bei teilweiser Anwesenheit angegeben werden, und nur dann. Sie dürfen nicht länger als die Schicht sein"
+    ))]
+    InvalidWorkedHours,
+
+    #[snafu(display("So kurz vor Schichtbeginn kannst du dich nicht mehr austragen"))]
+    SignOffCutoffPassed,
+
+    #[snafu(display("Es konnte keine Rolle mit der ID {id} gefunden werden"))]
+    RoleNotFound {
+        id: u32,
+    },
+
+    #[snafu(display("Eine Rolle mit diesem Namen existiert bereits"))]
+    RoleExists,
+
+    #[snafu(display("Die Rolle ist noch Nutzern zugewiesen"))]
+    RoleInUse,
+
+    #[snafu(display("Die Standardrollen können nicht gelöscht werden"))]
+    RoleProtected,
+
+    #[snafu(display("Die Berechtigung {name:?} existiert nicht"))]
+    PermissionNotFound {
+        name: String,
+    },
+
+    #[snafu(display("Der Seitencursor ist ungültig"))]
+    InvalidCursor,
+
+    #[snafu(display("Eine Schicht muss nach ihrem Beginn enden"))]
+    InvalidShiftTime,
+
+    #[snafu(display("Es gibt keine Schichtvorlage mit der ID {id}"))]
+    ShiftTemplateNotFound {
+        id: u32,
+    },
+
+    #[snafu(display(
+        "Eine Schichtvorlage braucht ein Intervall und eine Dauer von mindestens 1 und darf nicht vor ihrem Beginn enden"
+    ))]
+    InvalidRecurrence,
+
+    #[snafu(display(
+        "Der Zeitraum muss nach seinem Beginn enden und darf höchstens ein Jahr lang sein"
+    ))]
+    InvalidDateRange,
+
+    #[snafu(display("Unbekanntes Importformat {format:?}, erwartet wird csv oder json"))]
+    UnknownImportFormat {
+        format: String,
+    },
+
+    #[snafu(display("Die Importdatei konnte nicht gelesen werden: {reason}"))]
+    InvalidImportFile {
+        reason: String,
+    },
+
+    #[snafu(display("Dieser Kalender-Link ist ungültig oder wurde zurückgezogen"))]
+    CalendarTokenNotFound,
+
+    #[snafu(display("Der API-Token ist ungültig, abgelaufen oder wurde zurückgezogen"))]
+    InvalidApiToken,
+
+    #[snafu(display("Der API-Token wurde nicht gefunden"))]
+    ApiTokenNotFound,
+
+    #[snafu(display("Ein API-Token muss in der Zukunft ablaufen"))]
+    InvalidTokenExpiry,
+
+    #[snafu(display("Das geht nur nach einer Anmeldung, nicht mit einem API-Token"))]
+    LoginRequired,
+
     #[snafu(display("An internal error ocurred"))]
     GenericInternalError,
 }
@@ -75,14 +268,65 @@ pub enum Error {
 impl ResponseError for Error {
     fn status_code(&self) -> StatusCode {
         match self {
-            Error::RegisterValidationFailed => StatusCode::BAD_REQUEST,
-            Error::SessionUnauthenticated | Error::LoginFailed => StatusCode::UNAUTHORIZED,
-            Error::SessionUnauthorized => StatusCode::FORBIDDEN,
-            Error::InvalidUid { .. } => StatusCode::NOT_FOUND,
+            Error::RegisterValidationFailed
+            | Error::InvalidCursor
+            | Error::InvalidWorkedHours
+            | Error::InvalidShiftTime
+            | Error::UnknownImportFormat { .. }
+            | Error::InvalidImportFile { .. }
+            | Error::InvalidRecurrence
+            | Error::InvalidDateRange
+            | Error::InvalidCoordinates
+            | Error::InvalidMapUrl
+            | Error::PasswordMismatch
+            | Error::InvalidResetToken
+            | Error::InvalidVerificationToken
+            | Error::InvalidReminderLeadTime
+            | Error::InvalidTokenExpiry
+            | Error::InvalidPassword { .. } => StatusCode::BAD_REQUEST,
+            Error::SessionUnauthenticated | Error::LoginFailed | Error::InvalidApiToken => {
+                StatusCode::UNAUTHORIZED
+            }
+            Error::SessionUnauthorized
+            | Error::LoginRequired
+            | Error::NotQualified
+            | Error::WrongPassword
+            | Error::EmailNotVerified => StatusCode::FORBIDDEN,
+            Error::InvalidUid { .. }
+            | Error::UIDNotFound { .. }
+            | Error::ShiftNotFound
+            | Error::AngelTypeNotFound { .. }
+            | Error::AngelTypeIdNotFound { .. }
+            | Error::MembershipNotFound
+            | Error::RoleNotFound { .. }
+            | Error::PermissionNotFound { .. }
+            | Error::CalendarTokenNotFound
+            | Error::NotificationNotFound
+            | Error::UserSessionNotFound
+            | Error::ApiTokenNotFound
+            | Error::ShiftTemplateNotFound { .. }
+            | Error::LocationNotFound => StatusCode::NOT_FOUND,
+            Error::ShiftFull
+            | Error::EmailAlreadyVerified
+            | Error::AngelTypeExists
+            | Error::AngelTypeInUse
+            | Error::LocationExists
+            | Error::LocationInUse
+            | Error::AlreadyMember
+            | Error::RoleExists
+            | Error::RoleInUse
+            | Error::RoleProtected
+            | Error::AlreadySignedUp
+            | Error::NotSignedUp
+            | Error::SignOffCutoffPassed
+            | Error::ShiftConflict { .. }
+            | Error::ShiftNotStarted
+            | Error::ShiftAlreadyStarted => StatusCode::CONFLICT,
+            Error::LoginBlocked => StatusCode::TOO_MANY_REQUESTS,
             _ => {
                 error!("{self:?} || Readable: {self}");
                 StatusCode::INTERNAL_SERVER_ERROR
-            },
+            }
         }
     }
 }
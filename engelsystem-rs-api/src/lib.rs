@@ -1,8 +1,12 @@
 pub mod authorize_middleware;
 pub mod error;
+pub mod mail;
+pub mod reminders;
 pub mod routes;
 pub mod server;
 pub mod session_db;
+pub mod session_store;
+pub mod session_sweeper;
 pub mod utils;
 
 pub use error::*;
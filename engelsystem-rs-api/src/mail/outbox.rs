@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use chrono::TimeDelta;
+use engelsystem_rs_db::{
+    DatabaseConnection,
+    mail_outbox::{
+        RetryPolicy, claim_due_mails, delete_sent_mails, mark_mail_failed, mark_mail_sent,
+    },
+};
+use tracing::{error, warn};
+
+use super::{Mail, Mailer};
+
+/// How often the outbox is checked for mails to retry, if no new mail wakes it earlier
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a mail is reserved for one attempt. Has to be longer than a send can take.
+const LEASE: TimeDelta = TimeDelta::minutes(5);
+const BATCH_SIZE: u64 = 20;
+/// How long sent mails are kept before they are removed from the outbox
+const RETENTION: TimeDelta = TimeDelta::days(7);
+
+/// Sends the mails in the outbox for as long as the server runs. Queued mails are sent right away,
+/// failed ones are retried according to the policy.
+pub async fn run_outbox(mailer: Mailer, db: DatabaseConnection, retry_policy: RetryPolicy) {
+    loop {
+        if let Err(e) = deliver_due_mails(&mailer, &db, &retry_policy).await {
+            error!("Failed to process the mail outbox: {e}");
+        }
+
+        tokio::select! {
+            _ = mailer.wake.notified() => {}
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+    }
+}
+
+async fn deliver_due_mails(
+    mailer: &Mailer,
+    db: &DatabaseConnection,
+    retry_policy: &RetryPolicy,
+) -> engelsystem_rs_db::Result<()> {
+    delete_sent_mails(RETENTION, db).await?;
+
+    loop {
+        let mails = claim_due_mails(LEASE, BATCH_SIZE, db).await?;
+        let full_batch = mails.len() as u64 == BATCH_SIZE;
+
+        for mail in mails {
+            let id = mail.id;
+            let recipient = mail.recipient.clone();
+
+            let result = mailer
+                .send(Mail {
+                    to: mail.recipient,
+                    subject: mail.subject,
+                    body: mail.body,
+                    contains_secret: mail.contains_secret,
+                })
+                .await;
+
+            match result {
+                Ok(()) => mark_mail_sent(id, db).await?,
+                Err(e) => {
+                    let mail = mark_mail_failed(id, &e.to_string(), retry_policy, db).await?;
+
+                    if mail.failed_at.is_some() {
+                        error!(
+                            "Giving up on mail {id} to {recipient:?} after {} attempts: {e}",
+                            mail.attempts
+                        );
+                    } else {
+                        warn!(
+                            "Failed to send mail {id} to {recipient:?}, retrying at {}: {e}",
+                            mail.next_attempt_at
+                        );
+                    }
+                }
+            }
+        }
+
+        if !full_batch {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use chrono::{FixedOffset, TimeDelta};
+use engelsystem_rs_db::{
+    DatabaseConnection,
+    location::get_location_by_id,
+    reminder::{DueReminder, ReminderMail, get_due_reminders, record_reminder},
+};
+use tracing::{error, info};
+
+use crate::{mail::Mailer, render_mail};
+
+#[derive(Debug, Clone)]
+pub struct ReminderSettings {
+    /// How long before a shift starts angels are reminded, unless they chose otherwise
+    pub default_lead: TimeDelta,
+    /// How often the scheduler looks for shifts to remind angels of
+    pub interval: Duration,
+    /// The time zone shift times are shown in
+    pub utc_offset: FixedOffset,
+}
+
+/// Reminds angels of their upcoming shifts for as long as the server runs. Every angel is reminded
+/// once per shift, through a notification and a mail.
+pub async fn run_reminders(mailer: Mailer, db: DatabaseConnection, settings: ReminderSettings) {
+    loop {
+        if let Err(e) = send_due_reminders(&mailer, &db, &settings).await {
+            error!("Failed to send shift reminders: {e}");
+        }
+
+        tokio::time::sleep(settings.interval).await;
+    }
+}
+
+async fn send_due_reminders(
+    mailer: &Mailer,
+    db: &DatabaseConnection,
+    settings: &ReminderSettings,
+) -> engelsystem_rs_db::Result<()> {
+    for DueReminder { user, shift } in get_due_reminders(settings.default_lead, db).await? {
+        let starts_at = shift
+            .starts_at
+            .with_timezone(&settings.utc_offset)
+            .format("%d.%m.%Y um %H:%M")
+            .to_string();
+
+        let message = format!("Deine Schicht „{}“ beginnt am {starts_at}.", shift.name);
+
+        // Mails to addresses nobody confirmed could go to a stranger
+        let mail = if user.email_verified_at.is_some() {
+            let location = match shift.location_id {
+                Some(id) => get_location_by_id(id, db)
+                    .await?
+                    .map(|location| location.name),
+                None => None,
+            };
+
+            let mail = render_mail!(mailer, "shift_reminder", &user.email, [
+                "username" => &user.username,
+                "shift_name" => &shift.name,
+                "starts_at" => &starts_at,
+                "location" => &location
+            ]);
+
+            match mail {
+                Ok(mail) => Some(ReminderMail {
+                    recipient: mail.to,
+                    subject: mail.subject,
+                    body: mail.body,
+                }),
+                Err(e) => {
+                    error!("Failed to render the reminder for user {}: {e}", user.id);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // The mail is queued in the same transaction, so an angel is never marked as reminded
+        // without getting the mail, nor reminded twice
+        let queued = mail.is_some();
+        if !record_reminder(user.id, shift.id, &message, mail, db).await? {
+            continue;
+        }
+
+        info!("Reminded user {} of shift {}", user.id, shift.id);
+        if queued {
+            mailer.wake_outbox();
+        }
+    }
+
+    Ok(())
+}
@@ -1,18 +1,71 @@
+mod angel_types;
+mod api_tokens;
+mod audit;
+mod calendar;
+mod completion;
+pub(crate) mod email_verification;
+mod locations;
 mod login;
 mod logout;
+mod notifications;
+mod password_reset;
 mod register;
+mod reminders;
+mod roles;
+mod sessions;
 mod settings;
+mod shift_templates;
 mod shifts;
 mod stats;
 mod users;
 
-pub use login::request_login;
+pub use angel_types::{
+    AngelTypeSupporterAuth, angel_type_add, angel_type_delete, angel_type_join, angel_type_leave,
+    angel_type_list, angel_type_member_confirm, angel_type_member_remove,
+    angel_type_member_supporter, angel_type_members, angel_type_update, angel_types_self,
+};
+pub use api_tokens::{
+    personal_token_create, personal_token_list, personal_token_revoke, service_token_create,
+    service_token_list, service_token_revoke,
+};
+pub use audit::audit_list;
+pub use calendar::{calendar_feed, calendar_token_create, calendar_token_revoke};
+pub use completion::{ShiftEditorAuth, ShiftManagerAuth, shift_complete, shift_completions};
+pub use email_verification::{
+    EmailVerificationSettings, UnverifiedAccountPolicy, email_verification_confirm,
+    email_verification_resend,
+};
+pub use locations::{
+    location_add, location_delete, location_list, location_shifts, location_update,
+};
+pub use login::{login_lockout_list, request_login};
 pub use logout::request_logout;
+pub use notifications::{
+    notification_list, notification_read, notification_read_all, notification_unread_count,
+};
+pub use password_reset::{PasswordResetSettings, password_reset_confirm, password_reset_request};
 pub use register::request_register;
+pub use reminders::{reminder_preference_get, reminder_preference_set};
+pub use roles::{
+    role_add, role_delete, role_list, role_permission_set, role_permissions, role_rename,
+};
+pub use sessions::{session_list, session_revoke, session_revoke_all, user_sessions_end};
 pub use settings::update_settings;
+pub use shift_templates::{
+    shift_template_add, shift_template_delete, shift_template_expand, shift_template_list,
+    shift_template_update,
+};
+pub use shifts::ShiftSettings;
 pub use shifts::shift_add;
+pub use shifts::shift_delete;
+pub use shifts::shift_import;
+pub use shifts::shift_list;
+pub use shifts::shift_signoff;
+pub use shifts::shift_signup;
+pub use shifts::shift_update;
 pub use shifts::shifts_self;
-pub use stats::user_count;
+pub use stats::{session_count, user_count};
 pub use users::user_list;
 pub use users::view_me;
+pub use users::view_my_permissions;
 pub use users::view_user;
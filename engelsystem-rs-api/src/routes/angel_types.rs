@@ -0,0 +1,308 @@
+use actix_web::web::{Data, Json, Path};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use engelsystem_rs_db::{
+    AngelType, Database, UserAngelTypeView,
+    angel_type::{
+        add_angel_type, confirm_angel_type_membership, delete_angel_type,
+        delete_angel_type_membership, get_all_angel_types, get_angel_type_members,
+        get_angel_type_membership_view, get_angel_types_of_user, is_angel_type_supporter,
+        request_angel_type_membership, set_angel_type_supporter, update_angel_type,
+    },
+    permission::PermissionType,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use snafu::ResultExt;
+use uuid::Uuid;
+
+use crate::{
+    Error,
+    authorize_middleware::{
+        BasicAuthTrait, BasicGuestAuth, BasicUser, RequirePermission, has_permission,
+        permission::{JoinAngelTypes, ManageAngelTypes},
+        token_allows,
+    },
+    generated::DatabaseErr,
+    utils::path::parse_uuid,
+};
+
+// To use this type of authentication, please specify an angel_type_id resource on the request.
+// Users who may manage angel types and the supporters of that angel type are allowed through. API
+// tokens of supporters need the ManageAngelTypes scope.
+pub struct AngelTypeSupporterAuth {}
+
+impl BasicAuthTrait for AngelTypeSupporterAuth {
+    async fn authenticate(
+        user: BasicUser<Self>,
+        req: actix_web::HttpRequest,
+    ) -> crate::Result<BasicUser<Self>> {
+        if has_permission(&user, PermissionType::ManageAngelTypes, &req).await? {
+            return Ok(user);
+        }
+        if !token_allows(PermissionType::ManageAngelTypes, &req) {
+            return Err(Error::SessionUnauthorized);
+        }
+
+        let angel_type_id = req
+            .match_info()
+            .get("angel_type_id")
+            .expect("The route scope is missing an {angel_type_id} path parameter")
+            .parse()
+            .map_err(|_| Error::SessionUnauthorized)?;
+
+        let db = req
+            .app_data::<Data<Database>>()
+            .expect("The database is not registered as app data");
+
+        if is_angel_type_supporter(user.uid, angel_type_id, db)
+            .await
+            .context(DatabaseErr)?
+        {
+            Ok(user)
+        } else {
+            Err(Error::SessionUnauthorized)
+        }
+    }
+}
+
+fn map_angel_type_error(err: engelsystem_rs_db::Error, angel_type_id: u32) -> Error {
+    use engelsystem_rs_db::Error as DbError;
+
+    match err {
+        DbError::AngelTypeNotFound => Error::AngelTypeIdNotFound { id: angel_type_id },
+        DbError::AngelTypeExists => Error::AngelTypeExists,
+        DbError::AngelTypeInUse => Error::AngelTypeInUse,
+        DbError::AlreadyMember => Error::AlreadyMember,
+        DbError::MembershipNotFound => Error::MembershipNotFound,
+        source => Error::Database { source },
+    }
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Get all angel types",
+    security_scope(name = "session-id",)
+)]
+pub async fn angel_type_list(
+    db: Data<Database>,
+    _user: BasicUser<BasicGuestAuth>,
+) -> crate::Result<Json<Vec<AngelType>>> {
+    let angel_types = get_all_angel_types(&db).await.context(DatabaseErr)?;
+
+    Ok(Json(angel_types))
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct NewAngelType {
+    pub name: String,
+    #[serde(default)]
+    pub needs_introduction: bool,
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Add an angel type",
+    security_scope(name = "session-id", scope = "ManageAngelTypes",)
+)]
+pub async fn angel_type_add(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageAngelTypes>>,
+    Json(new): Json<NewAngelType>,
+) -> crate::Result<Json<AngelType>> {
+    match add_angel_type(new.name, new.needs_introduction, &db).await {
+        Ok(angel_type) => Ok(Json(angel_type)),
+        Err(engelsystem_rs_db::Error::AngelTypeExists) => Err(Error::AngelTypeExists),
+        Err(e) => Err(e).context(DatabaseErr),
+    }
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct AngelTypeUpdate {
+    pub name: Option<String>,
+    pub needs_introduction: Option<bool>,
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Rename an angel type or change if it needs an introduction",
+    security_scope(name = "session-id", scope = "ManageAngelTypes",)
+)]
+pub async fn angel_type_update(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageAngelTypes>>,
+    angel_type_id: Path<u32>,
+    Json(update): Json<AngelTypeUpdate>,
+) -> crate::Result<Json<AngelType>> {
+    let angel_type_id = angel_type_id.into_inner();
+    let angel_type = update_angel_type(angel_type_id, update.name, update.needs_introduction, &db)
+        .await
+        .map_err(|e| map_angel_type_error(e, angel_type_id))?;
+
+    Ok(Json(angel_type))
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Delete an angel type that no shift uses anymore",
+    security_scope(name = "session-id", scope = "ManageAngelTypes",)
+)]
+pub async fn angel_type_delete(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageAngelTypes>>,
+    angel_type_id: Path<u32>,
+) -> crate::Result<NoContent> {
+    let angel_type_id = angel_type_id.into_inner();
+    delete_angel_type(angel_type_id, &db)
+        .await
+        .map_err(|e| map_angel_type_error(e, angel_type_id))?;
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Get all angel types you are a member of or requested to join",
+    security_scope(name = "session-id",)
+)]
+pub async fn angel_types_self(
+    db: Data<Database>,
+    user: BasicUser<BasicGuestAuth>,
+) -> crate::Result<Json<Vec<UserAngelTypeView>>> {
+    let memberships = get_angel_types_of_user(user.uid, &db)
+        .await
+        .context(DatabaseErr)?;
+
+    Ok(Json(memberships))
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Request to join an angel type",
+    description = "Angel types that need an introduction have to be confirmed by one of their supporters first",
+    security_scope(name = "session-id", scope = "JoinAngelTypes",)
+)]
+pub async fn angel_type_join(
+    db: Data<Database>,
+    user: BasicUser<RequirePermission<JoinAngelTypes>>,
+    angel_type_id: Path<u32>,
+) -> crate::Result<Json<UserAngelTypeView>> {
+    let angel_type_id = angel_type_id.into_inner();
+    request_angel_type_membership(user.uid, angel_type_id, &db)
+        .await
+        .map_err(|e| map_angel_type_error(e, angel_type_id))?;
+
+    membership_view(user.uid, angel_type_id, &db).await
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Leave an angel type or withdraw a pending request",
+    security_scope(name = "session-id", scope = "JoinAngelTypes",)
+)]
+pub async fn angel_type_leave(
+    db: Data<Database>,
+    user: BasicUser<RequirePermission<JoinAngelTypes>>,
+    angel_type_id: Path<u32>,
+) -> crate::Result<NoContent> {
+    let angel_type_id = angel_type_id.into_inner();
+    delete_angel_type_membership(user.uid, angel_type_id, &db)
+        .await
+        .map_err(|e| map_angel_type_error(e, angel_type_id))?;
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Get all members and pending requests of an angel type",
+    security_scope(name = "session-id", scope = "supporter",)
+)]
+pub async fn angel_type_members(
+    db: Data<Database>,
+    _user: BasicUser<AngelTypeSupporterAuth>,
+    angel_type_id: Path<u32>,
+) -> crate::Result<Json<Vec<UserAngelTypeView>>> {
+    let members = get_angel_type_members(angel_type_id.into_inner(), &db)
+        .await
+        .context(DatabaseErr)?;
+
+    Ok(Json(members))
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Confirm a pending request to join an angel type",
+    security_scope(name = "session-id", scope = "supporter",)
+)]
+pub async fn angel_type_member_confirm(
+    db: Data<Database>,
+    user: BasicUser<AngelTypeSupporterAuth>,
+    path: Path<(u32, String)>,
+) -> crate::Result<Json<UserAngelTypeView>> {
+    let (angel_type_id, member_id) = path.into_inner();
+    let member_id = parse_uuid(member_id)?;
+
+    confirm_angel_type_membership(member_id, angel_type_id, user.uid, &db)
+        .await
+        .map_err(|e| map_angel_type_error(e, angel_type_id))?;
+
+    membership_view(member_id, angel_type_id, &db).await
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Reject a pending request or remove a member from an angel type",
+    security_scope(name = "session-id", scope = "supporter",)
+)]
+pub async fn angel_type_member_remove(
+    db: Data<Database>,
+    _user: BasicUser<AngelTypeSupporterAuth>,
+    path: Path<(u32, String)>,
+) -> crate::Result<NoContent> {
+    let (angel_type_id, member_id) = path.into_inner();
+    let member_id = parse_uuid(member_id)?;
+
+    delete_angel_type_membership(member_id, angel_type_id, &db)
+        .await
+        .map_err(|e| map_angel_type_error(e, angel_type_id))?;
+
+    Ok(NoContent)
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct SupporterUpdate {
+    pub supporter: bool,
+}
+
+#[api_operation(
+    tag = "angel_type",
+    summary = "Appoint or remove a supporter of an angel type",
+    security_scope(name = "session-id", scope = "ManageAngelTypes",)
+)]
+pub async fn angel_type_member_supporter(
+    db: Data<Database>,
+    user: BasicUser<RequirePermission<ManageAngelTypes>>,
+    path: Path<(u32, String)>,
+    Json(update): Json<SupporterUpdate>,
+) -> crate::Result<Json<UserAngelTypeView>> {
+    let (angel_type_id, member_id) = path.into_inner();
+    let member_id = parse_uuid(member_id)?;
+
+    set_angel_type_supporter(member_id, angel_type_id, update.supporter, user.uid, &db)
+        .await
+        .map_err(|e| map_angel_type_error(e, angel_type_id))?;
+
+    membership_view(member_id, angel_type_id, &db).await
+}
+
+async fn membership_view(
+    user_id: Uuid,
+    angel_type_id: u32,
+    db: &Database,
+) -> crate::Result<Json<UserAngelTypeView>> {
+    get_angel_type_membership_view(user_id, angel_type_id, db)
+        .await
+        .context(DatabaseErr)?
+        .map(Json)
+        .ok_or(Error::MembershipNotFound)
+}
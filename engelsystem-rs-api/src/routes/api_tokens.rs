@@ -0,0 +1,208 @@
+use std::str::FromStr;
+
+use actix_web::{
+    HttpRequest,
+    web::{Data, Json, Path},
+};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use chrono::{DateTime, Utc};
+use engelsystem_rs_db::{
+    ApiTokenView, Database,
+    api_token::{
+        create_personal_token, create_service_token, get_personal_tokens, get_service_tokens,
+        revoke_personal_token, revoke_service_token,
+    },
+    permission::PermissionType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    Error,
+    authorize_middleware::{
+        BasicGuestAuth, BasicUser, RequireLogin, RequirePermission, permission::ManageApiTokens,
+    },
+    generated::DatabaseErr,
+    utils::{audit::request_actor, path::parse_uuid},
+};
+
+fn map_token_error(err: engelsystem_rs_db::Error) -> Error {
+    use engelsystem_rs_db::Error as DbError;
+
+    match err {
+        DbError::ApiTokenNotFound => Error::ApiTokenNotFound,
+        DbError::InvalidTokenExpiry => Error::InvalidTokenExpiry,
+        source => Error::Database { source },
+    }
+}
+
+fn parse_scopes(names: Vec<String>) -> crate::Result<Vec<PermissionType>> {
+    names
+        .into_iter()
+        .map(|name| PermissionType::from_str(&name).map_err(|_| Error::PermissionNotFound { name }))
+        .collect()
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct NewPersonalToken {
+    pub name: String,
+    /// Names of the permissions the token may use. It only gets the ones the role of the user
+    /// has enabled.
+    pub scopes: Vec<String>,
+    /// The token doesn't expire if this is unset
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct NewServiceToken {
+    /// The account the token acts as
+    pub user_id: Uuid,
+    pub name: String,
+    /// Names of the permissions the token may use. It only gets the ones the role of the account
+    /// has enabled.
+    pub scopes: Vec<String>,
+    /// The token doesn't expire if this is unset
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct CreatedToken {
+    /// Sent as `Authorization: Bearer <token>`. It is only shown once.
+    pub token: String,
+    #[serde(flatten)]
+    pub details: ApiTokenView,
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Get the personal API tokens of the logged in user, newest first",
+    security_scope(name = "session-id",)
+)]
+pub async fn personal_token_list(
+    db: Data<Database>,
+    user: BasicUser<BasicGuestAuth>,
+) -> crate::Result<Json<Vec<ApiTokenView>>> {
+    let tokens = get_personal_tokens(user.uid, &db)
+        .await
+        .context(DatabaseErr)?;
+
+    Ok(Json(tokens))
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Create a personal API token for the logged in user",
+    description = "Only possible with the session cookie, not with another token",
+    security_scope(name = "login",)
+)]
+pub async fn personal_token_create(
+    db: Data<Database>,
+    user: BasicUser<RequireLogin>,
+    Json(new): Json<NewPersonalToken>,
+) -> crate::Result<Json<CreatedToken>> {
+    let scopes = parse_scopes(new.scopes)?;
+
+    let (details, token) = create_personal_token(user.uid, new.name, &scopes, new.expires_at, &db)
+        .await
+        .map_err(map_token_error)?;
+
+    Ok(Json(CreatedToken { token, details }))
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Revoke a personal API token of the logged in user",
+    description = "Only possible with the session cookie, not with another token",
+    security_scope(name = "login",)
+)]
+pub async fn personal_token_revoke(
+    db: Data<Database>,
+    user: BasicUser<RequireLogin>,
+    token_id: Path<String>,
+) -> crate::Result<NoContent> {
+    let token_id = parse_uuid(token_id.into_inner())?;
+
+    revoke_personal_token(user.uid, token_id, &db)
+        .await
+        .map_err(map_token_error)?;
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "token",
+    summary = "Get all service tokens, newest first",
+    security_scope(name = "session-id", scope = "ManageApiTokens",)
+)]
+pub async fn service_token_list(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageApiTokens>>,
+) -> crate::Result<Json<Vec<ApiTokenView>>> {
+    let tokens = get_service_tokens(&db).await.context(DatabaseErr)?;
+
+    Ok(Json(tokens))
+}
+
+#[api_operation(
+    tag = "token",
+    summary = "Create a service token for an integration, acting as the given account",
+    description = "Only possible with the session cookie. The change is recorded in the audit log",
+    security_scope(name = "login", scope = "ManageApiTokens",)
+)]
+pub async fn service_token_create(
+    req: HttpRequest,
+    db: Data<Database>,
+    user: BasicUser<RequireLogin<RequirePermission<ManageApiTokens>>>,
+    Json(new): Json<NewServiceToken>,
+) -> crate::Result<Json<CreatedToken>> {
+    let scopes = parse_scopes(new.scopes)?;
+
+    let (details, token) = create_service_token(
+        new.user_id,
+        new.name,
+        &scopes,
+        new.expires_at,
+        &request_actor(&req, user.uid),
+        db.get_ref(),
+    )
+    .await
+    .map_err(|err| match err {
+        engelsystem_rs_db::Error::UserNotFound => Error::UIDNotFound {
+            uid: new.user_id.to_string(),
+        },
+        err => map_token_error(err),
+    })?;
+
+    info!(
+        "Created service token {} for user {}",
+        details.id, new.user_id
+    );
+
+    Ok(Json(CreatedToken { token, details }))
+}
+
+#[api_operation(
+    tag = "token",
+    summary = "Revoke a service token",
+    description = "Only possible with the session cookie. The change is recorded in the audit log",
+    security_scope(name = "login", scope = "ManageApiTokens",)
+)]
+pub async fn service_token_revoke(
+    req: HttpRequest,
+    db: Data<Database>,
+    user: BasicUser<RequireLogin<RequirePermission<ManageApiTokens>>>,
+    token_id: Path<String>,
+) -> crate::Result<NoContent> {
+    let token_id = parse_uuid(token_id.into_inner())?;
+
+    revoke_service_token(token_id, &request_actor(&req, user.uid), db.get_ref())
+        .await
+        .map_err(map_token_error)?;
+
+    info!("Revoked service token {token_id}");
+
+    Ok(NoContent)
+}
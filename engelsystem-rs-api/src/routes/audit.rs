@@ -0,0 +1,82 @@
+use actix_web::web::{Data, Json, Query};
+use apistos::{ApiComponent, api_operation};
+use chrono::{DateTime, Utc};
+use engelsystem_rs_db::{
+    AuditAction, AuditEntry, AuditOrigin, AuditTarget, Database,
+    audit::{AuditQuery, get_audit_entries},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::{
+    authorize_middleware::{BasicUser, RequirePermission, permission::ViewAuditLog},
+    generated::DatabaseErr,
+    routes::shifts::resolve_user_id,
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct AuditFilter {
+    /// Username of the user who made the changes
+    actor: Option<String>,
+    origin: Option<AuditOrigin>,
+    action: Option<AuditAction>,
+    target_type: Option<AuditTarget>,
+    /// Id of the changed shift, role or user
+    target_id: Option<String>,
+    /// Only entries created at or after this point in time
+    since: Option<DateTime<Utc>>,
+    /// Only entries created before this point in time
+    until: Option<DateTime<Utc>>,
+    /// The `next_cursor` of the previous page
+    cursor: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+    next_cursor: Option<u32>,
+}
+
+#[api_operation(
+    tag = "audit",
+    summary = "Browse the log of administrative changes, newest first",
+    description = "Every change to shifts, roles and the roles of users is logged with who made it and whether through the API, the web frontend or the command line tool. Entries can't be changed or deleted.",
+    security_scope(name = "session-id", scope = "ViewAuditLog",)
+)]
+pub async fn audit_list(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ViewAuditLog>>,
+    Query(filter): Query<AuditFilter>,
+) -> crate::Result<Json<AuditLog>> {
+    let limit = filter
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE) as u64;
+
+    let query = AuditQuery {
+        actor_id: resolve_user_id(filter.actor, &db).await?,
+        origin: filter.origin,
+        action: filter.action,
+        target_type: filter.target_type,
+        target_id: filter.target_id,
+        since: filter.since,
+        until: filter.until,
+        before_id: filter.cursor,
+        limit,
+    };
+
+    let entries = get_audit_entries(&query, &db).await.context(DatabaseErr)?;
+    let next_cursor = (entries.len() as u64 == limit)
+        .then(|| entries.last().map(|entry| entry.id))
+        .flatten();
+
+    Ok(Json(AuditLog {
+        entries,
+        next_cursor,
+    }))
+}
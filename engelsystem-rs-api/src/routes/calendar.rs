@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use actix_web::{
+    HttpResponse,
+    http::header::{CacheControl, CacheDirective, ContentType},
+    web::{Data, Json, Path},
+};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use engelsystem_rs_db::{
+    Database,
+    angel_type::get_all_angel_types,
+    calendar::{create_calendar_token, get_user_id_by_calendar_token, revoke_calendar_token},
+    location::get_all_locations,
+    shift::get_shifts_by_user,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use snafu::ResultExt;
+
+use crate::{
+    Error,
+    authorize_middleware::{BasicUser, RequireLogin},
+    generated::DatabaseErr,
+    utils::ical::{Calendar, Event},
+};
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct CalendarLink {
+    /// The secret token. It is only shown once.
+    pub token: String,
+    /// The path of the feed, relative to the API
+    pub path: String,
+}
+
+#[api_operation(
+    tag = "calendar",
+    summary = "Create a new link to the calendar feed of the logged in user",
+    description = "A previous link stops working.",
+    security_scope(name = "login",)
+)]
+pub async fn calendar_token_create(
+    db: Data<Database>,
+    user: BasicUser<RequireLogin>,
+) -> crate::Result<Json<CalendarLink>> {
+    let token = create_calendar_token(user.uid, &db)
+        .await
+        .context(DatabaseErr)?;
+
+    Ok(Json(CalendarLink {
+        path: format!("/calendar/{token}.ics"),
+        token,
+    }))
+}
+
+#[api_operation(
+    tag = "calendar",
+    summary = "Revoke the link to the calendar feed of the logged in user",
+    security_scope(name = "login",)
+)]
+pub async fn calendar_token_revoke(
+    db: Data<Database>,
+    user: BasicUser<RequireLogin>,
+) -> crate::Result<NoContent> {
+    revoke_calendar_token(user.uid, &db)
+        .await
+        .context(DatabaseErr)?;
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "calendar",
+    summary = "Get the shifts of a user as iCalendar feed",
+    description = "Authenticated by the secret token in the path instead of a session, so calendar apps can subscribe to it. Shifts the user signed off from or that were removed disappear from the feed, edited shifts keep their UID and get a higher SEQUENCE."
+)]
+pub async fn calendar_feed(db: Data<Database>, token: Path<String>) -> crate::Result<HttpResponse> {
+    let user_id = get_user_id_by_calendar_token(&token, &db)
+        .await
+        .context(DatabaseErr)?
+        .ok_or(Error::CalendarTokenNotFound)?;
+
+    let shifts = get_shifts_by_user(user_id, None, true, true, &db)
+        .await
+        .context(DatabaseErr)?;
+
+    let angel_types: HashMap<u32, String> = get_all_angel_types(&db)
+        .await
+        .context(DatabaseErr)?
+        .into_iter()
+        .map(|angel_type| (angel_type.id, angel_type.name))
+        .collect();
+
+    let locations: HashMap<u32, String> = get_all_locations(&db)
+        .await
+        .context(DatabaseErr)?
+        .into_iter()
+        .map(|location| (location.id, location.name))
+        .collect();
+
+    let mut calendar = Calendar::new("Engelsystem");
+
+    for shift in &shifts {
+        calendar.add_event(Event {
+            id: shift.id,
+            created_at: shift.created_at,
+            updated_at: shift.updated_at,
+            sequence: shift.sequence,
+            starts_at: shift.starts_at,
+            ends_at: shift.ends_at,
+            summary: &shift.name,
+            description: shift.description.as_deref(),
+            category: shift
+                .angel_type_id
+                .and_then(|id| angel_types.get(&id))
+                .map(String::as_str),
+            location: shift
+                .location_id
+                .and_then(|id| locations.get(&id))
+                .map(String::as_str),
+        });
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType(
+            "text/calendar; charset=utf-8"
+                .parse()
+                .expect("The calendar mime type is valid"),
+        ))
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .body(calendar.finish()))
+}
@@ -0,0 +1,160 @@
+use actix_web::{
+    HttpRequest,
+    web::{Data, Json, Path},
+};
+use apistos::{ApiComponent, api_operation};
+use engelsystem_rs_db::{
+    CompletionStatus, Database, Shift, ShiftCompletion,
+    completion::{CompletionEntry, complete_shift, get_shift_completions},
+    permission::PermissionType,
+    shift::get_shift_by_id,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use snafu::ResultExt;
+use uuid::Uuid;
+
+use crate::{
+    Error,
+    authorize_middleware::{BasicAuthTrait, BasicUser, has_permission, token_allows},
+    generated::DatabaseErr,
+    routes::ShiftSettings,
+    utils::{audit::request_actor, path::parse_uuid},
+};
+
+/// The shift of the `{shift_id}` path parameter of the request
+async fn requested_shift(req: &actix_web::HttpRequest) -> crate::Result<Shift> {
+    let shift_id = parse_uuid(
+        req.match_info()
+            .get("shift_id")
+            .expect("The route scope is missing a {shift_id} path parameter")
+            .to_string(),
+    )?;
+
+    let db = req
+        .app_data::<Data<Database>>()
+        .expect("The database is not registered as app data");
+
+    get_shift_by_id(shift_id, db)
+        .await
+        .context(DatabaseErr)?
+        .ok_or(Error::ShiftNotFound)
+}
+
+// To use this type of authentication, please specify a shift_id resource on the request.
+// The manager of that shift and users who may manage shifts are allowed through. API tokens of the
+// manager need the ManageShifts scope.
+pub struct ShiftManagerAuth {}
+
+impl BasicAuthTrait for ShiftManagerAuth {
+    async fn authenticate(
+        user: BasicUser<Self>,
+        req: actix_web::HttpRequest,
+    ) -> crate::Result<BasicUser<Self>> {
+        let shift = requested_shift(&req).await?;
+
+        if (shift.managed_by == Some(user.uid) && token_allows(PermissionType::ManageShifts, &req))
+            || has_permission(&user, PermissionType::ManageShifts, &req).await?
+        {
+            Ok(user)
+        } else {
+            Err(Error::SessionUnauthorized)
+        }
+    }
+}
+
+// To use this type of authentication, please specify a shift_id resource on the request.
+// The creator and manager of that shift and users who may manage shifts are allowed through. API
+// tokens of the creator or manager need the ManageShifts scope.
+pub struct ShiftEditorAuth {}
+
+impl BasicAuthTrait for ShiftEditorAuth {
+    async fn authenticate(
+        user: BasicUser<Self>,
+        req: actix_web::HttpRequest,
+    ) -> crate::Result<BasicUser<Self>> {
+        let shift = requested_shift(&req).await?;
+
+        let related = shift.created_by == user.uid || shift.managed_by == Some(user.uid);
+        if (related && token_allows(PermissionType::ManageShifts, &req))
+            || has_permission(&user, PermissionType::ManageShifts, &req).await?
+        {
+            Ok(user)
+        } else {
+            Err(Error::SessionUnauthorized)
+        }
+    }
+}
+
+fn map_completion_error(err: engelsystem_rs_db::Error) -> Error {
+    use engelsystem_rs_db::Error as DbError;
+
+    match err {
+        DbError::ShiftNotFound => Error::ShiftNotFound,
+        DbError::ShiftNotStarted => Error::ShiftNotStarted,
+        DbError::NotSignedUp => Error::NotSignedUp,
+        DbError::InvalidWorkedHours => Error::InvalidWorkedHours,
+        source => Error::Database { source },
+    }
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct CompletionData {
+    user_id: Uuid,
+    status: CompletionStatus,
+    /// The hours the angel was present, only for partial attendance
+    worked_hours: Option<f64>,
+}
+
+#[api_operation(
+    tag = "shift",
+    summary = "Get how the angels of a shift completed it",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_completions(
+    db: Data<Database>,
+    _user: BasicUser<ShiftManagerAuth>,
+    shift_id: Path<String>,
+) -> crate::Result<Json<Vec<ShiftCompletion>>> {
+    let completions = get_shift_completions(parse_uuid(shift_id.into_inner())?, &db)
+        .await
+        .context(DatabaseErr)?;
+
+    Ok(Json(completions))
+}
+
+#[api_operation(
+    tag = "shift",
+    summary = "Mark signed up angels as present, absent or partially present",
+    description = "Credits shift time and points to the angels. Angels that were already marked get their previous credit replaced, the previous marking is kept in the audit log.",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_complete(
+    req: HttpRequest,
+    db: Data<Database>,
+    settings: Data<ShiftSettings>,
+    user: BasicUser<ShiftManagerAuth>,
+    shift_id: Path<String>,
+    Json(entries): Json<Vec<CompletionData>>,
+) -> crate::Result<Json<Vec<ShiftCompletion>>> {
+    let entries = entries
+        .into_iter()
+        .map(|entry| CompletionEntry {
+            user_id: entry.user_id,
+            status: entry.status,
+            worked_hours: entry.worked_hours,
+        })
+        .collect();
+
+    let completions = complete_shift(
+        parse_uuid(shift_id.into_inner())?,
+        entries,
+        &request_actor(&req, user.uid),
+        &settings.credit_rules,
+        &db,
+    )
+    .await
+    .map_err(map_completion_error)?;
+
+    Ok(Json(completions))
+}
@@ -0,0 +1,151 @@
+use std::str::FromStr;
+
+use actix_web::web::{Data, Json};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use chrono::TimeDelta;
+use engelsystem_rs_db::{
+    DatabaseConnection, User,
+    email_verification::{create_email_verification_token, verify_email},
+    user::get_user_by_id,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use snafu::ResultExt;
+use tracing::info;
+
+use crate::{
+    Error,
+    authorize_middleware::{BasicUser, RequireLogin},
+    generated::{DatabaseErr, MailErr},
+    mail::Mailer,
+    render_mail,
+};
+
+/// What users that haven't confirmed their email yet are allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnverifiedAccountPolicy {
+    /// Unverified users can do everything verified users can
+    Allow,
+    /// Unverified users can log in, but not sign up for shifts
+    NoShifts,
+    /// Unverified users can't log in
+    NoLogin,
+}
+
+impl FromStr for UnverifiedAccountPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "no_shifts" => Ok(Self::NoShifts),
+            "no_login" => Ok(Self::NoLogin),
+            _ => Err(format!(
+                "Unknown policy for unverified accounts {s:?}, expected allow, no_shifts or no_login"
+            )),
+        }
+    }
+}
+
+pub struct EmailVerificationSettings {
+    /// How long the link in a verification mail can be used
+    pub token_ttl: TimeDelta,
+    pub policy: UnverifiedAccountPolicy,
+}
+
+impl EmailVerificationSettings {
+    /// Fails with [`Error::EmailNotVerified`] if the user may not log in before confirming their
+    /// email
+    pub(crate) fn check_login(&self, user: &User) -> crate::Result<()> {
+        if user.email_verified_at.is_none() && self.policy == UnverifiedAccountPolicy::NoLogin {
+            return Err(Error::EmailNotVerified);
+        }
+
+        Ok(())
+    }
+
+    /// Fails with [`Error::EmailNotVerified`] if the user may not sign up for shifts before
+    /// confirming their email
+    pub(crate) fn check_shift_signup(&self, user: &User) -> crate::Result<()> {
+        if user.email_verified_at.is_none() && self.policy != UnverifiedAccountPolicy::Allow {
+            return Err(Error::EmailNotVerified);
+        }
+
+        Ok(())
+    }
+}
+
+/// Creates a new verification token for the user and queues a mail with it
+pub(crate) async fn send_verification_mail(
+    user: &User,
+    settings: &EmailVerificationSettings,
+    mailer: &Mailer,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    let token = create_email_verification_token(user.id, settings.token_ttl, db)
+        .await
+        .context(DatabaseErr)?;
+
+    let mut mail = render_mail!(mailer, "email_verification", &user.email, [
+        "username" => &user.username,
+        "token" => &token,
+        "valid_hours" => &settings.token_ttl.num_hours()
+    ])
+    .context(MailErr)?;
+    mail.contains_secret = true;
+
+    mailer.queue(mail, db).await.context(DatabaseErr)
+}
+
+#[derive(Debug, Deserialize, JsonSchema, ApiComponent)]
+pub struct EmailVerificationConfirm {
+    /// The token from the verification mail
+    token: String,
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Confirm an email with the token from the verification mail",
+    description = "The token can only be used once"
+)]
+pub async fn email_verification_confirm(
+    db: Data<DatabaseConnection>,
+    Json(request): Json<EmailVerificationConfirm>,
+) -> crate::Result<NoContent> {
+    let user = verify_email(&request.token, &db)
+        .await
+        .map_err(|err| match err {
+            engelsystem_rs_db::Error::InvalidVerificationToken => Error::InvalidVerificationToken,
+            source => Error::Database { source },
+        })?;
+
+    info!("User {} verified their email", user.id);
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Send the verification mail again",
+    description = "Earlier verification links stop working",
+    security_scope(name = "login")
+)]
+pub async fn email_verification_resend(
+    db: Data<DatabaseConnection>,
+    mailer: Data<Mailer>,
+    settings: Data<EmailVerificationSettings>,
+    user: BasicUser<RequireLogin>,
+) -> crate::Result<NoContent> {
+    let user = get_user_by_id(user.uid, &db)
+        .await
+        .context(DatabaseErr)?
+        .ok_or(Error::SessionUnauthenticated)?;
+
+    if user.email_verified_at.is_some() {
+        return Err(Error::EmailAlreadyVerified);
+    }
+
+    send_verification_mail(&user, &settings, &mailer, &db).await?;
+
+    Ok(NoContent)
+}
@@ -0,0 +1,170 @@
+use actix_web::web::{Data, Json, Path, Query};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use engelsystem_rs_db::{
+    ActiveLocation, Database, Location,
+    location::{
+        LocationChanges, add_location, delete_location, get_all_locations, get_location_by_id,
+        update_location,
+    },
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use snafu::ResultExt;
+
+use crate::{
+    Error,
+    authorize_middleware::{
+        BasicGuestAuth, BasicUser, RequirePermission, permission::ManageLocations,
+    },
+    generated::DatabaseErr,
+    routes::shifts::{ShiftList, ShiftListFilter, list_shifts},
+    utils::nullable,
+};
+
+fn map_location_error(err: engelsystem_rs_db::Error) -> Error {
+    use engelsystem_rs_db::Error as DbError;
+
+    match err {
+        DbError::LocationNotFound => Error::LocationNotFound,
+        DbError::LocationExists => Error::LocationExists,
+        DbError::LocationInUse => Error::LocationInUse,
+        DbError::InvalidCoordinates => Error::InvalidCoordinates,
+        DbError::InvalidMapUrl => Error::InvalidMapUrl,
+        source => Error::Database { source },
+    }
+}
+
+#[api_operation(
+    tag = "location",
+    summary = "Get all locations",
+    security_scope(name = "session-id",)
+)]
+pub async fn location_list(
+    db: Data<Database>,
+    _user: BasicUser<BasicGuestAuth>,
+) -> crate::Result<Json<Vec<Location>>> {
+    let locations = get_all_locations(&db).await.context(DatabaseErr)?;
+
+    Ok(Json(locations))
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct NewLocation {
+    pub name: String,
+    pub description: Option<String>,
+    /// Has to be given together with the longitude
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Link to the location on a map or a site plan
+    pub map_url: Option<String>,
+}
+
+#[api_operation(
+    tag = "location",
+    summary = "Add a location",
+    security_scope(name = "session-id", scope = "ManageLocations",)
+)]
+pub async fn location_add(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageLocations>>,
+    Json(new): Json<NewLocation>,
+) -> crate::Result<Json<Location>> {
+    use engelsystem_rs_db::ActiveValue::*;
+
+    let location = ActiveLocation {
+        id: NotSet,
+        created_at: NotSet,
+        name: Set(new.name),
+        description: Set(new.description),
+        latitude: Set(new.latitude),
+        longitude: Set(new.longitude),
+        map_url: Set(new.map_url),
+    };
+
+    let location = add_location(location, &db)
+        .await
+        .map_err(map_location_error)?;
+
+    Ok(Json(location))
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct LocationUpdate {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub description: Option<Option<String>>,
+    /// `null` removes the coordinates, together with the longitude
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub latitude: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub longitude: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub map_url: Option<Option<String>>,
+}
+
+#[api_operation(
+    tag = "location",
+    summary = "Edit a location",
+    security_scope(name = "session-id", scope = "ManageLocations",)
+)]
+pub async fn location_update(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageLocations>>,
+    location_id: Path<u32>,
+    Json(update): Json<LocationUpdate>,
+) -> crate::Result<Json<Location>> {
+    let changes = LocationChanges {
+        name: update.name,
+        description: update.description,
+        latitude: update.latitude,
+        longitude: update.longitude,
+        map_url: update.map_url,
+    };
+
+    let location = update_location(location_id.into_inner(), changes, &db)
+        .await
+        .map_err(map_location_error)?;
+
+    Ok(Json(location))
+}
+
+#[api_operation(
+    tag = "location",
+    summary = "Delete a location that no shift uses anymore",
+    security_scope(name = "session-id", scope = "ManageLocations",)
+)]
+pub async fn location_delete(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageLocations>>,
+    location_id: Path<u32>,
+) -> crate::Result<NoContent> {
+    delete_location(location_id.into_inner(), &db)
+        .await
+        .map_err(map_location_error)?;
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "location",
+    summary = "Browse the shifts at a location",
+    description = "Takes the same filters as the list of all shifts, the location_id filter is replaced by the location in the path",
+    security_scope(name = "session-id",)
+)]
+pub async fn location_shifts(
+    db: Data<Database>,
+    _user: BasicUser<BasicGuestAuth>,
+    location_id: Path<u32>,
+    Query(mut filters): Query<ShiftListFilter>,
+) -> crate::Result<Json<ShiftList>> {
+    let location_id = location_id.into_inner();
+
+    get_location_by_id(location_id, &db)
+        .await
+        .context(DatabaseErr)?
+        .ok_or(Error::LocationNotFound)?;
+
+    filters.location_id = Some(location_id);
+
+    Ok(Json(list_shifts(filters, &db).await?))
+}
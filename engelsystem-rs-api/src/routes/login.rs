@@ -1,12 +1,25 @@
+use crate::authorize_middleware::{BasicUser, RequirePermission, permission::ViewLoginLockouts};
+use crate::generated::DatabaseErr;
+use crate::routes::email_verification::EmailVerificationSettings;
+use crate::utils::client_ip::TrustedProxies;
 use crate::utils::schema_impls::ZeroizingDef;
 use crate::{Error, utils::validation::*};
 use actix_session::Session;
-use actix_web::web::{Data, Json};
+use actix_web::{
+    HttpRequest,
+    http::header,
+    web::{Data, Json},
+};
 use apistos::{ApiComponent, actix::NoContent, api_operation};
-use engelsystem_rs_db::{DatabaseConnection, user::verify_user};
+use engelsystem_rs_db::{
+    DatabaseConnection, LoginFailure,
+    login_throttle::{LoginThrottle, attempt_login, get_login_lockouts},
+};
 use schemars::JsonSchema;
 use serde::Deserialize;
+use snafu::ResultExt;
 use tracing::info;
+use uuid::Uuid;
 use validator::Validate;
 use zeroize::Zeroizing;
 
@@ -19,22 +32,57 @@ pub struct LoginData {
     password: Zeroizing<String>,
 }
 
+/// The browser the user logs in with. The frontend forwards the one of the visitor.
+fn user_agent(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::USER_AGENT)
+        .and_then(|agent| agent.to_str().ok())
+}
+
 #[api_operation(
     tag = "account",
     summary = "Request to log in with the given credentials",
+    description = "Depending on the configuration, users that haven't confirmed their email yet can't log in. After too many failed logins for an account or from an address, logins are blocked for a while and fail with 429, no matter if the username exists.",
     skip_args = "session"
 )]
 pub async fn request_login(
+    req: HttpRequest,
     Json(data): Json<LoginData>,
     db: Data<DatabaseConnection>,
+    verification: Data<EmailVerificationSettings>,
+    throttle: Data<LoginThrottle>,
+    proxies: Data<TrustedProxies>,
     session: Session,
 ) -> crate::Result<NoContent> {
-    let user = verify_user(&data.username, &data.password, &db).await;
+    let ip = proxies.client_ip(&req).map(|ip| ip.to_string());
+    let user = attempt_login(
+        &data.username,
+        &data.password,
+        ip.as_deref(),
+        &throttle,
+        &db,
+    )
+    .await
+    .map_err(|err| match err {
+        engelsystem_rs_db::Error::LoginBlocked { until } => {
+            info!(
+                "Login of {:?} from {ip:?} is blocked until {until}",
+                data.username
+            );
+            Error::LoginBlocked
+        }
+        source => Error::Database { source },
+    })?;
 
     if let Some(user) = user {
+        verification.check_login(&user)?;
+
         session.clear();
         session.insert("user_id", user.id)?;
-        session.insert("role_id", user.role_id)?;
+        // Lets the user recognize and end this session later
+        session.insert("session_id", Uuid::new_v4())?;
+        session.insert("ip", &ip)?;
+        session.insert("user_agent", user_agent(&req))?;
 
         info!("User {:?} logged in successfully", user.username);
 
@@ -45,3 +93,18 @@ pub async fn request_login(
 
     Err(Error::LoginFailed)
 }
+
+#[api_operation(
+    tag = "account",
+    summary = "Get the accounts and addresses that are blocked from logging in because of failed logins",
+    description = "Blocks can be lifted with the command line tool",
+    security_scope(name = "session-id", scope = "ViewLoginLockouts",)
+)]
+pub async fn login_lockout_list(
+    db: Data<DatabaseConnection>,
+    _user: BasicUser<RequirePermission<ViewLoginLockouts>>,
+) -> crate::Result<Json<Vec<LoginFailure>>> {
+    let lockouts = get_login_lockouts(&db).await.context(DatabaseErr)?;
+
+    Ok(Json(lockouts))
+}
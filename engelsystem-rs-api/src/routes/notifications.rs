@@ -0,0 +1,100 @@
+use actix_web::web::{Data, Json, Path, Query};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use engelsystem_rs_db::{
+    Database, Notification,
+    notification::{
+        get_notifications_by_user, get_unread_notification_count, mark_all_notifications_read,
+        mark_notification_read,
+    },
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::{
+    Error,
+    authorize_middleware::{BasicGuestAuth, BasicUser, RequireLogin},
+    generated::DatabaseErr,
+    utils::path::parse_uuid,
+};
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct NotificationFilter {
+    /// Only return notifications that weren't read yet
+    #[serde(default)]
+    unread: bool,
+}
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct UnreadCount {
+    pub count: u64,
+}
+
+#[api_operation(
+    tag = "notification",
+    summary = "Get the notifications of the logged in user, newest first",
+    security_scope(name = "session-id",)
+)]
+pub async fn notification_list(
+    db: Data<Database>,
+    user: BasicUser<BasicGuestAuth>,
+    Query(filter): Query<NotificationFilter>,
+) -> crate::Result<Json<Vec<Notification>>> {
+    let notifications = get_notifications_by_user(user.uid, filter.unread, &db)
+        .await
+        .context(DatabaseErr)?;
+
+    Ok(Json(notifications))
+}
+
+#[api_operation(
+    tag = "notification",
+    summary = "Count the unread notifications of the logged in user",
+    security_scope(name = "session-id",)
+)]
+pub async fn notification_unread_count(
+    db: Data<Database>,
+    user: BasicUser<BasicGuestAuth>,
+) -> crate::Result<Json<UnreadCount>> {
+    let count = get_unread_notification_count(user.uid, &db)
+        .await
+        .context(DatabaseErr)?;
+
+    Ok(Json(UnreadCount { count }))
+}
+
+#[api_operation(
+    tag = "notification",
+    summary = "Mark a notification of the logged in user as read",
+    security_scope(name = "login",)
+)]
+pub async fn notification_read(
+    db: Data<Database>,
+    user: BasicUser<RequireLogin>,
+    notification_id: Path<String>,
+) -> crate::Result<NoContent> {
+    mark_notification_read(user.uid, parse_uuid(notification_id.into_inner())?, &db)
+        .await
+        .map_err(|err| match err {
+            engelsystem_rs_db::Error::NotificationNotFound => Error::NotificationNotFound,
+            source => Error::Database { source },
+        })?;
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "notification",
+    summary = "Mark all notifications of the logged in user as read",
+    security_scope(name = "login",)
+)]
+pub async fn notification_read_all(
+    db: Data<Database>,
+    user: BasicUser<RequireLogin>,
+) -> crate::Result<NoContent> {
+    mark_all_notifications_read(user.uid, &db)
+        .await
+        .context(DatabaseErr)?;
+
+    Ok(NoContent)
+}
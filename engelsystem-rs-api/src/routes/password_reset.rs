@@ -0,0 +1,118 @@
+use actix_web::web::{Data, Json};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use chrono::TimeDelta;
+use engelsystem_rs_db::{
+    DatabaseConnection,
+    password_reset::{
+        create_password_reset_token, delete_stale_password_reset_tokens, reset_password,
+    },
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use snafu::ResultExt;
+use tracing::info;
+use zeroize::Zeroizing;
+
+use crate::{
+    Error,
+    generated::{DatabaseErr, MailErr, SessionStoreErr},
+    mail::Mailer,
+    render_mail,
+    session_store::{SessionBackend, SessionIndex},
+    utils::{schema_impls::ZeroizingDef, validation::validate_password},
+};
+
+pub struct PasswordResetSettings {
+    /// How long the link in a password reset mail can be used
+    pub token_ttl: TimeDelta,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, ApiComponent)]
+pub struct PasswordResetRequest {
+    email: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, ApiComponent)]
+pub struct PasswordResetConfirm {
+    /// The token from the password reset mail
+    token: String,
+    #[serde(with = "ZeroizingDef::<String>")]
+    password: Zeroizing<String>,
+    #[serde(with = "ZeroizingDef::<String>")]
+    confirm_password: Zeroizing<String>,
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Request a mail to reset the password",
+    description = "If a user with the email exists, a link to set a new password is sent to it. Earlier links stop working. The response doesn't reveal whether the email is registered."
+)]
+pub async fn password_reset_request(
+    db: Data<DatabaseConnection>,
+    mailer: Data<Mailer>,
+    settings: Data<PasswordResetSettings>,
+    Json(request): Json<PasswordResetRequest>,
+) -> crate::Result<NoContent> {
+    delete_stale_password_reset_tokens(&db)
+        .await
+        .context(DatabaseErr)?;
+
+    let Some((user, token)) = create_password_reset_token(&request.email, settings.token_ttl, &db)
+        .await
+        .context(DatabaseErr)?
+    else {
+        return Ok(NoContent);
+    };
+
+    info!("User {} requested a password reset", user.id);
+
+    let mut mail = render_mail!(mailer, "password_reset", user.email, [
+        "username" => &user.username,
+        "token" => &token,
+        "valid_minutes" => &settings.token_ttl.num_minutes()
+    ])
+    .context(MailErr)?;
+    mail.contains_secret = true;
+
+    // Only queued, as waiting for the mail server would reveal that the email belongs to a user
+    mailer.queue(mail, &db).await.context(DatabaseErr)?;
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Set a new password with the token from a password reset mail",
+    description = "The token can only be used once. All sessions of the user are ended."
+)]
+pub async fn password_reset_confirm(
+    db: Data<DatabaseConnection>,
+    store: Data<SessionBackend>,
+    Json(request): Json<PasswordResetConfirm>,
+) -> crate::Result<NoContent> {
+    if request.password != request.confirm_password {
+        return Err(Error::PasswordMismatch);
+    }
+
+    validate_password(&request.password).map_err(|e| Error::InvalidPassword {
+        message: e
+            .message
+            .map_or_else(|| e.code.to_string(), |message| message.to_string()),
+    })?;
+
+    let user = reset_password(&request.token, &request.password, &db)
+        .await
+        .map_err(|err| match err {
+            engelsystem_rs_db::Error::InvalidResetToken => Error::InvalidResetToken,
+            source => Error::Database { source },
+        })?;
+    // The database store already lost the sessions with the password reset
+    store
+        .end_user_sessions(user.id)
+        .await
+        .context(SessionStoreErr)?;
+
+    info!("User {} reset their password", user.id);
+
+    Ok(NoContent)
+}
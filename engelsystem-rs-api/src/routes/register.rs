@@ -7,12 +7,13 @@ use engelsystem_rs_db::{
 use schemars::JsonSchema;
 use serde::Deserialize;
 use snafu::ResultExt;
-use tracing::info;
+use tracing::{error, info};
 use validator::Validate;
 use zeroize::Zeroizing;
 
+use crate::routes::email_verification::{EmailVerificationSettings, send_verification_mail};
 use crate::utils::{schema_impls::ZeroizingDef, validation::*};
-use crate::{Error, generated::DatabaseErr};
+use crate::{Error, generated::DatabaseErr, mail::Mailer};
 
 // TODO: Validate better
 #[derive(Debug, Deserialize, Validate, JsonSchema, ApiComponent)]
@@ -30,11 +31,14 @@ pub struct RegistrationData {
 
 #[api_operation(
     tag = "account",
-    summary = "Request to register a new user account"
+    summary = "Request to register a new user account",
+    description = "A link to confirm the email is sent to it"
 )]
 pub async fn request_register(
     Json(data): Json<RegistrationData>,
     db: Data<DatabaseConnection>,
+    mailer: Data<Mailer>,
+    verification: Data<EmailVerificationSettings>,
 ) -> crate::Result<NoContent> {
     let errors = data.validate().err().map(|e| {
         e.field_errors()
@@ -54,9 +58,19 @@ pub async fn request_register(
 
     info!("User {:?} registered", data.username);
 
-    match user::add_guest(data.username, data.email, &data.password, &db).await {
-        Ok(_) => Ok(NoContent),
-        Err(engelsystem_rs_db::Error::UserExists) => Err(Error::UserExists),
-        Err(e) => Err(e).context(DatabaseErr),
+    let user = match user::add_guest(data.username, data.email, &data.password, &db).await {
+        Ok(user) => user,
+        Err(engelsystem_rs_db::Error::UserExists) => return Err(Error::UserExists),
+        Err(e) => return Err(e).context(DatabaseErr),
+    };
+
+    // The account exists either way, the mail can be requested again after logging in
+    if let Err(e) = send_verification_mail(&user, &verification, &mailer, &db).await {
+        error!(
+            "Failed to send the verification mail for user {}: {e}",
+            user.id
+        );
     }
+
+    Ok(NoContent)
 }
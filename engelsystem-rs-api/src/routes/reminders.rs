@@ -0,0 +1,82 @@
+use actix_web::web::{Data, Json};
+use apistos::{ApiComponent, api_operation};
+use engelsystem_rs_db::reminder::{get_reminder_preference, set_reminder_preference};
+use engelsystem_rs_db::{DatabaseConnection, ReminderPreference};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error,
+    authorize_middleware::{BasicGuestAuth, BasicUser, RequireLogin},
+    reminders::ReminderSettings,
+};
+
+#[derive(Debug, Serialize, JsonSchema, ApiComponent)]
+pub struct ReminderPreferenceView {
+    /// Whether the user is reminded of their shifts
+    pub enabled: bool,
+    /// How many minutes before a shift starts the reminder is sent, unset for the default
+    pub lead_minutes: Option<u32>,
+    /// The lead time used when the user didn't choose one
+    pub default_lead_minutes: i64,
+}
+
+impl ReminderPreferenceView {
+    fn new(preference: ReminderPreference, settings: &ReminderSettings) -> Self {
+        Self {
+            enabled: preference.enabled,
+            lead_minutes: preference.lead_minutes,
+            default_lead_minutes: settings.default_lead.num_minutes(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema, ApiComponent)]
+pub struct ReminderPreferenceUpdate {
+    enabled: bool,
+    /// Between 5 minutes and 7 days. Unset to use the default.
+    lead_minutes: Option<u32>,
+}
+
+fn map_db_error(err: engelsystem_rs_db::Error) -> Error {
+    match err {
+        engelsystem_rs_db::Error::InvalidReminderLeadTime => Error::InvalidReminderLeadTime,
+        source => Error::Database { source },
+    }
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Get how the logged in user is reminded of their shifts",
+    security_scope(name = "session-id",)
+)]
+pub async fn reminder_preference_get(
+    db: Data<DatabaseConnection>,
+    settings: Data<ReminderSettings>,
+    user: BasicUser<BasicGuestAuth>,
+) -> crate::Result<Json<ReminderPreferenceView>> {
+    let preference = get_reminder_preference(user.uid, &db)
+        .await
+        .map_err(map_db_error)?;
+
+    Ok(Json(ReminderPreferenceView::new(preference, &settings)))
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Choose whether and how early the logged in user is reminded of their shifts",
+    description = "Shifts the user was already reminded of aren't reminded of again.",
+    security_scope(name = "login",)
+)]
+pub async fn reminder_preference_set(
+    db: Data<DatabaseConnection>,
+    settings: Data<ReminderSettings>,
+    user: BasicUser<RequireLogin>,
+    Json(request): Json<ReminderPreferenceUpdate>,
+) -> crate::Result<Json<ReminderPreferenceView>> {
+    let preference = set_reminder_preference(user.uid, request.enabled, request.lead_minutes, &db)
+        .await
+        .map_err(map_db_error)?;
+
+    Ok(Json(ReminderPreferenceView::new(preference, &settings)))
+}
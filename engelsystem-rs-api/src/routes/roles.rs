@@ -0,0 +1,170 @@
+use std::str::FromStr;
+
+use actix_web::{
+    HttpRequest,
+    web::{Data, Json, Path},
+};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use engelsystem_rs_db::{
+    Database, Role, RolePermissionView,
+    permission::PermissionType,
+    role::{
+        add_role, delete_role, get_all_roles, get_role_permission_views, rename_role,
+        set_role_permission,
+    },
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use snafu::ResultExt;
+
+use crate::{
+    Error,
+    authorize_middleware::{BasicUser, RequirePermission, permission::ManageRoles},
+    generated::DatabaseErr,
+    utils::audit::request_actor,
+};
+
+fn map_role_error(err: engelsystem_rs_db::Error, role_id: u32) -> Error {
+    use engelsystem_rs_db::Error as DbError;
+
+    match err {
+        DbError::RoleNotFound => Error::RoleNotFound { id: role_id },
+        DbError::RoleExists => Error::RoleExists,
+        DbError::RoleInUse => Error::RoleInUse,
+        DbError::RoleProtected => Error::RoleProtected,
+        source => Error::Database { source },
+    }
+}
+
+#[api_operation(
+    tag = "role",
+    summary = "Get all roles",
+    security_scope(name = "session-id", scope = "ManageRoles",)
+)]
+pub async fn role_list(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageRoles>>,
+) -> crate::Result<Json<Vec<Role>>> {
+    let roles = get_all_roles(&db).await.context(DatabaseErr)?;
+
+    Ok(Json(roles))
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct RoleName {
+    pub name: String,
+}
+
+#[api_operation(
+    tag = "role",
+    summary = "Add a role without any permissions",
+    security_scope(name = "session-id", scope = "ManageRoles",)
+)]
+pub async fn role_add(
+    req: HttpRequest,
+    db: Data<Database>,
+    user: BasicUser<RequirePermission<ManageRoles>>,
+    Json(new): Json<RoleName>,
+) -> crate::Result<Json<Role>> {
+    match add_role(new.name, &request_actor(&req, user.uid), &db).await {
+        Ok(role) => Ok(Json(role)),
+        Err(engelsystem_rs_db::Error::RoleExists) => Err(Error::RoleExists),
+        Err(e) => Err(e).context(DatabaseErr),
+    }
+}
+
+#[api_operation(
+    tag = "role",
+    summary = "Rename a role",
+    security_scope(name = "session-id", scope = "ManageRoles",)
+)]
+pub async fn role_rename(
+    req: HttpRequest,
+    db: Data<Database>,
+    user: BasicUser<RequirePermission<ManageRoles>>,
+    role_id: Path<u32>,
+    Json(update): Json<RoleName>,
+) -> crate::Result<Json<Role>> {
+    let role_id = role_id.into_inner();
+    let role = rename_role(role_id, update.name, &request_actor(&req, user.uid), &db)
+        .await
+        .map_err(|e| map_role_error(e, role_id))?;
+
+    Ok(Json(role))
+}
+
+#[api_operation(
+    tag = "role",
+    summary = "Delete a role that no user has anymore",
+    description = "The default roles Guest, User and Administrator can't be deleted",
+    security_scope(name = "session-id", scope = "ManageRoles",)
+)]
+pub async fn role_delete(
+    req: HttpRequest,
+    db: Data<Database>,
+    user: BasicUser<RequirePermission<ManageRoles>>,
+    role_id: Path<u32>,
+) -> crate::Result<NoContent> {
+    let role_id = role_id.into_inner();
+    delete_role(role_id, &request_actor(&req, user.uid), &db)
+        .await
+        .map_err(|e| map_role_error(e, role_id))?;
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "role",
+    summary = "Get all permissions and whether they are enabled for a role",
+    security_scope(name = "session-id", scope = "ManageRoles",)
+)]
+pub async fn role_permissions(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageRoles>>,
+    role_id: Path<u32>,
+) -> crate::Result<Json<Vec<RolePermissionView>>> {
+    let role_id = role_id.into_inner();
+    let permissions = get_role_permission_views(role_id, &db)
+        .await
+        .map_err(|e| map_role_error(e, role_id))?;
+
+    Ok(Json(permissions))
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct PermissionUpdate {
+    pub enabled: bool,
+}
+
+#[api_operation(
+    tag = "role",
+    summary = "Enable or disable a permission for a role",
+    security_scope(name = "session-id", scope = "ManageRoles",)
+)]
+pub async fn role_permission_set(
+    req: HttpRequest,
+    db: Data<Database>,
+    user: BasicUser<RequirePermission<ManageRoles>>,
+    path: Path<(u32, String)>,
+    Json(update): Json<PermissionUpdate>,
+) -> crate::Result<Json<Vec<RolePermissionView>>> {
+    let (role_id, permission) = path.into_inner();
+    let permission = PermissionType::from_str(&permission)
+        .map_err(|_| Error::PermissionNotFound { name: permission })?;
+
+    set_role_permission(
+        role_id,
+        permission,
+        update.enabled,
+        &request_actor(&req, user.uid),
+        &db,
+    )
+    .await
+    .map_err(|e| map_role_error(e, role_id))?;
+
+    let permissions = get_role_permission_views(role_id, &db)
+        .await
+        .map_err(|e| map_role_error(e, role_id))?;
+
+    Ok(Json(permissions))
+}
@@ -0,0 +1,159 @@
+use actix_session::Session;
+use actix_web::{
+    HttpRequest,
+    web::{Data, Json, Path},
+};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use chrono::{DateTime, Utc};
+use engelsystem_rs_db::{Database, session::record_user_sessions_ended};
+use schemars::JsonSchema;
+use serde::Serialize;
+use snafu::ResultExt;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    Error,
+    authorize_middleware::{
+        BasicGuestAuth, BasicUser, RequireLogin, RequirePermission, permission::EndUserSessions,
+    },
+    generated::{SessionDeserializeErr, SessionStoreErr},
+    session_store::{SessionBackend, SessionIndex},
+    utils::{audit::request_actor, path::parse_uuid},
+};
+
+/// A login of the user. The session key in the cookie is never shown.
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct SessionView {
+    /// Used to end the session
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_active_at: Option<DateTime<Utc>>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// Whether this is the session of the request
+    pub current: bool,
+}
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct EndedSessions {
+    pub count: u64,
+}
+
+/// The public id of the session of the request, as stored on login
+fn current_session_id(session: &Session) -> crate::Result<Option<Uuid>> {
+    session.get("session_id").context(SessionDeserializeErr)
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Get the active sessions of the logged in user, most recently used first",
+    description = "The address and browser are the ones the user logged in with",
+    security_scope(name = "session-id",),
+    skip_args = "session"
+)]
+pub async fn session_list(
+    store: Data<SessionBackend>,
+    user: BasicUser<BasicGuestAuth>,
+    session: Session,
+) -> crate::Result<Json<Vec<SessionView>>> {
+    let current = current_session_id(&session)?;
+    let sessions = store
+        .user_sessions(user.uid)
+        .await
+        .context(SessionStoreErr)?
+        .into_iter()
+        .map(|s| SessionView {
+            id: s.public_id,
+            created_at: s.created_at,
+            last_active_at: s.last_active_at,
+            ip: s.ip,
+            user_agent: s.user_agent,
+            current: Some(s.public_id) == current,
+        })
+        .collect();
+
+    Ok(Json(sessions))
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "End a session of the logged in user",
+    description = "Ending the current session is the same as logging out",
+    security_scope(name = "login",),
+    skip_args = "session"
+)]
+pub async fn session_revoke(
+    store: Data<SessionBackend>,
+    user: BasicUser<RequireLogin>,
+    session_id: Path<String>,
+    session: Session,
+) -> crate::Result<NoContent> {
+    let session_id = parse_uuid(session_id.into_inner())?;
+
+    if !store
+        .end_session(user.uid, session_id)
+        .await
+        .context(SessionStoreErr)?
+    {
+        return Err(Error::UserSessionNotFound);
+    }
+
+    if current_session_id(&session)? == Some(session_id) {
+        session.purge();
+    }
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "account",
+    summary = "Log the logged in user out everywhere, including the current session",
+    security_scope(name = "login",),
+    skip_args = "session"
+)]
+pub async fn session_revoke_all(
+    store: Data<SessionBackend>,
+    user: BasicUser<RequireLogin>,
+    session: Session,
+) -> crate::Result<Json<EndedSessions>> {
+    let count = store
+        .end_user_sessions(user.uid)
+        .await
+        .context(SessionStoreErr)?;
+    session.purge();
+
+    Ok(Json(EndedSessions { count }))
+}
+
+#[api_operation(
+    tag = "user",
+    summary = "Log a user out everywhere",
+    description = "The change is recorded in the audit log",
+    security_scope(name = "session-id", scope = "EndUserSessions",)
+)]
+pub async fn user_sessions_end(
+    req: HttpRequest,
+    db: Data<Database>,
+    store: Data<SessionBackend>,
+    user: BasicUser<RequirePermission<EndUserSessions>>,
+    user_id: Path<String>,
+) -> crate::Result<Json<EndedSessions>> {
+    let uid = user_id.into_inner();
+    let target = parse_uuid(uid.clone())?;
+
+    let count = store
+        .end_user_sessions(target)
+        .await
+        .context(SessionStoreErr)?;
+    record_user_sessions_ended(target, count, &request_actor(&req, user.uid), db.get_ref())
+        .await
+        .map_err(|err| match err {
+            engelsystem_rs_db::Error::UserNotFound => Error::UIDNotFound { uid },
+            source => Error::Database { source },
+        })?;
+
+    info!("Ended {count} sessions of user {target}");
+
+    Ok(Json(EndedSessions { count }))
+}
@@ -1,3 +1,4 @@
+use actix_session::Session;
 use actix_web::{
     Either,
     web::{Data, Json},
@@ -8,20 +9,40 @@ use apistos::{
     api_operation,
 };
 use engelsystem_rs_db::ActiveValue::*;
-use engelsystem_rs_db::{ActiveUser, DatabaseConnection, user::update_user};
+use engelsystem_rs_db::{
+    ActiveUser, DatabaseConnection,
+    user::{change_password, check_password, get_user_by_id, update_user},
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
+use tracing::{error, info};
+use zeroize::Zeroizing;
 
 use crate::{
-    authorize_middleware::{BasicGuestAuth, BasicUser},
-    generated::DatabaseErr,
+    Error,
+    authorize_middleware::{BasicUser, RequireLogin},
+    generated::{DatabaseErr, SessionStoreErr},
+    mail::Mailer,
+    routes::email_verification::{EmailVerificationSettings, send_verification_mail},
+    session_store::{SessionBackend, SessionIndex},
+    utils::validation::validate_password,
 };
 
+fn map_password_error(err: engelsystem_rs_db::Error) -> Error {
+    match err {
+        engelsystem_rs_db::Error::WrongPassword => Error::WrongPassword,
+        source => Error::Database { source },
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ApiComponent)]
 pub struct SettingsUpdateRequest {
     username: String,
     email: String,
+    /// Needed to change the password
+    current_password: Option<String>,
+    /// The new password. Empty or missing to keep the current one.
     password: Option<String>,
     confirm_password: Option<String>,
 }
@@ -29,13 +50,43 @@ pub struct SettingsUpdateRequest {
 #[api_operation(
     tag = "account",
     summary = "Update user settings",
-    security_scope(name = "session-id")
+    description = "Changing the password needs the current password and logs out all other sessions of the user. The current session gets a new id. A changed email has to be confirmed again.",
+    security_scope(name = "login"),
+    skip_args = "session"
 )]
 pub async fn update_settings(
     db: Data<DatabaseConnection>,
-    session: BasicUser<BasicGuestAuth>,
+    mailer: Data<Mailer>,
+    verification: Data<EmailVerificationSettings>,
+    store: Data<SessionBackend>,
+    user: BasicUser<RequireLogin>,
+    session: Session,
     Json(new): Json<SettingsUpdateRequest>,
 ) -> crate::Result<Either<AcceptedJson<()>, NoContent>> {
+    let new_password = new
+        .password
+        .filter(|password| !password.is_empty())
+        .map(Zeroizing::new);
+
+    // Checked before anything is changed, so a rejected password doesn't leave half applied
+    // settings behind
+    if let Some(password) = &new_password {
+        if new.confirm_password.as_deref() != Some(password.as_str()) {
+            return Err(Error::PasswordMismatch);
+        }
+
+        validate_password(password).map_err(|e| Error::InvalidPassword {
+            message: e
+                .message
+                .map_or_else(|| e.code.to_string(), |message| message.to_string()),
+        })?;
+
+        let current_password = new.current_password.as_deref().unwrap_or_default();
+        check_password(user.uid, current_password, &db)
+            .await
+            .map_err(map_password_error)?;
+    }
+
     let changed = ActiveUser {
         id: NotSet,
         created_at: NotSet,
@@ -46,13 +97,49 @@ pub async fn update_settings(
         role_id: NotSet,
         shift_time: NotSet,
         points: NotSet,
+        email_verified_at: NotSet,
     };
 
-    if update_user(session.uid, changed, &db)
+    let previous = get_user_by_id(user.uid, &db)
         .await
         .context(DatabaseErr)?
-        .is_some()
+        .ok_or(Error::SessionUnauthenticated)?;
+
+    let updated_user = update_user(user.uid, changed, &db)
+        .await
+        .context(DatabaseErr)?;
+    let mut updated = updated_user.is_some();
+
+    if let Some(updated_user) = updated_user
+        && updated_user.email != previous.email
+        && let Err(e) = send_verification_mail(&updated_user, &verification, &mailer, &db).await
     {
+        error!(
+            "Failed to send the verification mail for user {}: {e}",
+            user.uid
+        );
+    }
+
+    if let Some(password) = new_password {
+        let current_password = Zeroizing::new(new.current_password.unwrap_or_default());
+
+        change_password(user.uid, &current_password, &password, &db)
+            .await
+            .map_err(map_password_error)?;
+        // The database store already lost the sessions with the password change
+        store
+            .end_user_sessions(user.uid)
+            .await
+            .context(SessionStoreErr)?;
+
+        // All sessions of the user were ended, this one continues under a new id
+        session.renew();
+        updated = true;
+
+        info!("User {} changed their password", user.uid);
+    }
+
+    if updated {
         Ok(Either::Left(AcceptedJson(())))
     } else {
         Ok(Either::Right(NoContent))
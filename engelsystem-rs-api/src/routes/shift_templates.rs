@@ -0,0 +1,259 @@
+use actix_web::{
+    HttpRequest,
+    web::{Data, Json, Path},
+};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use chrono::{NaiveDate, NaiveTime};
+use engelsystem_rs_db::{
+    ActiveShiftTemplate, Database, Shift, ShiftTemplate,
+    shift_template::{
+        ShiftTemplateChanges, add_shift_template, delete_shift_template, expand_shift_template,
+        get_all_shift_templates, update_shift_template,
+    },
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::{
+    Error,
+    authorize_middleware::{BasicUser, RequirePermission, permission::ManageShifts},
+    generated::DatabaseErr,
+    routes::{
+        ShiftSettings,
+        shifts::{resolve_angel_type_id, resolve_user_id},
+    },
+    utils::{audit::request_actor, nullable},
+};
+
+fn map_template_error(err: engelsystem_rs_db::Error, template_id: u32) -> Error {
+    use engelsystem_rs_db::Error as DbError;
+
+    match err {
+        DbError::ShiftTemplateNotFound => Error::ShiftTemplateNotFound { id: template_id },
+        DbError::InvalidRecurrence => Error::InvalidRecurrence,
+        DbError::InvalidDateRange => Error::InvalidDateRange,
+        DbError::LocationNotFound => Error::LocationNotFound,
+        source => Error::Database { source },
+    }
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct NewShiftTemplate {
+    pub managed_by: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub angels_needed: u32,
+    pub angel_type: Option<String>,
+    pub location_id: Option<u32>,
+    /// The day of the first shift
+    pub starts_on: NaiveDate,
+    /// The last day a shift may be generated for
+    pub ends_on: Option<NaiveDate>,
+    /// 1 for a daily shift, 7 for a weekly one
+    #[serde(default = "one")]
+    pub interval_days: u32,
+    /// In the local time of the event
+    pub start_time: NaiveTime,
+    pub duration_minutes: u32,
+}
+
+#[api_operation(
+    tag = "shift_template",
+    summary = "Get all shift templates",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_template_list(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageShifts>>,
+) -> crate::Result<Json<Vec<ShiftTemplate>>> {
+    let templates = get_all_shift_templates(&db).await.context(DatabaseErr)?;
+
+    Ok(Json(templates))
+}
+
+#[api_operation(
+    tag = "shift_template",
+    summary = "Add a template for a recurring shift",
+    description = "No shifts are generated until the template is expanded over a date range",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_template_add(
+    db: Data<Database>,
+    user: BasicUser<RequirePermission<ManageShifts>>,
+    Json(new): Json<NewShiftTemplate>,
+) -> crate::Result<Json<ShiftTemplate>> {
+    use engelsystem_rs_db::ActiveValue::*;
+
+    let template = ActiveShiftTemplate {
+        id: NotSet,
+        created_at: NotSet,
+        created_by: Set(user.uid),
+        managed_by: Set(resolve_user_id(new.managed_by, &db).await?),
+        name: Set(new.name),
+        description: Set(new.description),
+        angels_needed: Set(new.angels_needed),
+        angel_type_id: Set(resolve_angel_type_id(new.angel_type, &db).await?),
+        location_id: Set(new.location_id),
+        starts_on: Set(new.starts_on),
+        ends_on: Set(new.ends_on),
+        interval_days: Set(new.interval_days),
+        start_time: Set(new.start_time),
+        duration_minutes: Set(new.duration_minutes),
+    };
+
+    let template = add_shift_template(template, &db)
+        .await
+        .map_err(|err| match err {
+            engelsystem_rs_db::Error::InvalidRecurrence => Error::InvalidRecurrence,
+            engelsystem_rs_db::Error::LocationNotFound => Error::LocationNotFound,
+            source => Error::Database { source },
+        })?;
+
+    Ok(Json(template))
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct ShiftTemplateUpdate {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub description: Option<Option<String>>,
+    pub angels_needed: Option<u32>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub angel_type: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub location_id: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub managed_by: Option<Option<String>>,
+    pub starts_on: Option<NaiveDate>,
+    /// `null` lets the template repeat without an end
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub ends_on: Option<Option<NaiveDate>>,
+    pub interval_days: Option<u32>,
+    pub start_time: Option<NaiveTime>,
+    pub duration_minutes: Option<u32>,
+}
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct ShiftTemplateUpdated {
+    template: ShiftTemplate,
+    /// Generated shifts that were changed to match the template
+    updated_shifts: u64,
+    /// Generated shifts that were deleted, because the template no longer occurs on their day
+    removed_shifts: u64,
+}
+
+#[api_operation(
+    tag = "shift_template",
+    summary = "Edit a shift template",
+    description = "The changes are pushed to the generated shifts that haven't started yet and that nobody signed up for",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_template_update(
+    req: HttpRequest,
+    db: Data<Database>,
+    settings: Data<ShiftSettings>,
+    user: BasicUser<RequirePermission<ManageShifts>>,
+    template_id: Path<u32>,
+    Json(update): Json<ShiftTemplateUpdate>,
+) -> crate::Result<Json<ShiftTemplateUpdated>> {
+    let template_id = template_id.into_inner();
+
+    let managed_by = match update.managed_by {
+        Some(name) => Some(resolve_user_id(name, &db).await?),
+        None => None,
+    };
+    let angel_type_id = match update.angel_type {
+        Some(angel_type) => Some(resolve_angel_type_id(angel_type, &db).await?),
+        None => None,
+    };
+
+    let changes = ShiftTemplateChanges {
+        name: update.name,
+        description: update.description,
+        angels_needed: update.angels_needed,
+        angel_type_id,
+        location_id: update.location_id,
+        managed_by,
+        starts_on: update.starts_on,
+        ends_on: update.ends_on,
+        interval_days: update.interval_days,
+        start_time: update.start_time,
+        duration_minutes: update.duration_minutes,
+    };
+
+    let (template, sync) = update_shift_template(
+        template_id,
+        changes,
+        settings.credit_rules.utc_offset,
+        &request_actor(&req, user.uid),
+        &db,
+    )
+    .await
+    .map_err(|e| map_template_error(e, template_id))?;
+
+    Ok(Json(ShiftTemplateUpdated {
+        template,
+        updated_shifts: sync.updated,
+        removed_shifts: sync.removed,
+    }))
+}
+
+#[api_operation(
+    tag = "shift_template",
+    summary = "Delete a shift template",
+    description = "The shifts generated from it are kept",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_template_delete(
+    db: Data<Database>,
+    _user: BasicUser<RequirePermission<ManageShifts>>,
+    template_id: Path<u32>,
+) -> crate::Result<NoContent> {
+    let template_id = template_id.into_inner();
+    delete_shift_template(template_id, &db)
+        .await
+        .map_err(|e| map_template_error(e, template_id))?;
+
+    Ok(NoContent)
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    /// Included in the range
+    pub until: NaiveDate,
+}
+
+#[api_operation(
+    tag = "shift_template",
+    summary = "Generate the shifts of a template for a date range",
+    description = "Days that already have a shift from this template are skipped. Returns the new shifts.",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_template_expand(
+    req: HttpRequest,
+    db: Data<Database>,
+    settings: Data<ShiftSettings>,
+    user: BasicUser<RequirePermission<ManageShifts>>,
+    template_id: Path<u32>,
+    Json(range): Json<DateRange>,
+) -> crate::Result<Json<Vec<Shift>>> {
+    let template_id = template_id.into_inner();
+    let shifts = expand_shift_template(
+        template_id,
+        range.from,
+        range.until,
+        settings.credit_rules.utc_offset,
+        &request_actor(&req, user.uid),
+        &db,
+    )
+    .await
+    .map_err(|e| map_template_error(e, template_id))?;
+
+    Ok(Json(shifts))
+}
@@ -1,21 +1,67 @@
-use actix_web::web::{Data, Json, Query};
-use apistos::{ApiComponent, api_operation};
-use chrono::{DateTime, Utc};
+use actix_web::{
+    HttpRequest,
+    web::{Data, Json, Path, Query},
+};
+use apistos::{ApiComponent, actix::NoContent, api_operation};
+use chrono::{DateTime, TimeDelta, Utc};
 use engelsystem_rs_db::{
-    ActiveShift, Database, Shift,
-    shift::{add_shift, get_shifts_by_user},
-    user::{get_angel_type_id_by_name, get_user_id_by_name},
+    ActiveShift, Database, Shift, ShiftView,
+    completion::CreditRules,
+    permission::PermissionType,
+    shift::{
+        OverlapPolicy, ShiftChanges, ShiftCursor, ShiftQuery, add_shift, delete_shift,
+        get_shift_views, get_shifts_by_user, sign_off_from_shift, sign_up_for_shift, update_shift,
+    },
+    shift_import::{ImportFormat, import_shifts},
+    user::{get_angel_type_id_by_name, get_user_by_id, get_user_id_by_name},
 };
 use schemars::JsonSchema;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use snafu::{OptionExt, ResultExt};
 use uuid::Uuid;
 
 use crate::{
-    authorize_middleware::{BasicAdminAuth, BasicGuestAuth, BasicUser},
+    Error,
+    authorize_middleware::{
+        BasicGuestAuth, BasicUser, RequirePermission, has_permission,
+        permission::{ManageShifts, SignUpForShifts},
+    },
     generated::{AngelTypeNotFoundErr, DatabaseErr, UserNotFoundErr},
+    routes::{ShiftEditorAuth, email_verification::EmailVerificationSettings},
+    utils::{audit::request_actor, nullable, path::parse_uuid},
 };
 
+#[derive(Debug, Clone)]
+pub struct ShiftSettings {
+    /// How long before the start of a shift angels are still allowed to sign off
+    pub signoff_cutoff: TimeDelta,
+    /// The minimum time between two shifts of the same angel
+    pub overlap_buffer: TimeDelta,
+    /// How completed shifts are credited to the angels
+    pub credit_rules: CreditRules,
+}
+
+fn map_shift_error(err: engelsystem_rs_db::Error) -> Error {
+    use engelsystem_rs_db::Error as DbError;
+
+    match err {
+        DbError::ShiftNotFound => Error::ShiftNotFound,
+        DbError::ShiftFull => Error::ShiftFull,
+        DbError::AlreadySignedUp => Error::AlreadySignedUp,
+        DbError::NotSignedUp => Error::NotSignedUp,
+        DbError::SignOffCutoffPassed => Error::SignOffCutoffPassed,
+        DbError::NotQualified => Error::NotQualified,
+        DbError::ShiftConflict { shift_id, name } => Error::ShiftConflict { shift_id, name },
+        DbError::InvalidShiftTime => Error::InvalidShiftTime,
+        DbError::LocationNotFound => Error::LocationNotFound,
+        DbError::ShiftAlreadyStarted => Error::ShiftAlreadyStarted,
+        source => Error::Database { source },
+    }
+}
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
 fn b_true() -> bool {
     true
 }
@@ -39,13 +85,122 @@ pub async fn shifts_self(
     user: BasicUser<BasicGuestAuth>,
     Query(filters): Query<ShiftFilter>,
 ) -> crate::Result<Json<Vec<Shift>>> {
-    let shifts = get_shifts_by_user(user.uid, filters.limit, filters.include_expired, filters.include_started, &db)
-        .await
-        .context(DatabaseErr)?;
+    let shifts = get_shifts_by_user(
+        user.uid,
+        filters.limit,
+        filters.include_expired,
+        filters.include_started,
+        &db,
+    )
+    .await
+    .context(DatabaseErr)?;
 
     Ok(Json(shifts))
 }
 
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct ShiftListFilter {
+    /// Only shifts that end after this point in time
+    from: Option<DateTime<Utc>>,
+    /// Only shifts that start before this point in time
+    until: Option<DateTime<Utc>>,
+    angel_type_id: Option<u32>,
+    pub(crate) location_id: Option<u32>,
+    /// Only shifts that still have free slots
+    #[serde(default)]
+    free_only: bool,
+    /// Search term for the shift name and description
+    search: Option<String>,
+    /// The `next_cursor` of the previous page
+    cursor: Option<String>,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct ShiftList {
+    shifts: Vec<ShiftView>,
+    next_cursor: Option<String>,
+}
+
+#[api_operation(
+    tag = "shift",
+    summary = "Browse all shifts with optional filters",
+    security_scope(name = "session-id",)
+)]
+pub async fn shift_list(
+    db: Data<Database>,
+    _user: BasicUser<BasicGuestAuth>,
+    Query(filters): Query<ShiftListFilter>,
+) -> crate::Result<Json<ShiftList>> {
+    Ok(Json(list_shifts(filters, &db).await?))
+}
+
+/// Gets one page of the shift list. Shared by the lists of all shifts and of the shifts at a
+/// location.
+pub(crate) async fn list_shifts(
+    filters: ShiftListFilter,
+    db: &Database,
+) -> crate::Result<ShiftList> {
+    let after = filters
+        .cursor
+        .map(|cursor| cursor.parse::<ShiftCursor>())
+        .transpose()
+        .map_err(|_| Error::InvalidCursor)?;
+
+    let query = ShiftQuery {
+        from: filters.from,
+        until: filters.until,
+        angel_type_id: filters.angel_type_id,
+        location_id: filters.location_id,
+        free_only: filters.free_only,
+        search: filters.search.filter(|s| !s.is_empty()),
+        after,
+        limit: filters
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE) as u64,
+    };
+
+    let page = get_shift_views(query, db).await.context(DatabaseErr)?;
+
+    Ok(ShiftList {
+        shifts: page.shifts,
+        next_cursor: page.next.map(|cursor| cursor.to_string()),
+    })
+}
+
+/// Looks up the id of a shift manager given by username
+pub(crate) async fn resolve_user_id(
+    name: Option<String>,
+    db: &Database,
+) -> crate::Result<Option<Uuid>> {
+    match name {
+        Some(name) => Ok(Some(
+            get_user_id_by_name(&name, db)
+                .await
+                .context(DatabaseErr)?
+                .context(UserNotFoundErr { name })?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Looks up the id of an angel type given by name
+pub(crate) async fn resolve_angel_type_id(
+    name: Option<String>,
+    db: &Database,
+) -> crate::Result<Option<u32>> {
+    match name {
+        Some(name) => Ok(Some(
+            get_angel_type_id_by_name(&name, db)
+                .await
+                .context(DatabaseErr)?
+                .context(AngelTypeNotFoundErr { name })?,
+        )),
+        None => Ok(None),
+    }
+}
+
 #[derive(Deserialize, JsonSchema, ApiComponent)]
 pub struct NewShift {
     pub managed_by: Option<String>,
@@ -55,35 +210,15 @@ pub struct NewShift {
     pub description: Option<String>,
     pub angels_needed: u32,
     pub angel_type: Option<String>,
+    pub location_id: Option<u32>,
 }
 
 impl NewShift {
     pub async fn prepare(self, created_by: Uuid, db: &Database) -> crate::Result<ActiveShift> {
         use engelsystem_rs_db::ActiveValue::*;
 
-        let managed_by = match self.managed_by {
-            Some(name) => Some(
-                get_user_id_by_name(&name, db)
-                    .await
-                    .context(DatabaseErr)?
-                    .context(UserNotFoundErr { name })?,
-            ),
-            None => None,
-        };
-
-        let angel_type = match self.angel_type {
-            Some(angel_type) => {
-                Some(
-                    get_angel_type_id_by_name(&angel_type, db)
-                        .await
-                        .context(DatabaseErr)?
-                        .context(AngelTypeNotFoundErr {
-                            name: angel_type,
-                        })?
-                )
-            }
-            None => None
-        };
+        let managed_by = resolve_user_id(self.managed_by, db).await?;
+        let angel_type = resolve_angel_type_id(self.angel_type, db).await?;
 
         Ok(ActiveShift {
             id: NotSet,
@@ -96,6 +231,9 @@ impl NewShift {
             description: Set(self.description),
             angels_needed: Set(self.angels_needed),
             angel_type_id: Set(angel_type),
+            location_id: Set(self.location_id),
+            sequence: NotSet,
+            updated_at: NotSet,
         })
     }
 }
@@ -103,17 +241,265 @@ impl NewShift {
 #[api_operation(
     tag = "shift",
     summary = "Add a shift",
-    security_scope(name = "session-id", scope = "admin",)
+    security_scope(name = "session-id", scope = "ManageShifts",)
 )]
 pub async fn shift_add(
+    req: HttpRequest,
     Json(shift): Json<NewShift>,
     db: Data<Database>,
-    user: BasicUser<BasicAdminAuth>,
+    user: BasicUser<RequirePermission<ManageShifts>>,
 ) -> crate::Result<Json<Shift>> {
-    let shifts = add_shift(shift.prepare(user.uid, &db).await?, &db)
-        .await
-        .context(DatabaseErr)?;
+    let shifts = add_shift(
+        shift.prepare(user.uid, &db).await?,
+        &request_actor(&req, user.uid),
+        db.get_ref(),
+    )
+    .await
+    .map_err(map_shift_error)?;
 
     Ok(Json(shifts))
 }
 
+fn default_import_format() -> String {
+    ImportFormat::Json.to_string()
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct ImportOptions {
+    /// `csv` or `json`
+    #[serde(default = "default_import_format")]
+    format: String,
+    /// Only validate the rows without adding any shifts
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct ImportRowError {
+    /// For CSV the line in the file, so the header is row 1. For JSON the position in the array,
+    /// starting at 1.
+    row: u64,
+    message: String,
+}
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct ImportReport {
+    rows: u64,
+    /// Nothing is added in a dry run or if any row has errors
+    imported: u64,
+    errors: Vec<ImportRowError>,
+}
+
+#[api_operation(
+    tag = "shift",
+    summary = "Add many shifts at once from a CSV or JSON file",
+    description = "The rows have the same fields as a new shift. CSV files need a header row. If any row is invalid no shift is added, and every error is reported with its row.",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_import(
+    req: HttpRequest,
+    db: Data<Database>,
+    user: BasicUser<RequirePermission<ManageShifts>>,
+    Query(options): Query<ImportOptions>,
+    body: String,
+) -> crate::Result<Json<ImportReport>> {
+    let format =
+        options
+            .format
+            .parse::<ImportFormat>()
+            .map_err(|_| Error::UnknownImportFormat {
+                format: options.format.clone(),
+            })?;
+
+    let report = import_shifts(
+        body.as_bytes(),
+        format,
+        user.uid,
+        options.dry_run,
+        &request_actor(&req, user.uid),
+        &db,
+    )
+    .await
+    .map_err(|err| match err {
+        engelsystem_rs_db::Error::InvalidImportFile { reason } => {
+            Error::InvalidImportFile { reason }
+        }
+        source => Error::Database { source },
+    })?;
+
+    Ok(Json(ImportReport {
+        rows: report.rows,
+        imported: report.imported,
+        errors: report
+            .errors
+            .into_iter()
+            .map(|error| ImportRowError {
+                row: error.row,
+                message: error.message,
+            })
+            .collect(),
+    }))
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct ShiftUpdate {
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub name: Option<String>,
+    /// `null` removes the description
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub description: Option<Option<String>>,
+    pub angels_needed: Option<u32>,
+    /// `null` allows every angel to sign up again
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub angel_type: Option<Option<String>>,
+    /// `null` removes the location
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub location_id: Option<Option<u32>>,
+    /// `null` removes the shift manager
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub managed_by: Option<Option<String>>,
+}
+
+impl ShiftUpdate {
+    pub async fn prepare(self, db: &Database) -> crate::Result<ShiftChanges> {
+        let managed_by = match self.managed_by {
+            Some(name) => Some(resolve_user_id(name, db).await?),
+            None => None,
+        };
+
+        let angel_type_id = match self.angel_type {
+            Some(angel_type) => Some(resolve_angel_type_id(angel_type, db).await?),
+            None => None,
+        };
+
+        Ok(ShiftChanges {
+            starts_at: self.starts_at,
+            ends_at: self.ends_at,
+            name: self.name,
+            description: self.description,
+            angels_needed: self.angels_needed,
+            angel_type_id,
+            location_id: self.location_id,
+            managed_by,
+        })
+    }
+}
+
+#[api_operation(
+    tag = "shift",
+    summary = "Edit a shift",
+    description = "Allowed for the creator and manager of the shift. Signed up angels are kept even if the shift now needs fewer angels, and every signed up angel gets a notification about the change.",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_update(
+    req: HttpRequest,
+    db: Data<Database>,
+    user: BasicUser<ShiftEditorAuth>,
+    shift_id: Path<String>,
+    Json(update): Json<ShiftUpdate>,
+) -> crate::Result<Json<ShiftView>> {
+    let changes = update.prepare(&db).await?;
+    let shift = update_shift(
+        parse_uuid(shift_id.into_inner())?,
+        changes,
+        &request_actor(&req, user.uid),
+        &db,
+    )
+    .await
+    .map_err(map_shift_error)?;
+
+    Ok(Json(shift))
+}
+
+#[api_operation(
+    tag = "shift",
+    summary = "Cancel a shift",
+    description = "Allowed for the creator and manager of the shift. Every signed up angel gets a notification about the cancellation. Shifts that already started or were credited to angels can't be cancelled, so the angels keep their shift time and points.",
+    security_scope(name = "session-id", scope = "ManageShifts",)
+)]
+pub async fn shift_delete(
+    req: HttpRequest,
+    db: Data<Database>,
+    user: BasicUser<ShiftEditorAuth>,
+    shift_id: Path<String>,
+) -> crate::Result<NoContent> {
+    delete_shift(
+        parse_uuid(shift_id.into_inner())?,
+        &request_actor(&req, user.uid),
+        &db,
+    )
+    .await
+    .map_err(map_shift_error)?;
+
+    Ok(NoContent)
+}
+
+#[derive(Deserialize, JsonSchema, ApiComponent)]
+pub struct SignUpOptions {
+    /// Sign up even if the shift overlaps with another one. Needs the ManageShifts permission.
+    #[serde(default)]
+    allow_overlap: bool,
+}
+
+#[api_operation(
+    tag = "shift",
+    summary = "Sign up for a shift with free slots",
+    description = "Shifts that overlap with another shift you signed up for are refused. Depending on the configuration, your email has to be confirmed.",
+    security_scope(name = "session-id", scope = "SignUpForShifts",)
+)]
+pub async fn shift_signup(
+    req: HttpRequest,
+    db: Data<Database>,
+    settings: Data<ShiftSettings>,
+    verification: Data<EmailVerificationSettings>,
+    user: BasicUser<RequirePermission<SignUpForShifts>>,
+    shift_id: Path<String>,
+    Query(options): Query<SignUpOptions>,
+) -> crate::Result<NoContent> {
+    let account = get_user_by_id(user.uid, &db)
+        .await
+        .context(DatabaseErr)?
+        .ok_or(Error::SessionUnauthenticated)?;
+    verification.check_shift_signup(&account)?;
+
+    let overlap = if options.allow_overlap {
+        if !has_permission(&user, PermissionType::ManageShifts, &req).await? {
+            return Err(Error::SessionUnauthorized);
+        }
+        OverlapPolicy::Allow
+    } else {
+        OverlapPolicy::Refuse {
+            buffer: settings.overlap_buffer,
+        }
+    };
+
+    sign_up_for_shift(parse_uuid(shift_id.into_inner())?, user.uid, overlap, &db)
+        .await
+        .map_err(map_shift_error)?;
+
+    Ok(NoContent)
+}
+
+#[api_operation(
+    tag = "shift",
+    summary = "Sign off from a shift you signed up for",
+    security_scope(name = "session-id", scope = "SignUpForShifts",)
+)]
+pub async fn shift_signoff(
+    db: Data<Database>,
+    settings: Data<ShiftSettings>,
+    user: BasicUser<RequirePermission<SignUpForShifts>>,
+    shift_id: Path<String>,
+) -> crate::Result<NoContent> {
+    sign_off_from_shift(
+        parse_uuid(shift_id.into_inner())?,
+        user.uid,
+        settings.signoff_cutoff,
+        &db,
+    )
+    .await
+    .map_err(map_shift_error)?;
+
+    Ok(NoContent)
+}
@@ -8,7 +8,10 @@ use schemars::JsonSchema;
 use serde::Serialize;
 use snafu::ResultExt;
 
-use crate::generated::DatabaseErr;
+use crate::{
+    generated::{DatabaseErr, SessionStoreErr},
+    session_store::{SessionBackend, SessionIndex},
+};
 
 #[derive(Serialize, JsonSchema, ApiComponent)]
 pub struct UserCountStats {
@@ -25,10 +28,22 @@ async fn fetch_user_count_stats(db: &DatabaseConnection) -> crate::Result<UserCo
     })
 }
 
-#[api_operation(
-    tag = "statistics",
-    summary = "Get global user count statistics"
-)]
+#[api_operation(tag = "statistics", summary = "Get global user count statistics")]
 pub async fn user_count(db: Data<DatabaseConnection>) -> crate::Result<Json<UserCountStats>> {
     Ok(Json(fetch_user_count_stats(&db).await?))
 }
+
+#[derive(Serialize, JsonSchema, ApiComponent)]
+pub struct SessionCountStats {
+    /// Sessions that haven't expired yet
+    live: u64,
+    /// Live sessions of logged in users
+    logged_in: u64,
+}
+
+#[api_operation(tag = "statistics", summary = "Get the number of live sessions")]
+pub async fn session_count(store: Data<SessionBackend>) -> crate::Result<Json<SessionCountStats>> {
+    let (live, logged_in) = store.live_count().await.context(SessionStoreErr)?;
+
+    Ok(Json(SessionCountStats { live, logged_in }))
+}
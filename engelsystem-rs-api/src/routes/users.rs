@@ -4,7 +4,7 @@ use actix_web::web::{self, Data, Json};
 use apistos::api_operation;
 use engelsystem_rs_db::{
     Database, UserView,
-    role::RoleType,
+    permission::PermissionType,
     user::{get_all_user_views, get_user_view_by_id},
 };
 use snafu::{OptionExt, ResultExt};
@@ -12,7 +12,10 @@ use uuid::Uuid;
 
 use crate::{
     Error,
-    authorize_middleware::{BasicAdminAuth, BasicAuthTrait, BasicGuestAuth, BasicUser},
+    authorize_middleware::{
+        BasicAuthTrait, BasicGuestAuth, BasicUser, RequirePermission, has_permission,
+        permission::ViewUsers, user_permissions,
+    },
     generated::{DatabaseErr, UIDNotFoundErr},
 };
 
@@ -30,9 +33,9 @@ impl BasicAuthTrait for UserViewAuth {
             .get("user_id")
             .expect("The route scope is missing a {user_id} path parameter");
 
-        // TODO: This is currently just a bogus check to see if the authorization design works.
-
-        if user.role == RoleType::Admin || user.uid.to_string() == user_id {
+        if user.uid.to_string() == user_id
+            || has_permission(&user, PermissionType::ViewUsers, &req).await?
+        {
             Ok(user)
         } else {
             Err(Error::SessionUnauthorized)
@@ -45,11 +48,11 @@ impl BasicAuthTrait for UserViewAuth {
 #[api_operation(
     tag = "user",
     summary = "Get a view of all users",
-    security_scope(name = "session-id", scope = "admin",)
+    security_scope(name = "session-id", scope = "ViewUsers",)
 )]
 pub async fn user_list(
     db: Data<Database>,
-    _user: BasicUser<BasicAdminAuth>,
+    _user: BasicUser<RequirePermission<ViewUsers>>,
 ) -> crate::Result<Json<Vec<UserView>>> {
     let users = get_all_user_views(&db).await.context(DatabaseErr)?;
 
@@ -97,3 +100,23 @@ pub async fn view_me(
         }
     }
 }
+
+#[api_operation(
+    tag = "user",
+    summary = "Get the names of the permissions of the logged in user, sorted by name",
+    description = "Requests with an API token only get the permissions of its scopes",
+    security_scope(name = "session-id",)
+)]
+pub async fn view_my_permissions(
+    req: actix_web::HttpRequest,
+    user: BasicUser<BasicGuestAuth>,
+) -> crate::Result<Json<Vec<&'static str>>> {
+    let mut permissions: Vec<_> = user_permissions(&user, &req)
+        .await?
+        .iter()
+        .map(PermissionType::name)
+        .collect();
+    permissions.sort_unstable();
+
+    Ok(Json(permissions))
+}
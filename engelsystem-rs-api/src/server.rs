@@ -1,23 +1,61 @@
-use std::{env, net::Ipv4Addr, process::exit};
+use std::{env, net::Ipv4Addr, path::PathBuf, process::exit, time::Duration};
 
+use crate::authorize_middleware::add_token_alternatives;
 use crate::error::generated::*;
+use crate::mail::{MailConfig, Mailer, TransportConfig, run_outbox};
+use crate::reminders::{ReminderSettings, run_reminders};
 use crate::routes::*;
-use crate::session_db::DbSessionStore;
+use crate::session_store::{SessionBackend, SessionStoreConfig};
+use crate::session_sweeper::{SessionSweepSettings, run_session_sweeper};
+use crate::utils::client_ip::TrustedProxies;
 use actix_session::SessionMiddleware;
-use actix_web::{App, HttpServer, cookie::Key, web::Data};
+use actix_session::config::{BrowserSession, TtlExtensionPolicy};
+use actix_web::{
+    App, HttpResponse, HttpServer,
+    body::{MessageBody, to_bytes},
+    cookie::Key,
+    dev::{ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    middleware::{Next, from_fn},
+    web::Data,
+};
 use apistos::{
     SwaggerUIConfig,
     app::{BuildConfig, OpenApiWrapper},
     info::Info,
     spec::Spec,
-    web::{ServiceConfig, get, post, put, resource, scope},
+    web::{ServiceConfig, delete, get, patch, post, put, resource, scope},
+};
+use chrono::TimeDelta;
+use engelsystem_rs_db::{
+    completion::CreditRules,
+    connect_and_migrate,
+    login_throttle::LoginThrottle,
+    mail_outbox::RetryPolicy,
+    reminder::{MAX_LEAD_TIME, MIN_LEAD_TIME},
+    shift_template::parse_utc_offset,
 };
-use engelsystem_rs_db::connect_and_migrate;
 use snafu::ResultExt;
 use tracing::warn;
 
+const OPENAPI_PATH: &str = "/openapi.json";
 const DEFAULT_DATABASE_URL: &str = "sqlite://meow.sqlite?mode=rwc";
 const DEFAULT_PORT: u16 = 8081;
+const DEFAULT_SIGNOFF_CUTOFF_HOURS: i64 = 3;
+const DEFAULT_SHIFT_OVERLAP_BUFFER_MINUTES: i64 = 0;
+const DEFAULT_PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+const DEFAULT_EMAIL_VERIFICATION_TTL_HOURS: i64 = 48;
+const DEFAULT_UNVERIFIED_ACCOUNT_POLICY: UnverifiedAccountPolicy =
+    UnverifiedAccountPolicy::NoShifts;
+const DEFAULT_MAIL_TRANSPORT: &str = "log";
+const DEFAULT_MAIL_FROM: &str = "Engelsystem <noreply@localhost>";
+const DEFAULT_PUBLIC_URL: &str = "http://127.0.0.1:8080";
+const DEFAULT_REMINDER_LEAD_MINUTES: i64 = 60;
+const DEFAULT_REMINDER_INTERVAL_SECONDS: u64 = 60;
+const DEFAULT_TRUSTED_PROXIES: &str = "127.0.0.1,::1";
+const DEFAULT_SESSION_SWEEP_INTERVAL_SECONDS: u64 = 15 * 60;
+const DEFAULT_SESSION_SWEEP_BATCH_SIZE: u64 = 500;
+const DEFAULT_SESSION_STORE: &str = "database";
 const SESSION_COOKIE_NAME: &str = "session-id";
 const DUMMY_SECRET_KEY: &[u8; 64] =
     b"7E8CDED394A2BC2EB3547B16F6C4259DFF4B8218BDA5DF224E27CE44AC999999";
@@ -27,6 +65,20 @@ struct ServerConfig {
     database_url: String,
     secret_key: Vec<u8>,
     port: u16,
+    signoff_cutoff: TimeDelta,
+    overlap_buffer: TimeDelta,
+    credit_rules: CreditRules,
+    password_reset_ttl: TimeDelta,
+    email_verification_ttl: TimeDelta,
+    unverified_account_policy: UnverifiedAccountPolicy,
+    mail: MailConfig,
+    mail_retry_policy: RetryPolicy,
+    reminder_lead: TimeDelta,
+    reminder_interval: Duration,
+    login_throttle: LoginThrottle,
+    trusted_proxies: TrustedProxies,
+    session_sweep: SessionSweepSettings,
+    session_store: SessionStoreConfig,
 }
 
 impl ServerConfig {
@@ -34,11 +86,39 @@ impl ServerConfig {
         let database_url = Self::get_database_url();
         let secret_key = Self::get_secret_key();
         let port = Self::get_port();
+        let signoff_cutoff = Self::get_signoff_cutoff();
+        let overlap_buffer = Self::get_overlap_buffer();
+        let credit_rules = Self::get_credit_rules();
+        let password_reset_ttl = Self::get_password_reset_ttl();
+        let email_verification_ttl = Self::get_email_verification_ttl();
+        let unverified_account_policy = Self::get_unverified_account_policy();
+        let mail = Self::get_mail_config();
+        let mail_retry_policy = Self::get_mail_retry_policy();
+        let reminder_lead = Self::get_reminder_lead();
+        let reminder_interval = Self::get_reminder_interval();
+        let login_throttle = Self::get_login_throttle();
+        let trusted_proxies = Self::get_trusted_proxies();
+        let session_sweep = Self::get_session_sweep_settings();
+        let session_store = Self::get_session_store();
 
         Self {
             database_url,
             secret_key,
             port,
+            signoff_cutoff,
+            overlap_buffer,
+            credit_rules,
+            password_reset_ttl,
+            email_verification_ttl,
+            unverified_account_policy,
+            mail,
+            mail_retry_policy,
+            reminder_lead,
+            reminder_interval,
+            login_throttle,
+            trusted_proxies,
+            session_sweep,
+            session_store,
         }
     }
 
@@ -73,21 +153,369 @@ impl ServerConfig {
             .and_then(|p| p.parse().ok())
             .unwrap_or(DEFAULT_PORT)
     }
+
+    fn get_signoff_cutoff() -> TimeDelta {
+        let hours = env::var("SIGNOFF_CUTOFF_HOURS")
+            .ok()
+            .and_then(|h| h.parse().ok())
+            .unwrap_or(DEFAULT_SIGNOFF_CUTOFF_HOURS);
+
+        TimeDelta::hours(hours)
+    }
+
+    fn get_overlap_buffer() -> TimeDelta {
+        let minutes = env::var("SHIFT_OVERLAP_BUFFER_MINUTES")
+            .ok()
+            .and_then(|m| m.parse().ok())
+            .unwrap_or(DEFAULT_SHIFT_OVERLAP_BUFFER_MINUTES);
+
+        TimeDelta::minutes(minutes)
+    }
+
+    fn get_credit_rules() -> CreditRules {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+
+        let defaults = CreditRules::default();
+
+        CreditRules {
+            night_start_hour: var("NIGHT_SHIFT_START_HOUR").unwrap_or(defaults.night_start_hour),
+            night_end_hour: var("NIGHT_SHIFT_END_HOUR").unwrap_or(defaults.night_end_hour),
+            night_multiplier: var("NIGHT_SHIFT_MULTIPLIER").unwrap_or(defaults.night_multiplier),
+            points_per_hour: var("POINTS_PER_HOUR").unwrap_or(defaults.points_per_hour),
+            utc_offset: env::var("EVENT_UTC_OFFSET_HOURS")
+                .ok()
+                .and_then(|hours| parse_utc_offset(&hours))
+                .unwrap_or(defaults.utc_offset),
+        }
+    }
+
+    fn get_password_reset_ttl() -> TimeDelta {
+        let minutes = env::var("PASSWORD_RESET_TTL_MINUTES")
+            .ok()
+            .and_then(|m| m.parse().ok())
+            .unwrap_or(DEFAULT_PASSWORD_RESET_TTL_MINUTES);
+
+        TimeDelta::minutes(minutes)
+    }
+
+    fn get_email_verification_ttl() -> TimeDelta {
+        let hours = env::var("EMAIL_VERIFICATION_TTL_HOURS")
+            .ok()
+            .and_then(|h| h.parse().ok())
+            .unwrap_or(DEFAULT_EMAIL_VERIFICATION_TTL_HOURS);
+
+        TimeDelta::hours(hours)
+    }
+
+    fn get_unverified_account_policy() -> UnverifiedAccountPolicy {
+        env::var("UNVERIFIED_ACCOUNT_POLICY")
+            .map(|policy| {
+                policy.parse().unwrap_or_else(|e| {
+                    warn!("{e}");
+                    exit(1);
+                })
+            })
+            .unwrap_or(DEFAULT_UNVERIFIED_ACCOUNT_POLICY)
+    }
+
+    fn get_mail_retry_policy() -> RetryPolicy {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+
+        let defaults = RetryPolicy::default();
+
+        RetryPolicy {
+            max_attempts: var("MAIL_MAX_ATTEMPTS").unwrap_or(defaults.max_attempts),
+            base_delay: var("MAIL_RETRY_DELAY_SECONDS")
+                .map(TimeDelta::seconds)
+                .unwrap_or(defaults.base_delay),
+            max_delay: defaults.max_delay,
+        }
+    }
+
+    /// Has to be within the lead times users can choose themselves
+    fn get_reminder_lead() -> TimeDelta {
+        let Ok(minutes) = env::var("REMINDER_LEAD_MINUTES") else {
+            return TimeDelta::minutes(DEFAULT_REMINDER_LEAD_MINUTES);
+        };
+
+        minutes
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .map(|minutes| TimeDelta::minutes(minutes.into()))
+            .filter(|lead| (MIN_LEAD_TIME..=MAX_LEAD_TIME).contains(lead))
+            .unwrap_or_else(|| {
+                warn!(
+                    "REMINDER_LEAD_MINUTES has to be between {} and {} minutes, got {minutes:?}",
+                    MIN_LEAD_TIME.num_minutes(),
+                    MAX_LEAD_TIME.num_minutes()
+                );
+                exit(1);
+            })
+    }
+
+    fn get_reminder_interval() -> Duration {
+        let seconds = env::var("REMINDER_INTERVAL_SECONDS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&s| s > 0)
+            .unwrap_or(DEFAULT_REMINDER_INTERVAL_SECONDS);
+
+        Duration::from_secs(seconds)
+    }
+
+    fn get_session_sweep_settings() -> SessionSweepSettings {
+        fn var(name: &str) -> Option<u64> {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&v| v > 0)
+        }
+
+        SessionSweepSettings {
+            interval: Duration::from_secs(
+                var("SESSION_SWEEP_INTERVAL_SECONDS")
+                    .unwrap_or(DEFAULT_SESSION_SWEEP_INTERVAL_SECONDS),
+            ),
+            batch_size: var("SESSION_SWEEP_BATCH_SIZE").unwrap_or(DEFAULT_SESSION_SWEEP_BATCH_SIZE),
+        }
+    }
+
+    fn get_session_store() -> SessionStoreConfig {
+        let name = env::var("SESSION_STORE").unwrap_or_else(|_| DEFAULT_SESSION_STORE.to_string());
+
+        SessionStoreConfig::new(&name, env::var("SESSION_RESP_URL").ok()).unwrap_or_else(|e| {
+            warn!("{e}");
+            exit(1);
+        })
+    }
+
+    fn get_login_throttle() -> LoginThrottle {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+
+        let defaults = LoginThrottle::default();
+
+        LoginThrottle {
+            max_account_failures: var("LOGIN_MAX_ACCOUNT_FAILURES")
+                .unwrap_or(defaults.max_account_failures),
+            max_ip_failures: var("LOGIN_MAX_IP_FAILURES").unwrap_or(defaults.max_ip_failures),
+            base_delay: var("LOGIN_BACKOFF_SECONDS")
+                .map(TimeDelta::seconds)
+                .unwrap_or(defaults.base_delay),
+            max_delay: defaults.max_delay,
+            lockout: var("LOGIN_LOCKOUT_MINUTES")
+                .map(TimeDelta::minutes)
+                .unwrap_or(defaults.lockout),
+        }
+    }
+
+    fn get_trusted_proxies() -> TrustedProxies {
+        let proxies =
+            env::var("TRUSTED_PROXIES").unwrap_or_else(|_| DEFAULT_TRUSTED_PROXIES.into());
+
+        TrustedProxies(
+            proxies
+                .split(',')
+                .map(str::trim)
+                .filter(|proxy| !proxy.is_empty())
+                .map(|proxy| {
+                    proxy.parse().unwrap_or_else(|_| {
+                        warn!("TRUSTED_PROXIES contains the invalid address {proxy:?}");
+                        exit(1);
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    fn get_mail_config() -> MailConfig {
+        let name = env::var("MAIL_TRANSPORT").unwrap_or_else(|_| {
+            warn!("No MAIL_TRANSPORT set. Mails are only logged and not sent.");
+            DEFAULT_MAIL_TRANSPORT.to_string()
+        });
+
+        let transport = TransportConfig::new(
+            &name,
+            env::var("SMTP_URL").ok(),
+            env::var("MAIL_DIR").ok().map(PathBuf::from),
+        )
+        .unwrap_or_else(|e| {
+            warn!("{e}");
+            exit(1);
+        });
+
+        MailConfig {
+            transport,
+            from: env::var("MAIL_FROM").unwrap_or_else(|_| DEFAULT_MAIL_FROM.to_string()),
+            public_url: env::var("PUBLIC_URL").unwrap_or_else(|_| DEFAULT_PUBLIC_URL.to_string()),
+        }
+    }
 }
 
 fn configure_routes(cfg: &mut ServiceConfig) {
     cfg.service(resource("/register").route(post().to(request_register)))
         .service(resource("/login").route(post().to(request_login)))
+        .service(resource("/login_lockouts").route(get().to(login_lockout_list)))
         .service(resource("/logout").route(get().to(request_logout)))
+        .service(resource("/password-reset/request").route(post().to(password_reset_request)))
+        .service(resource("/password-reset/confirm").route(post().to(password_reset_confirm)))
+        .service(
+            resource("/email-verification/confirm").route(post().to(email_verification_confirm)),
+        )
         .service(resource("/users").route(get().to(user_list)))
         .service(resource("/users/{user_id}").route(get().to(view_user)))
+        .service(resource("/users/{user_id}/sessions").route(delete().to(user_sessions_end)))
         .service(resource("/me").route(get().to(view_me)))
+        .service(resource("/me/permissions").route(get().to(view_my_permissions)))
+        .service(
+            resource("/me/sessions")
+                .route(get().to(session_list))
+                .route(delete().to(session_revoke_all)),
+        )
+        .service(resource("/me/sessions/{session_id}").route(delete().to(session_revoke)))
+        .service(
+            resource("/me/tokens")
+                .route(get().to(personal_token_list))
+                .route(post().to(personal_token_create)),
+        )
+        .service(resource("/me/tokens/{token_id}").route(delete().to(personal_token_revoke)))
+        .service(
+            resource("/tokens")
+                .route(get().to(service_token_list))
+                .route(post().to(service_token_create)),
+        )
+        .service(resource("/tokens/{token_id}").route(delete().to(service_token_revoke)))
+        .service(resource("/me/email-verification").route(post().to(email_verification_resend)))
+        .service(
+            resource("/me/reminders")
+                .route(get().to(reminder_preference_get))
+                .route(put().to(reminder_preference_set)),
+        )
+        .service(
+            resource("/me/calendar_token")
+                .route(put().to(calendar_token_create))
+                .route(delete().to(calendar_token_revoke)),
+        )
+        .service(
+            scope("/notifications")
+                .service(resource("/").route(get().to(notification_list)))
+                .service(resource("/unread_count").route(get().to(notification_unread_count)))
+                .service(resource("/read").route(post().to(notification_read_all)))
+                .service(resource("/{notification_id}/read").route(post().to(notification_read))),
+        )
+        .service(resource("/audit").route(get().to(audit_list)))
+        .service(resource("/calendar/{token}.ics").route(get().to(calendar_feed)))
         .service(resource("/stats/user_count").route(get().to(user_count)))
+        .service(resource("/stats/session_count").route(get().to(session_count)))
         .service(resource("/settings").route(post().to(update_settings)))
+        .service(
+            scope("/angel_types")
+                .service(
+                    resource("/")
+                        .route(get().to(angel_type_list))
+                        .route(put().to(angel_type_add)),
+                )
+                .service(resource("/me").route(get().to(angel_types_self)))
+                .service(
+                    resource("/{angel_type_id}")
+                        .route(patch().to(angel_type_update))
+                        .route(delete().to(angel_type_delete)),
+                )
+                .service(
+                    resource("/{angel_type_id}/membership")
+                        .route(post().to(angel_type_join))
+                        .route(delete().to(angel_type_leave)),
+                )
+                .service(resource("/{angel_type_id}/members").route(get().to(angel_type_members)))
+                .service(
+                    resource("/{angel_type_id}/members/{user_id}")
+                        .route(delete().to(angel_type_member_remove)),
+                )
+                .service(
+                    resource("/{angel_type_id}/members/{user_id}/confirm")
+                        .route(post().to(angel_type_member_confirm)),
+                )
+                .service(
+                    resource("/{angel_type_id}/members/{user_id}/supporter")
+                        .route(put().to(angel_type_member_supporter)),
+                ),
+        )
+        .service(
+            scope("/roles")
+                .service(
+                    resource("/")
+                        .route(get().to(role_list))
+                        .route(put().to(role_add)),
+                )
+                .service(
+                    resource("/{role_id}")
+                        .route(patch().to(role_rename))
+                        .route(delete().to(role_delete)),
+                )
+                .service(resource("/{role_id}/permissions").route(get().to(role_permissions)))
+                .service(
+                    resource("/{role_id}/permissions/{permission}")
+                        .route(put().to(role_permission_set)),
+                ),
+        )
+        .service(
+            scope("/locations")
+                .service(
+                    resource("/")
+                        .route(get().to(location_list))
+                        .route(put().to(location_add)),
+                )
+                .service(
+                    resource("/{location_id}")
+                        .route(patch().to(location_update))
+                        .route(delete().to(location_delete)),
+                )
+                .service(resource("/{location_id}/shifts").route(get().to(location_shifts))),
+        )
+        .service(
+            scope("/shift_templates")
+                .service(
+                    resource("/")
+                        .route(get().to(shift_template_list))
+                        .route(put().to(shift_template_add)),
+                )
+                .service(
+                    resource("/{template_id}")
+                        .route(patch().to(shift_template_update))
+                        .route(delete().to(shift_template_delete)),
+                )
+                .service(resource("/{template_id}/expand").route(post().to(shift_template_expand))),
+        )
         .service(
             scope("/shifts")
-                .service(resource("/").route(put().to(shift_add)))
-                .service(resource("/me").route(get().to(shifts_self))),
+                .service(
+                    resource("/")
+                        .route(get().to(shift_list))
+                        .route(put().to(shift_add)),
+                )
+                .service(resource("/me").route(get().to(shifts_self)))
+                .service(resource("/import").route(post().to(shift_import)))
+                .service(
+                    resource("/{shift_id}")
+                        .route(patch().to(shift_update))
+                        .route(delete().to(shift_delete)),
+                )
+                .service(
+                    resource("/{shift_id}/signup")
+                        .route(post().to(shift_signup))
+                        .route(delete().to(shift_signoff)),
+                )
+                .service(
+                    resource("/{shift_id}/completion")
+                        .route(get().to(shift_completions))
+                        .route(post().to(shift_complete)),
+                ),
         );
 }
 
@@ -100,34 +528,120 @@ fn api_spec() -> Spec {
         info: Info {
             title: "Engelsystem RS API".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
+            description: Some(
+                "Routes are authenticated with the `session-id` cookie from `/login`. Most \
+                 routes also accept an API token as `Authorization: Bearer <token>` instead, \
+                 except for changes to the account itself. A token only has the permissions of \
+                 its scopes that the role of its user has enabled."
+                    .to_string(),
+            ),
             ..Default::default()
         },
         ..Default::default()
     }
 }
 
+/// apistos only gives an operation the security requirement of its `BasicUser`, so the API token
+/// alternative is added to the spec while it's served
+async fn document_token_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let is_spec = req.path() == OPENAPI_PATH;
+    let res = next.call(req).await?;
+    if !is_spec || !res.status().is_success() {
+        return Ok(res.map_into_left_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let body = to_bytes(res.into_body())
+        .await
+        .map_err(|_| ErrorInternalServerError("Failed to read the OpenAPI spec"))?;
+    let mut spec: serde_json::Value = serde_json::from_slice(&body)?;
+    add_token_alternatives(&mut spec);
+
+    Ok(ServiceResponse::new(req, HttpResponse::Ok().json(spec)).map_into_right_body())
+}
+
 async fn start_server(
     config: ServerConfig,
     shared_db: Data<engelsystem_rs_db::Database>,
 ) -> crate::Result<()> {
+    let shift_settings = Data::new(ShiftSettings {
+        signoff_cutoff: config.signoff_cutoff,
+        overlap_buffer: config.overlap_buffer,
+        credit_rules: config.credit_rules.clone(),
+    });
+    let password_reset_settings = Data::new(PasswordResetSettings {
+        token_ttl: config.password_reset_ttl,
+    });
+    let email_verification_settings = Data::new(EmailVerificationSettings {
+        token_ttl: config.email_verification_ttl,
+        policy: config.unverified_account_policy,
+    });
+    let reminder_settings = Data::new(ReminderSettings {
+        default_lead: config.reminder_lead,
+        interval: config.reminder_interval,
+        utc_offset: config.credit_rules.utc_offset,
+    });
+    let login_throttle = Data::new(config.login_throttle.clone());
+    let trusted_proxies = Data::new(config.trusted_proxies.clone());
+    let mailer = Data::new(Mailer::from_config(&config.mail).context(MailErr)?);
+    let session_store = Data::new(
+        SessionBackend::from_config(&config.session_store, shared_db.clone())
+            .await
+            .context(SessionStoreErr)?,
+    );
+
+    actix_web::rt::spawn(run_outbox(
+        mailer.get_ref().clone(),
+        shared_db.get_ref().clone(),
+        config.mail_retry_policy.clone(),
+    ));
+    actix_web::rt::spawn(run_reminders(
+        mailer.get_ref().clone(),
+        shared_db.get_ref().clone(),
+        reminder_settings.get_ref().clone(),
+    ));
+    actix_web::rt::spawn(run_session_sweeper(
+        session_store.get_ref().clone(),
+        shared_db.get_ref().clone(),
+        config.login_throttle.clone(),
+        config.session_sweep.clone(),
+    ));
+
     HttpServer::new(move || {
         App::new()
             .document(api_spec())
             .wrap(
                 SessionMiddleware::builder(
-                    DbSessionStore::new(shared_db.clone()),
+                    session_store.get_ref().clone(),
                     Key::from(&config.secret_key),
                 )
                 .cookie_name(SESSION_COOKIE_NAME.to_string())
                 .cookie_content_security(actix_session::config::CookieContentSecurity::Signed)
+                // Keeps the last activity of sessions up to date
+                .session_lifecycle(
+                    BrowserSession::default()
+                        .state_ttl_extension_policy(TtlExtensionPolicy::OnEveryRequest),
+                )
                 .build(),
             )
             .app_data(shared_db.clone())
+            .app_data(shift_settings.clone())
+            .app_data(password_reset_settings.clone())
+            .app_data(email_verification_settings.clone())
+            .app_data(reminder_settings.clone())
+            .app_data(login_throttle.clone())
+            .app_data(trusted_proxies.clone())
+            .app_data(mailer.clone())
+            .app_data(session_store.clone())
             .configure(configure_routes)
             .build_with(
-                "/openapi.json",
+                OPENAPI_PATH,
                 BuildConfig::default().with(SwaggerUIConfig::new(&"swagger")),
             )
+            .wrap(from_fn(document_token_auth))
     })
     .bind((Ipv4Addr::UNSPECIFIED, config.port))
     .context(WebserverErr)?
@@ -4,12 +4,20 @@ use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, Upd
 use actix_web::{cookie::time::Duration, web::Data};
 use engelsystem_rs_db::{
     DatabaseConnection,
-    session::{delete_session, load_session, save_session, update_session, update_session_ttl},
+    session::{
+        delete_session, delete_user_session, delete_user_sessions, get_live_session_count,
+        get_user_sessions, load_session, prune_expired_sessions, save_session, update_session,
+        update_session_ttl,
+    },
 };
 use tracing::error;
+use uuid::Uuid;
 
-pub(crate) type SessionState = HashMap<String, String>;
+use crate::session_store::{SessionIndex, SessionInfo, SessionStoreError};
 
+pub type SessionState = HashMap<String, String>;
+
+#[derive(Clone)]
 pub struct DbSessionStore {
     db: Data<DatabaseConnection>,
 }
@@ -86,8 +94,11 @@ impl SessionStore for DbSessionStore {
         session_key: &SessionKey,
         ttl: &Duration,
     ) -> Result<(), anyhow::Error> {
+        use engelsystem_rs_db::session::SessionError as SE;
+
         match update_session_ttl(&self.db, session_key.as_ref(), ttl).await {
-            Ok(_) => Ok(()),
+            // Ended while the request was handled, e.g. by logging out everywhere
+            Ok(_) | Err(SE::SessionNotFound) => Ok(()),
             Err(e) => {
                 error!("Error when updating session TTL: {e}");
                 Err(e.into())
@@ -96,8 +107,11 @@ impl SessionStore for DbSessionStore {
     }
 
     async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        use engelsystem_rs_db::session::SessionError as SE;
+
         match delete_session(&self.db, session_key.as_ref()).await {
-            Ok(_) => Ok(()),
+            // Already gone, e.g. a renewed session after all sessions of the user were ended
+            Ok(_) | Err(SE::SessionNotFound) => Ok(()),
             Err(e) => {
                 error!("Error when deleting session: {e}");
                 Err(e.into())
@@ -105,3 +119,39 @@ impl SessionStore for DbSessionStore {
         }
     }
 }
+
+impl SessionIndex for DbSessionStore {
+    async fn user_sessions(&self, user_id: Uuid) -> Result<Vec<SessionInfo>, SessionStoreError> {
+        Ok(get_user_sessions(user_id, &self.db)
+            .await?
+            .into_iter()
+            .map(|session| SessionInfo {
+                public_id: session.public_id,
+                created_at: session.created_at,
+                last_active_at: session.last_active_at,
+                ip: session.ip,
+                user_agent: session.user_agent,
+            })
+            .collect())
+    }
+
+    async fn end_session(&self, user_id: Uuid, public_id: Uuid) -> Result<bool, SessionStoreError> {
+        match delete_user_session(user_id, public_id, &self.db).await {
+            Ok(()) => Ok(true),
+            Err(engelsystem_rs_db::Error::SessionNotFound) => Ok(false),
+            Err(source) => Err(source.into()),
+        }
+    }
+
+    async fn end_user_sessions(&self, user_id: Uuid) -> Result<u64, SessionStoreError> {
+        Ok(delete_user_sessions(user_id, self.db.get_ref()).await?)
+    }
+
+    async fn live_count(&self) -> Result<(u64, u64), SessionStoreError> {
+        Ok(get_live_session_count(&self.db).await?)
+    }
+
+    async fn prune(&self, batch_size: u64) -> Result<u64, SessionStoreError> {
+        Ok(prune_expired_sessions(batch_size, &self.db).await?)
+    }
+}
@@ -0,0 +1,304 @@
+mod memory;
+mod resp;
+
+use std::future::Future;
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::{cookie::time::Duration, web::Data};
+use chrono::{DateTime, Utc};
+use engelsystem_rs_db::{DatabaseConnection, session::session_state_value};
+use rand::{Rng, distr::Alphanumeric};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use uuid::Uuid;
+
+use crate::session_db::{DbSessionStore, SessionState};
+
+pub use memory::MemorySessionStore;
+pub use resp::{RespClient, RespSessionStore, RespValue};
+
+#[derive(Debug, Snafu)]
+#[snafu(context(suffix(Err)), visibility(pub(crate)))]
+pub enum SessionStoreError {
+    #[snafu(display("Unknown session store {name:?}, expected database, memory or resp"))]
+    UnknownStore { name: String },
+
+    #[snafu(transparent)]
+    Database { source: engelsystem_rs_db::Error },
+
+    #[snafu(display("Invalid RESP URL {url:?}, expected redis://[:password@]host[:port][/db]"))]
+    InvalidRespUrl { url: String },
+
+    #[snafu(display("Failed to connect to the session store at {address}: {source}"))]
+    Connect {
+        address: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Connection to the session store failed: {source}"))]
+    Io { source: std::io::Error },
+
+    #[snafu(display("The session store replied with an error: {message}"))]
+    Reply { message: String },
+
+    #[snafu(display("The session store didn't answer within {timeout:?}"))]
+    Timeout { timeout: std::time::Duration },
+
+    #[snafu(display("The session store sent an invalid reply: {reason}"))]
+    Protocol { reason: String },
+
+    #[snafu(display("A stored session couldn't be read or written: {source}"))]
+    Serialization { source: serde_json::Error },
+
+    #[snafu(display("The session was not found"))]
+    NotFound,
+}
+
+/// Where sessions are kept, chosen with `SESSION_STORE`
+#[derive(Debug, Clone)]
+pub enum SessionStoreConfig {
+    /// The `session` table of the database. The default, and the only store the command line
+    /// tool can reach.
+    Database,
+    /// A map in the memory of the API server. Sessions are lost on restart and aren't shared
+    /// between servers, so it's meant for tests and single-node setups.
+    Memory,
+    /// A server speaking the Redis protocol, for setups with several API servers
+    Resp {
+        /// A URL like `redis://:password@localhost:6379/0`
+        url: String,
+    },
+}
+
+impl SessionStoreConfig {
+    /// Parses the name of a store, with the settings the store needs
+    pub fn new(name: &str, resp_url: Option<String>) -> Result<Self, SessionStoreError> {
+        match name {
+            "database" => Ok(Self::Database),
+            "memory" => Ok(Self::Memory),
+            "resp" => Ok(Self::Resp {
+                url: resp_url.unwrap_or_else(|| "redis://127.0.0.1:6379".to_string()),
+            }),
+            _ => UnknownStoreErr { name }.fail(),
+        }
+    }
+}
+
+/// A login as shown to its user, independent of the store it is kept in
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub public_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_active_at: Option<DateTime<Utc>>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// What every store offers beyond [`SessionStore`], to let users and administrators manage logins
+pub trait SessionIndex {
+    /// The sessions of a user that haven't expired yet, most recently used first
+    fn user_sessions(
+        &self,
+        user_id: Uuid,
+    ) -> impl Future<Output = Result<Vec<SessionInfo>, SessionStoreError>>;
+
+    /// Ends a session of a user by its public id. Returns `false` if the user has no such session.
+    fn end_session(
+        &self,
+        user_id: Uuid,
+        public_id: Uuid,
+    ) -> impl Future<Output = Result<bool, SessionStoreError>>;
+
+    /// Ends all sessions of a user. Returns the amount of ended sessions.
+    fn end_user_sessions(
+        &self,
+        user_id: Uuid,
+    ) -> impl Future<Output = Result<u64, SessionStoreError>>;
+
+    /// The amount of sessions that haven't expired yet, and how many of them are logged in
+    fn live_count(&self) -> impl Future<Output = Result<(u64, u64), SessionStoreError>>;
+
+    /// Forgets expired sessions, at most `batch_size` per step. Returns the amount of forgotten
+    /// sessions.
+    fn prune(&self, batch_size: u64) -> impl Future<Output = Result<u64, SessionStoreError>>;
+}
+
+/// The configured session store. Cloning it shares the underlying store.
+#[derive(Clone)]
+pub enum SessionBackend {
+    Database(DbSessionStore),
+    Memory(MemorySessionStore),
+    Resp(RespSessionStore),
+}
+
+impl SessionBackend {
+    pub async fn from_config(
+        config: &SessionStoreConfig,
+        db: Data<DatabaseConnection>,
+    ) -> Result<Self, SessionStoreError> {
+        Ok(match config {
+            SessionStoreConfig::Database => Self::Database(DbSessionStore::new(db)),
+            SessionStoreConfig::Memory => Self::Memory(MemorySessionStore::default()),
+            SessionStoreConfig::Resp { url } => Self::Resp(RespSessionStore::connect(url).await?),
+        })
+    }
+}
+
+/// Calls the same method on whichever store is configured
+macro_rules! dispatch {
+    ($self:ident.$method:ident($($arg:expr),*)) => {
+        match $self {
+            Self::Database(store) => store.$method($($arg),*).await,
+            Self::Memory(store) => store.$method($($arg),*).await,
+            Self::Resp(store) => store.$method($($arg),*).await,
+        }
+    };
+}
+
+impl SessionStore for SessionBackend {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        dispatch!(self.load(session_key))
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        dispatch!(self.save(session_state, ttl))
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        dispatch!(self.update(session_key, session_state, ttl))
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        dispatch!(self.update_ttl(session_key, ttl))
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        dispatch!(self.delete(session_key))
+    }
+}
+
+impl SessionIndex for SessionBackend {
+    async fn user_sessions(&self, user_id: Uuid) -> Result<Vec<SessionInfo>, SessionStoreError> {
+        dispatch!(self.user_sessions(user_id))
+    }
+
+    async fn end_session(&self, user_id: Uuid, public_id: Uuid) -> Result<bool, SessionStoreError> {
+        dispatch!(self.end_session(user_id, public_id))
+    }
+
+    async fn end_user_sessions(&self, user_id: Uuid) -> Result<u64, SessionStoreError> {
+        dispatch!(self.end_user_sessions(user_id))
+    }
+
+    async fn live_count(&self) -> Result<(u64, u64), SessionStoreError> {
+        dispatch!(self.live_count())
+    }
+
+    async fn prune(&self, batch_size: u64) -> Result<u64, SessionStoreError> {
+        dispatch!(self.prune(batch_size))
+    }
+}
+
+/// A session as the memory and RESP stores keep it. The database store has its own table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredSession {
+    state: SessionState,
+    created_at: DateTime<Utc>,
+    last_active_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    /// The logged in user, `None` for anonymous sessions
+    user_id: Option<Uuid>,
+    public_id: Uuid,
+    ip: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl StoredSession {
+    /// Reads the metadata the API stores on login from the state, like the database store does
+    fn new(state: SessionState, ttl: &Duration) -> Self {
+        let now = Utc::now();
+        let mut session = Self {
+            state: SessionState::new(),
+            created_at: now,
+            last_active_at: now,
+            expires_at: now,
+            user_id: None,
+            public_id: session_state_value(&state, "session_id").unwrap_or_else(Uuid::new_v4),
+            ip: None,
+            user_agent: None,
+        };
+        session.update(state, ttl);
+        session
+    }
+
+    fn update(&mut self, state: SessionState, ttl: &Duration) {
+        self.user_id = session_state_value(&state, "user_id");
+        // Set on login, which can happen in an existing anonymous session
+        if let Some(public_id) = session_state_value(&state, "session_id") {
+            self.public_id = public_id;
+        }
+        if let Some(ip) = session_state_value(&state, "ip") {
+            self.ip = Some(ip);
+        }
+        if let Some(user_agent) = session_state_value(&state, "user_agent") {
+            self.user_agent = Some(user_agent);
+        }
+        self.state = state;
+        self.touch(ttl);
+    }
+
+    /// Marks the session as active and extends its lifetime
+    fn touch(&mut self, ttl: &Duration) {
+        let now = Utc::now();
+        self.last_active_at = now;
+        self.expires_at = now + chrono::Duration::seconds(ttl.whole_seconds());
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    fn info(&self) -> SessionInfo {
+        SessionInfo {
+            public_id: self.public_id,
+            created_at: self.created_at,
+            last_active_at: Some(self.last_active_at),
+            ip: self.ip.clone(),
+            user_agent: self.user_agent.clone(),
+        }
+    }
+}
+
+/// A new random session key, as long as the ones of the database store
+fn generate_session_key() -> SessionKey {
+    let key: String = rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(256)
+        .map(char::from)
+        .collect();
+
+    key.try_into()
+        .expect("256 characters are within the limit of session keys")
+}
+
+/// Orders sessions like [`SessionIndex::user_sessions`] promises
+fn sort_by_activity(sessions: &mut [SessionInfo]) {
+    sessions.sort_by(|a, b| {
+        b.last_active_at
+            .cmp(&a.last_active_at)
+            .then(b.created_at.cmp(&a.created_at))
+    });
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration;
+use uuid::Uuid;
+
+use super::{
+    SessionIndex, SessionInfo, SessionStoreError, StoredSession, generate_session_key,
+    sort_by_activity,
+};
+use crate::session_db::SessionState;
+
+/// Keeps sessions in the memory of the API server. Clones share the same sessions.
+#[derive(Clone, Default)]
+pub struct MemorySessionStore {
+    sessions: Arc<Mutex<HashMap<String, StoredSession>>>,
+}
+
+impl MemorySessionStore {
+    fn sessions(&self) -> MutexGuard<'_, HashMap<String, StoredSession>> {
+        // A panic while holding the lock can't leave the map half changed
+        self.sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let mut sessions = self.sessions();
+
+        match sessions.get(session_key.as_ref()) {
+            Some(session) if session.is_expired() => {
+                sessions.remove(session_key.as_ref());
+                Ok(None)
+            }
+            Some(session) => Ok(Some(session.state.clone())),
+            None => Ok(None),
+        }
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let session_key = generate_session_key();
+        self.sessions().insert(
+            session_key.as_ref().to_string(),
+            StoredSession::new(session_state, ttl),
+        );
+
+        Ok(session_key)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        match self.sessions().get_mut(session_key.as_ref()) {
+            Some(session) => session.update(session_state, ttl),
+            None => Err(UpdateError::Other(SessionStoreError::NotFound.into()))?,
+        }
+
+        Ok(session_key)
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        // A session that is already gone was ended while the request was handled
+        if let Some(session) = self.sessions().get_mut(session_key.as_ref()) {
+            session.touch(ttl);
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        self.sessions().remove(session_key.as_ref());
+
+        Ok(())
+    }
+}
+
+impl SessionIndex for MemorySessionStore {
+    async fn user_sessions(&self, user_id: Uuid) -> Result<Vec<SessionInfo>, SessionStoreError> {
+        let mut sessions: Vec<_> = self
+            .sessions()
+            .values()
+            .filter(|session| session.user_id == Some(user_id) && !session.is_expired())
+            .map(StoredSession::info)
+            .collect();
+        sort_by_activity(&mut sessions);
+
+        Ok(sessions)
+    }
+
+    async fn end_session(&self, user_id: Uuid, public_id: Uuid) -> Result<bool, SessionStoreError> {
+        let mut sessions = self.sessions();
+        let before = sessions.len();
+        sessions.retain(|_, session| {
+            session.user_id != Some(user_id) || session.public_id != public_id
+        });
+
+        Ok(sessions.len() < before)
+    }
+
+    async fn end_user_sessions(&self, user_id: Uuid) -> Result<u64, SessionStoreError> {
+        let mut sessions = self.sessions();
+        let before = sessions.len();
+        sessions.retain(|_, session| session.user_id != Some(user_id));
+
+        Ok((before - sessions.len()) as u64)
+    }
+
+    async fn live_count(&self) -> Result<(u64, u64), SessionStoreError> {
+        let sessions = self.sessions();
+        let live = sessions.values().filter(|session| !session.is_expired());
+
+        Ok((
+            live.clone().count() as u64,
+            live.filter(|session| session.user_id.is_some()).count() as u64,
+        ))
+    }
+
+    async fn prune(&self, _batch_size: u64) -> Result<u64, SessionStoreError> {
+        // Removing from a map is cheap enough to do all at once
+        let mut sessions = self.sessions();
+        let before = sessions.len();
+        sessions.retain(|_, session| !session.is_expired());
+
+        Ok((before - sessions.len()) as u64)
+    }
+}
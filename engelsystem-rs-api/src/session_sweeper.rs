@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use engelsystem_rs_db::{
+    DatabaseConnection,
+    login_throttle::{LoginThrottle, delete_stale_login_failures},
+};
+use tracing::{error, info};
+
+use crate::session_store::{SessionBackend, SessionIndex};
+
+#[derive(Debug, Clone)]
+pub struct SessionSweepSettings {
+    /// How often expired sessions are deleted
+    pub interval: Duration,
+    /// How many sessions are deleted per statement
+    pub batch_size: u64,
+}
+
+/// Deletes expired sessions for as long as the server runs. Expired sessions are otherwise only
+/// deleted when their cookie is used again. Failed logins that no longer count are removed as
+/// well.
+pub async fn run_session_sweeper(
+    store: SessionBackend,
+    db: DatabaseConnection,
+    throttle: LoginThrottle,
+    settings: SessionSweepSettings,
+) {
+    loop {
+        match store.prune(settings.batch_size).await {
+            Ok(0) => {}
+            Ok(deleted) => info!("Deleted {deleted} expired sessions"),
+            Err(e) => error!("Failed to delete expired sessions: {e}"),
+        }
+
+        match delete_stale_login_failures(&throttle, &db).await {
+            Ok(0) => {}
+            Ok(deleted) => info!("Forgot the failed logins of {deleted} accounts and addresses"),
+            Err(e) => error!("Failed to delete stale login failures: {e}"),
+        }
+
+        tokio::time::sleep(settings.interval).await;
+    }
+}
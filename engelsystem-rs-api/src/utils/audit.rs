@@ -0,0 +1,30 @@
+use actix_web::{HttpRequest, web::Data};
+use engelsystem_rs_db::{AuditOrigin, audit::Actor};
+use uuid::Uuid;
+
+use crate::utils::client_ip::TrustedProxies;
+
+/// Header the frontend sends with its requests, so its changes are logged as made through the web
+pub const CLIENT_HEADER: &str = "x-engelsystem-client";
+
+/// The actor for the audit log of a change made by `user_id` through this request. The client
+/// header is only believed from trusted proxies, anyone else could claim to be the frontend.
+pub fn request_actor(req: &HttpRequest, user_id: Uuid) -> Actor {
+    let trusted = req
+        .app_data::<Data<TrustedProxies>>()
+        .is_some_and(|proxies| proxies.is_trusted(req));
+    let from_web = trusted
+        && req
+            .headers()
+            .get(CLIENT_HEADER)
+            .is_some_and(|client| client == "web");
+
+    Actor::new(
+        user_id,
+        if from_web {
+            AuditOrigin::Web
+        } else {
+            AuditOrigin::Api
+        },
+    )
+}
@@ -0,0 +1,34 @@
+use std::net::IpAddr;
+
+use actix_web::HttpRequest;
+
+/// The proxies whose `X-Forwarded-For` header is believed, e.g. the frontend, which sends the
+/// logins of its visitors
+#[derive(Debug, Clone)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+impl TrustedProxies {
+    /// Whether the request was sent by a trusted proxy or from the same host
+    pub fn is_trusted(&self, req: &HttpRequest) -> bool {
+        req.peer_addr()
+            .is_some_and(|peer| peer.ip().is_loopback() || self.0.contains(&peer.ip()))
+    }
+
+    /// The address the request came from. For requests from a trusted proxy that's the last
+    /// address the proxy added to `X-Forwarded-For`, otherwise the peer of the connection.
+    pub fn client_ip(&self, req: &HttpRequest) -> Option<IpAddr> {
+        let peer = req.peer_addr()?.ip();
+        if !self.0.contains(&peer) {
+            return Some(peer);
+        }
+
+        let forwarded = req
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+
+        Some(forwarded.unwrap_or(peer))
+    }
+}
@@ -0,0 +1,216 @@
+//! Minimal iCalendar (RFC 5545) writer for the calendar feed
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+const MAX_LINE_OCTETS: usize = 75;
+
+pub struct Event<'a> {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub sequence: u32,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub summary: &'a str,
+    pub description: Option<&'a str>,
+    pub category: Option<&'a str>,
+    pub location: Option<&'a str>,
+}
+
+pub struct Calendar {
+    content: String,
+    stamp: DateTime<Utc>,
+}
+
+impl Calendar {
+    pub fn new(name: &str) -> Self {
+        let mut calendar = Calendar {
+            content: String::new(),
+            stamp: Utc::now(),
+        };
+
+        calendar.line("BEGIN:VCALENDAR");
+        calendar.line("VERSION:2.0");
+        calendar.line("PRODID:-//engelsystem-rs//calendar feed//EN");
+        calendar.line("CALSCALE:GREGORIAN");
+        calendar.line("METHOD:PUBLISH");
+        calendar.line(&format!("X-WR-CALNAME:{}", escape(name)));
+
+        calendar
+    }
+
+    pub fn add_event(&mut self, event: Event) {
+        self.line("BEGIN:VEVENT");
+        self.line(&format!("UID:{}@engelsystem-rs", event.id));
+        self.line(&format!("DTSTAMP:{}", format_time(self.stamp)));
+        self.line(&format!(
+            "LAST-MODIFIED:{}",
+            format_time(event.updated_at.unwrap_or(event.created_at))
+        ));
+        self.line(&format!("SEQUENCE:{}", event.sequence));
+        self.line(&format!("DTSTART:{}", format_time(event.starts_at)));
+        self.line(&format!("DTEND:{}", format_time(event.ends_at)));
+        self.line(&format!("SUMMARY:{}", escape(event.summary)));
+
+        if let Some(description) = event.description {
+            self.line(&format!("DESCRIPTION:{}", escape(description)));
+        }
+
+        if let Some(category) = event.category {
+            self.line(&format!("CATEGORIES:{}", escape(category)));
+        }
+
+        if let Some(location) = event.location {
+            self.line(&format!("LOCATION:{}", escape(location)));
+        }
+
+        self.line("STATUS:CONFIRMED");
+        self.line("END:VEVENT");
+    }
+
+    pub fn finish(mut self) -> String {
+        self.line("END:VCALENDAR");
+        self.content
+    }
+
+    /// Appends a content line, folded so no physical line is longer than 75 octets
+    fn line(&mut self, line: &str) {
+        let mut octets = 0;
+
+        for c in line.chars() {
+            if octets + c.len_utf8() > MAX_LINE_OCTETS {
+                self.content.push_str("\r\n ");
+                octets = 1;
+            }
+
+            self.content.push(c);
+            octets += c.len_utf8();
+        }
+
+        self.content.push_str("\r\n");
+    }
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Undoes the folding, see RFC 5545 section 3.1
+    fn unfold(content: &str) -> String {
+        content.replace("\r\n ", "")
+    }
+
+    fn fold(line: &str) -> String {
+        let mut calendar = Calendar {
+            content: String::new(),
+            stamp: Utc::now(),
+        };
+        calendar.line(line);
+        calendar.content
+    }
+
+    /// No physical line may be longer than 75 octets. Continuation lines start with a space, which
+    /// comparing the unfolded content checks.
+    fn assert_folded(folded: &str) {
+        for line in folded.split("\r\n") {
+            assert!(line.len() <= MAX_LINE_OCTETS, "{line:?} is too long");
+        }
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(escape("Bar, Kasse; Lager"), r"Bar\, Kasse\; Lager");
+        assert_eq!(escape("C:\\Schichten"), r"C:\\Schichten");
+        assert_eq!(escape("Zeile 1\nZeile 2"), r"Zeile 1\nZeile 2");
+        assert_eq!(escape("Zeile 1\r\nZeile 2"), r"Zeile 1\nZeile 2");
+        assert_eq!(escape("\\,;\n"), r"\\\,\;\n");
+        assert_eq!(escape("Küche: 5 € 🍕"), "Küche: 5 € 🍕");
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        let line = "a".repeat(MAX_LINE_OCTETS);
+        assert_eq!(fold(&line), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let line = format!("DESCRIPTION:{}", "a".repeat(200));
+        let folded = fold(&line);
+
+        assert_folded(&folded);
+        assert_eq!(folded.split("\r\n").next().unwrap().len(), MAX_LINE_OCTETS);
+        assert_eq!(unfold(&folded), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn multibyte_characters_are_not_split() {
+        // 2, 3 and 4 octets, so the fold falls next to every kind of character
+        for c in ['ä', '€', '🍕'] {
+            for prefix in 0..4 {
+                let line = format!("{}{}", "a".repeat(prefix), c.to_string().repeat(60));
+                let folded = fold(&line);
+
+                assert_folded(&folded);
+                assert_eq!(unfold(&folded), format!("{line}\r\n"));
+            }
+        }
+    }
+
+    #[test]
+    fn calendar_with_event() {
+        let mut calendar = Calendar::new("Schichten von Meow");
+        calendar.add_event(Event {
+            id: Uuid::nil(),
+            created_at: "2026-10-18T10:00:00Z".parse().unwrap(),
+            updated_at: None,
+            sequence: 2,
+            starts_at: "2026-10-18T14:00:00Z".parse().unwrap(),
+            ends_at: "2026-10-18T16:00:00Z".parse().unwrap(),
+            summary: "Bar, Kasse",
+            description: Some(&"Gläser spülen; Theke wischen\n".repeat(5)),
+            category: None,
+            location: Some("Halle 1"),
+        });
+        let content = calendar.finish();
+
+        assert_folded(&content);
+        let content = unfold(&content);
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.first(), Some(&"BEGIN:VCALENDAR"));
+        assert_eq!(lines.last(), Some(&"END:VCALENDAR"));
+        assert!(lines.contains(&"UID:00000000-0000-0000-0000-000000000000@engelsystem-rs"));
+        assert!(lines.contains(&"DTSTART:20261018T140000Z"));
+        assert!(lines.contains(&"SEQUENCE:2"));
+        assert!(lines.contains(&r"SUMMARY:Bar\, Kasse"));
+        assert!(lines.contains(&"LOCATION:Halle 1"));
+        assert!(!lines.iter().any(|line| line.starts_with("CATEGORIES")));
+        let description = lines
+            .iter()
+            .find_map(|line| line.strip_prefix("DESCRIPTION:"))
+            .unwrap();
+        assert_eq!(description, r"Gläser spülen\; Theke wischen\n".repeat(5));
+    }
+}
@@ -1,2 +1,7 @@
+pub mod audit;
+pub mod client_ip;
+pub mod ical;
+pub mod nullable;
+pub mod path;
 pub mod schema_impls;
 pub mod validation;
@@ -0,0 +1,13 @@
+//! Tells a missing field (keep the value) apart from an explicit `null` (clear the value) in
+//! update requests. Use with `#[serde(default, deserialize_with = "nullable::deserialize")]` on an
+//! `Option<Option<T>>` field.
+
+use serde::{Deserialize, Deserializer};
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
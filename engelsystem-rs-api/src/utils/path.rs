@@ -0,0 +1,9 @@
+use std::str::FromStr;
+
+use uuid::Uuid;
+
+use crate::Error;
+
+pub fn parse_uuid(uid: String) -> crate::Result<Uuid> {
+    Uuid::from_str(&uid).map_err(|_| Error::InvalidUid { uid })
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use actix_web::{
+    App,
+    dev::{Service, ServiceResponse},
+    http::{StatusCode, header::AUTHORIZATION},
+    test,
+    web::{self, Data},
+};
+use chrono::{TimeDelta, Utc};
+use engelsystem_rs_api::routes::{
+    calendar_token_create, shift_completions, shift_update, view_my_permissions,
+};
+use engelsystem_rs_db::{
+    ActiveShift,
+    ActiveValue::Set,
+    Database,
+    api_token::{create_personal_token, revoke_personal_token},
+    audit::Actor,
+    connect_and_migrate,
+    permission::PermissionType,
+    shift::add_shift,
+    user::add_user,
+};
+use uuid::Uuid;
+
+async fn setup() -> (Database, Uuid) {
+    let db = connect_and_migrate("sqlite::memory:").await.unwrap();
+    let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+        .await
+        .unwrap();
+
+    (db, user.id)
+}
+
+async fn call(
+    db: &Database,
+    req: test::TestRequest,
+) -> ServiceResponse<impl actix_web::body::MessageBody> {
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(db.clone()))
+            .route("/me/permissions", web::get().to(view_my_permissions))
+            .route("/me/calendar-token", web::post().to(calendar_token_create))
+            .route("/shifts/{shift_id}", web::patch().to(shift_update))
+            .route(
+                "/shifts/{shift_id}/completions",
+                web::get().to(shift_completions),
+            ),
+    )
+    .await;
+
+    app.call(req.to_request()).await.unwrap()
+}
+
+fn permissions_with(header: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri("/me/permissions")
+        .insert_header((AUTHORIZATION, header))
+}
+
+#[test_log::test(actix_web::test)]
+async fn token_gets_scopes_the_role_has() {
+    let (db, user_id) = setup().await;
+
+    // A user can't view users, so the token doesn't get that scope
+    let scopes = [PermissionType::SignUpForShifts, PermissionType::ViewUsers];
+    let (_, token) = create_personal_token(user_id, "Script".into(), &scopes, None, &db)
+        .await
+        .unwrap();
+
+    let res = call(&db, permissions_with(&format!("Bearer {token}"))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let permissions: Vec<String> = test::read_body_json(res).await;
+    assert_eq!(permissions, ["SignUpForShifts"]);
+}
+
+#[test_log::test(actix_web::test)]
+async fn expired_and_revoked_tokens_are_rejected() {
+    let (db, user_id) = setup().await;
+
+    let expires_at = Utc::now() + TimeDelta::seconds(1);
+    let (_, expiring) =
+        create_personal_token(user_id, "Expiring".into(), &[], Some(expires_at), &db)
+            .await
+            .unwrap();
+    let (revoked, revoked_token) = create_personal_token(user_id, "Revoked".into(), &[], None, &db)
+        .await
+        .unwrap();
+
+    let res = call(&db, permissions_with(&format!("Bearer {revoked_token}"))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    revoke_personal_token(user_id, revoked.id, &db)
+        .await
+        .unwrap();
+    let res = call(&db, permissions_with(&format!("Bearer {revoked_token}"))).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let res = call(&db, permissions_with(&format!("Bearer {expiring}"))).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test_log::test(actix_web::test)]
+async fn other_authorization_is_rejected() {
+    let (db, user_id) = setup().await;
+    let (_, token) = create_personal_token(user_id, "Script".into(), &[], None, &db)
+        .await
+        .unwrap();
+
+    for header in [
+        format!("Basic {token}"),
+        token.clone(),
+        "Bearer not-a-token".to_string(),
+    ] {
+        let res = call(&db, permissions_with(&header)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{header}");
+    }
+}
+
+#[test_log::test(actix_web::test)]
+async fn account_changes_need_a_login() {
+    let (db, user_id) = setup().await;
+    let scopes = [PermissionType::SignUpForShifts];
+    let (_, token) = create_personal_token(user_id, "Script".into(), &scopes, None, &db)
+        .await
+        .unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/me/calendar-token")
+        .insert_header((AUTHORIZATION, format!("Bearer {token}")));
+    let res = call(&db, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[test_log::test(actix_web::test)]
+async fn managing_own_shifts_needs_the_scope() {
+    let (db, user_id) = setup().await;
+    let starts_at = Utc::now() + TimeDelta::days(1);
+    let shift = add_shift(
+        ActiveShift {
+            created_by: Set(user_id),
+            managed_by: Set(Some(user_id)),
+            starts_at: Set(starts_at),
+            ends_at: Set(starts_at + TimeDelta::hours(2)),
+            name: Set("Bar".to_string()),
+            description: Set(None),
+            angels_needed: Set(2),
+            angel_type_id: Set(None),
+            ..Default::default()
+        },
+        &Actor::cli(),
+        &db,
+    )
+    .await
+    .unwrap();
+
+    let (_, unscoped) = create_personal_token(user_id, "Unscoped".into(), &[], None, &db)
+        .await
+        .unwrap();
+    let scopes = [PermissionType::ManageShifts];
+    let (_, scoped) = create_personal_token(user_id, "Scoped".into(), &scopes, None, &db)
+        .await
+        .unwrap();
+
+    let update = |token: &str| {
+        test::TestRequest::patch()
+            .uri(&format!("/shifts/{}", shift.id))
+            .insert_header((AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(serde_json::json!({ "name": "Kitchen" }))
+    };
+    let completions = |token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/shifts/{}/completions", shift.id))
+            .insert_header((AUTHORIZATION, format!("Bearer {token}")))
+    };
+
+    // The user creates and manages the shift, but their token may not manage shifts
+    let res = call(&db, update(&unscoped)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = call(&db, completions(&unscoped)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = call(&db, update(&scoped)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = call(&db, completions(&scoped)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
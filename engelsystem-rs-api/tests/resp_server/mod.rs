@@ -0,0 +1,368 @@
+//! A stand-in for a Redis server, supporting just the commands the session store uses
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+enum Entry {
+    String {
+        value: String,
+        expires_at: Option<Instant>,
+    },
+    Set(HashSet<String>),
+    SortedSet(HashMap<String, f64>),
+}
+
+impl Entry {
+    fn is_expired(&self) -> bool {
+        matches!(self, Self::String { expires_at: Some(at), .. } if *at <= Instant::now())
+    }
+}
+
+enum Reply {
+    Ok,
+    Nil,
+    Integer(i64),
+    Bulk(String),
+    Array(Vec<String>),
+    Error(String),
+}
+
+impl Reply {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Ok => b"+OK\r\n".to_vec(),
+            Self::Nil => b"$-1\r\n".to_vec(),
+            Self::Integer(value) => format!(":{value}\r\n").into_bytes(),
+            Self::Bulk(value) => format!("${}\r\n{value}\r\n", value.len()).into_bytes(),
+            Self::Array(values) => {
+                let mut encoded = format!("*{}\r\n", values.len()).into_bytes();
+                for value in values {
+                    encoded.extend(Self::Bulk(value.clone()).encode());
+                }
+                encoded
+            }
+            Self::Error(message) => format!("-{message}\r\n").into_bytes(),
+        }
+    }
+}
+
+fn wrong_type() -> Reply {
+    Reply::Error("WRONGTYPE Operation against a key holding the wrong kind of value".to_string())
+}
+
+/// Parses a score bound like `-inf`, `10` or `(10`. Returns the score and if it is exclusive.
+fn parse_bound(bound: &str) -> Option<(f64, bool)> {
+    let (bound, exclusive) = match bound.strip_prefix('(') {
+        Some(bound) => (bound, true),
+        None => (bound, false),
+    };
+    let score = match bound {
+        "-inf" => f64::NEG_INFINITY,
+        "+inf" | "inf" => f64::INFINITY,
+        bound => bound.parse().ok()?,
+    };
+
+    Some((score, exclusive))
+}
+
+fn in_range(score: f64, min: (f64, bool), max: (f64, bool)) -> bool {
+    let above = if min.1 { score > min.0 } else { score >= min.0 };
+    let below = if max.1 { score < max.0 } else { score <= max.0 };
+    above && below
+}
+
+#[derive(Default)]
+struct Data {
+    entries: HashMap<String, Entry>,
+}
+
+impl Data {
+    fn get(&mut self, key: &str) -> Option<&mut Entry> {
+        if self.entries.get(key).is_some_and(Entry::is_expired) {
+            self.entries.remove(key);
+        }
+        self.entries.get_mut(key)
+    }
+
+    fn execute(&mut self, args: &[String]) -> Reply {
+        let Some(command) = args.first() else {
+            return Reply::Error("ERR empty command".to_string());
+        };
+
+        match (command.to_ascii_uppercase().as_str(), &args[1..]) {
+            ("PING", []) => Reply::Ok,
+            ("SELECT", [_]) => Reply::Ok,
+            ("GET", [key]) => match self.get(key) {
+                None => Reply::Nil,
+                Some(Entry::String { value, .. }) => Reply::Bulk(value.clone()),
+                Some(_) => wrong_type(),
+            },
+            ("SET", [key, value, options @ ..]) => {
+                let mut expires_at = None;
+                let mut condition = None;
+                let mut options = options.iter();
+                while let Some(option) = options.next() {
+                    match option.to_ascii_uppercase().as_str() {
+                        "EX" => {
+                            let Some(seconds) = options.next().and_then(|s| s.parse().ok()) else {
+                                return Reply::Error("ERR syntax error".to_string());
+                            };
+                            expires_at = Some(Instant::now() + Duration::from_secs(seconds));
+                        }
+                        "NX" => condition = Some(false),
+                        "XX" => condition = Some(true),
+                        _ => return Reply::Error("ERR syntax error".to_string()),
+                    }
+                }
+
+                let exists = self.get(key).is_some();
+                if condition.is_some_and(|must_exist| must_exist != exists) {
+                    return Reply::Nil;
+                }
+
+                self.entries.insert(
+                    key.clone(),
+                    Entry::String {
+                        value: value.clone(),
+                        expires_at,
+                    },
+                );
+                Reply::Ok
+            }
+            ("DEL", keys) if !keys.is_empty() => {
+                let mut deleted = 0;
+                for key in keys {
+                    if self.get(key).is_some() {
+                        self.entries.remove(key);
+                        deleted += 1;
+                    }
+                }
+                Reply::Integer(deleted)
+            }
+            ("SADD", [key, members @ ..]) if !members.is_empty() => {
+                let entry = self
+                    .entries
+                    .entry(key.clone())
+                    .or_insert_with(|| Entry::Set(HashSet::new()));
+                let Entry::Set(set) = entry else {
+                    return wrong_type();
+                };
+                let added = members.iter().filter(|m| set.insert((*m).clone())).count();
+                Reply::Integer(added as i64)
+            }
+            ("SREM", [key, members @ ..]) if !members.is_empty() => match self.get(key) {
+                None => Reply::Integer(0),
+                Some(Entry::Set(set)) => {
+                    let removed = members.iter().filter(|m| set.remove(*m)).count();
+                    if set.is_empty() {
+                        self.entries.remove(key);
+                    }
+                    Reply::Integer(removed as i64)
+                }
+                Some(_) => wrong_type(),
+            },
+            ("SMEMBERS", [key]) => match self.get(key) {
+                None => Reply::Array(Vec::new()),
+                Some(Entry::Set(set)) => Reply::Array(set.iter().cloned().collect()),
+                Some(_) => wrong_type(),
+            },
+            ("ZADD", [key, score, member]) => {
+                let Ok(score) = score.parse() else {
+                    return Reply::Error("ERR value is not a valid float".to_string());
+                };
+                let entry = self
+                    .entries
+                    .entry(key.clone())
+                    .or_insert_with(|| Entry::SortedSet(HashMap::new()));
+                let Entry::SortedSet(set) = entry else {
+                    return wrong_type();
+                };
+                let added = set.insert(member.clone(), score).is_none();
+                Reply::Integer(added as i64)
+            }
+            ("ZREM", [key, members @ ..]) if !members.is_empty() => match self.get(key) {
+                None => Reply::Integer(0),
+                Some(Entry::SortedSet(set)) => {
+                    let removed = members.iter().filter(|m| set.remove(*m).is_some()).count();
+                    if set.is_empty() {
+                        self.entries.remove(key);
+                    }
+                    Reply::Integer(removed as i64)
+                }
+                Some(_) => wrong_type(),
+            },
+            ("ZCOUNT", [key, min, max]) => {
+                let (Some(min), Some(max)) = (parse_bound(min), parse_bound(max)) else {
+                    return Reply::Error("ERR min or max is not a float".to_string());
+                };
+                match self.get(key) {
+                    None => Reply::Integer(0),
+                    Some(Entry::SortedSet(set)) => Reply::Integer(
+                        set.values()
+                            .filter(|&&score| in_range(score, min, max))
+                            .count() as i64,
+                    ),
+                    Some(_) => wrong_type(),
+                }
+            }
+            ("ZRANGEBYSCORE", [key, min, max, rest @ ..]) => {
+                let (Some(min), Some(max)) = (parse_bound(min), parse_bound(max)) else {
+                    return Reply::Error("ERR min or max is not a float".to_string());
+                };
+                let (offset, count) = match rest {
+                    [] => (0, usize::MAX),
+                    [limit, offset, count] if limit.eq_ignore_ascii_case("LIMIT") => {
+                        match (offset.parse(), count.parse()) {
+                            (Ok(offset), Ok(count)) => (offset, count),
+                            _ => return Reply::Error("ERR syntax error".to_string()),
+                        }
+                    }
+                    _ => return Reply::Error("ERR syntax error".to_string()),
+                };
+                match self.get(key) {
+                    None => Reply::Array(Vec::new()),
+                    Some(Entry::SortedSet(set)) => {
+                        let mut members: Vec<_> = set
+                            .iter()
+                            .filter(|(_, score)| in_range(**score, min, max))
+                            .collect();
+                        members.sort_by(|a, b| a.1.total_cmp(b.1).then(a.0.cmp(b.0)));
+                        Reply::Array(
+                            members
+                                .into_iter()
+                                .skip(offset)
+                                .take(count)
+                                .map(|(member, _)| member.clone())
+                                .collect(),
+                        )
+                    }
+                    Some(_) => wrong_type(),
+                }
+            }
+            (command, _) => Reply::Error(format!(
+                "ERR unknown command or wrong arguments '{command}'"
+            )),
+        }
+    }
+}
+
+/// A running stand-in server. Stops when the test's runtime shuts down.
+pub struct RespServer {
+    pub url: String,
+    data: Arc<Mutex<Data>>,
+    stall: Arc<Mutex<Option<Duration>>>,
+}
+
+impl RespServer {
+    pub async fn start() -> Self {
+        Self::start_with_password(None).await
+    }
+
+    /// Rejects all commands but `AUTH` until the client sent the password
+    pub async fn start_with_password(password: Option<&str>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let data = Arc::new(Mutex::new(Data::default()));
+
+        let url = match password {
+            Some(password) => format!("redis://:{password}@{address}/0"),
+            None => format!("redis://{address}"),
+        };
+
+        let password = password.map(str::to_string);
+        let stall = Arc::new(Mutex::new(None));
+        let (shared, shared_stall) = (data.clone(), stall.clone());
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(serve(
+                    stream,
+                    shared.clone(),
+                    shared_stall.clone(),
+                    password.clone(),
+                ));
+            }
+        });
+
+        Self { url, data, stall }
+    }
+
+    /// Holds back the next reply on any connection for `delay`, like a server behind a network
+    /// partition that keeps the connection open
+    pub fn stall_next_reply(&self, delay: Duration) {
+        *self.stall.lock().unwrap() = Some(delay);
+    }
+
+    /// The amount of keys, for checking that nothing is left behind
+    pub fn key_count(&self) -> usize {
+        let mut data = self.data.lock().unwrap();
+        data.entries.retain(|_, entry| !entry.is_expired());
+        data.entries.len()
+    }
+}
+
+async fn read_command(stream: &mut BufReader<TcpStream>) -> Option<Vec<String>> {
+    async fn read_line(stream: &mut BufReader<TcpStream>) -> Option<String> {
+        let mut line = String::new();
+        match stream.read_line(&mut line).await {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end_matches("\r\n").to_string()),
+        }
+    }
+
+    let count: usize = read_line(stream).await?.strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let length: usize = read_line(stream).await?.strip_prefix('$')?.parse().ok()?;
+        let mut bytes = vec![0; length + 2];
+        stream.read_exact(&mut bytes).await.ok()?;
+        bytes.truncate(length);
+        args.push(String::from_utf8(bytes).ok()?);
+    }
+
+    Some(args)
+}
+
+async fn serve(
+    stream: TcpStream,
+    data: Arc<Mutex<Data>>,
+    stall: Arc<Mutex<Option<Duration>>>,
+    password: Option<String>,
+) {
+    let mut stream = BufReader::new(stream);
+    let mut authenticated = password.is_none();
+
+    while let Some(args) = read_command(&mut stream).await {
+        let is_auth = args.first().is_some_and(|c| c.eq_ignore_ascii_case("AUTH"));
+
+        let reply = if is_auth {
+            // The password is the last argument, with or without a username
+            if args.len() > 1 && args.last() == password.as_ref() {
+                authenticated = true;
+                Reply::Ok
+            } else {
+                Reply::Error("WRONGPASS invalid username-password pair".to_string())
+            }
+        } else if !authenticated {
+            Reply::Error("NOAUTH Authentication required.".to_string())
+        } else {
+            data.lock().unwrap().execute(&args)
+        };
+
+        let delay = stall.lock().unwrap().take();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        if stream.get_mut().write_all(&reply.encode()).await.is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use actix_session::{Session, SessionMiddleware};
+use actix_web::{
+    App, HttpResponse,
+    cookie::Key,
+    http::StatusCode,
+    test,
+    web::{self, Data, Path},
+};
+use chrono::{TimeDelta, Utc};
+use engelsystem_rs_api::{
+    routes::{shift_delete, shift_update},
+    session_store::MemorySessionStore,
+};
+use engelsystem_rs_db::{
+    ActiveShift, ActiveValue::Set, Database, audit::Actor, connect_and_migrate, shift::add_shift,
+    shift::get_shift_by_id, user::add_user,
+};
+use uuid::Uuid;
+
+/// Logs the client in as the user of the path, instead of going through `/login` with a password
+async fn log_in_as(session: Session, user_id: Path<Uuid>) -> HttpResponse {
+    session.insert("user_id", user_id.into_inner()).unwrap();
+    HttpResponse::NoContent().finish()
+}
+
+macro_rules! shift_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .wrap(SessionMiddleware::new(
+                    MemorySessionStore::default(),
+                    Key::generate(),
+                ))
+                .app_data(Data::new($db.clone()))
+                .route("/test-login/{user_id}", web::post().to(log_in_as))
+                .route("/shifts/{shift_id}", web::patch().to(shift_update))
+                .route("/shifts/{shift_id}", web::delete().to(shift_delete)),
+        )
+        .await
+    };
+}
+
+/// Logs in as `user_id` and returns the session cookie
+macro_rules! session_of {
+    ($app:expr, $user_id:expr) => {{
+        let req = test::TestRequest::post()
+            .uri(&format!("/test-login/{}", $user_id))
+            .to_request();
+        let res = test::call_service(&$app, req).await;
+
+        res.response().cookies().next().unwrap().into_owned()
+    }};
+}
+
+/// A shift created by a user who may not manage shifts, e.g. because their role lost the
+/// permission since
+async fn shift_of(created_by: Uuid, db: &Database) -> Uuid {
+    let starts_at = Utc::now() + TimeDelta::days(1);
+    let shift = add_shift(
+        ActiveShift {
+            created_by: Set(created_by),
+            managed_by: Set(None),
+            starts_at: Set(starts_at),
+            ends_at: Set(starts_at + TimeDelta::hours(2)),
+            name: Set("Bar".to_string()),
+            description: Set(None),
+            angels_needed: Set(2),
+            angel_type_id: Set(None),
+            ..Default::default()
+        },
+        &Actor::cli(),
+        db,
+    )
+    .await
+    .unwrap();
+
+    shift.id
+}
+
+#[test_log::test(actix_web::test)]
+async fn creator_edits_and_cancels_their_shift() {
+    let db = connect_and_migrate("sqlite::memory:").await.unwrap();
+    let creator = add_user("Meow", "meow@meow.de", "awawa", &db)
+        .await
+        .unwrap();
+    let shift_id = shift_of(creator.id, &db).await;
+
+    let app = shift_app!(db);
+    let session = session_of!(app, creator.id);
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/shifts/{shift_id}"))
+        .cookie(session.clone())
+        .set_json(serde_json::json!({ "name": "Kitchen" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let shift = get_shift_by_id(shift_id, &db).await.unwrap().unwrap();
+    assert_eq!(shift.name, "Kitchen");
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/shifts/{shift_id}"))
+        .cookie(session)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(get_shift_by_id(shift_id, &db).await.unwrap().is_none());
+}
+
+#[test_log::test(actix_web::test)]
+async fn others_cant_edit_a_shift() {
+    let db = connect_and_migrate("sqlite::memory:").await.unwrap();
+    let creator = add_user("Meow", "meow@meow.de", "awawa", &db)
+        .await
+        .unwrap();
+    let other = add_user("Nyan", "nyan@meow.de", "awawa", &db)
+        .await
+        .unwrap();
+    let shift_id = shift_of(creator.id, &db).await;
+
+    let app = shift_app!(db);
+    let session = session_of!(app, other.id);
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/shifts/{shift_id}"))
+        .cookie(session.clone())
+        .set_json(serde_json::json!({ "name": "Kitchen" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/shifts/{shift_id}"))
+        .cookie(session)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert!(get_shift_by_id(shift_id, &db).await.unwrap().is_some());
+}
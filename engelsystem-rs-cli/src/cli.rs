@@ -1,5 +1,11 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
 use clap::{Args, Parser, Subcommand};
-use engelsystem_rs_db::role::RoleType;
+use engelsystem_rs_db::{
+    AuditAction, AuditOrigin, AuditTarget, permission::PermissionType, shift_import::ImportFormat,
+};
+use uuid::Uuid;
 
 #[derive(Debug, Parser)]
 #[command(name = "engelcli")]
@@ -8,6 +14,29 @@ pub enum EngelCli {
     #[command(subcommand)]
     Users(UsersCmd),
 
+    #[command(subcommand)]
+    Roles(RolesCmd),
+
+    #[command(subcommand)]
+    Shifts(ShiftsCmd),
+
+    #[command(subcommand)]
+    Templates(TemplatesCmd),
+
+    #[command(subcommand)]
+    Outbox(OutboxCmd),
+
+    #[command(subcommand)]
+    Lockouts(LockoutsCmd),
+
+    #[command(subcommand)]
+    Sessions(SessionsCmd),
+
+    #[command(subcommand)]
+    Tokens(TokensCmd),
+
+    Audit(AuditCmd),
+
     #[command(subcommand)]
     Debug(DebugCmd),
 }
@@ -19,6 +48,21 @@ pub enum UsersCmd {
     List,
 
     Role(RoleCmd),
+
+    #[command(about = "Mark the email of <USER> as verified without a verification mail")]
+    VerifyEmail {
+        user: String,
+    },
+
+    #[command(about = "End all sessions of <USER>, which logs them out everywhere")]
+    Logout {
+        user: String,
+    },
+
+    #[command(
+        about = "Recompute the shift time and points of all users from their completed shifts"
+    )]
+    RecomputeTotals,
 }
 
 #[derive(Debug, Args)]
@@ -37,10 +81,214 @@ pub enum RoleAction {
         #[arg(
             help = "The new role of <USER>. This is dynamic, but default roles are 'Guest', 'User' and 'Admin'"
         )]
-        role: RoleType,
+        role: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+#[command(about = "Role related management commands")]
+pub enum RolesCmd {
+    #[command(about = "List all roles")]
+    List,
+
+    #[command(about = "Add a role without any permissions")]
+    Add {
+        name: String,
+    },
+
+    #[command(about = "Rename <ROLE> to <NAME>")]
+    Rename {
+        role: String,
+        name: String,
+    },
+
+    #[command(
+        about = "Delete <ROLE>. The default roles and roles that users still have can't be deleted"
+    )]
+    Delete {
+        role: String,
+    },
+
+    Permissions(PermissionsCmd),
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Show or change the permissions of a role")]
+pub struct PermissionsCmd {
+    pub role: String,
+
+    #[clap(subcommand)]
+    pub action: Option<PermissionAction>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PermissionAction {
+    #[command(about = "Enable <PERMISSION> for <ROLE>")]
+    Enable { permission: PermissionType },
+
+    #[command(about = "Disable <PERMISSION> for <ROLE>")]
+    Disable { permission: PermissionType },
+}
+
+#[derive(Debug, Subcommand)]
+#[command(about = "Shift related management commands")]
+pub enum ShiftsCmd {
+    #[command(
+        about = "Add all shifts from a CSV or JSON file. Nothing is added if any row is invalid"
+    )]
+    Import {
+        file: PathBuf,
+
+        #[arg(long, help = "The user that is recorded as creator of the shifts")]
+        created_by: String,
+
+        #[arg(
+            long,
+            help = "csv or json. Detected from the file extension by default"
+        )]
+        format: Option<ImportFormat>,
+
+        #[arg(long, help = "Only validate the rows without adding any shifts")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+#[command(about = "Shift template related management commands")]
+pub enum TemplatesCmd {
+    #[command(about = "List all shift templates")]
+    List,
+
+    #[command(
+        about = "Generate the shifts of <TEMPLATE> from <FROM> to <UNTIL>. Days that already have a shift from it are skipped"
+    )]
+    Expand {
+        template: u32,
+        from: NaiveDate,
+        until: NaiveDate,
+    },
+
+    #[command(about = "Delete <TEMPLATE>. The shifts generated from it are kept")]
+    Delete { template: u32 },
+}
+
+#[derive(Debug, Subcommand)]
+#[command(about = "Commands for the outbox of mails the API sends")]
+pub enum OutboxCmd {
+    #[command(about = "List the mails in the outbox, newest first")]
+    List {
+        #[arg(
+            long,
+            help = "Only list mails that weren't sent yet",
+            conflicts_with = "failed"
+        )]
+        pending: bool,
+
+        #[arg(long, help = "Only list mails that were given up on")]
+        failed: bool,
+    },
+
+    #[command(about = "Try sending <MAIL> again, with a fresh set of attempts")]
+    Retry { mail: u32 },
+}
+
+#[derive(Debug, Subcommand)]
+#[command(about = "Session related management commands")]
+pub enum SessionsCmd {
+    #[command(about = "Show how many sessions are live and how many of them are logged in")]
+    Count,
+
+    #[command(
+        about = "Delete expired sessions. The API server also does this regularly while it runs"
+    )]
+    Prune {
+        #[arg(
+            long,
+            default_value_t = 500,
+            help = "How many sessions are deleted at once"
+        )]
+        batch_size: u64,
     },
 }
 
+#[derive(Debug, Subcommand)]
+#[command(about = "Service token related management commands")]
+pub enum TokensCmd {
+    #[command(about = "List all service tokens")]
+    List,
+
+    #[command(about = "Create a service token that acts as <USER>. The token is only shown once")]
+    Create {
+        user: String,
+        name: String,
+
+        #[arg(
+            long = "scope",
+            help = "A permission the token may use, if the role of <USER> has it. Can be repeated"
+        )]
+        scopes: Vec<PermissionType>,
+
+        #[arg(
+            long,
+            help = "The last day the token is valid. It doesn't expire without this"
+        )]
+        expires: Option<NaiveDate>,
+    },
+
+    #[command(about = "Revoke the service token with the id <TOKEN>")]
+    Revoke { token: Uuid },
+}
+
+#[derive(Debug, Subcommand)]
+#[command(about = "Commands for the logins that are blocked after too many failures")]
+pub enum LockoutsCmd {
+    #[command(about = "List the usernames and addresses that can't log in right now")]
+    List,
+
+    #[command(about = "Forget the failed logins of the username <KEY>, which lifts its lockout")]
+    Clear {
+        #[arg(required_unless_present = "all")]
+        key: Option<String>,
+
+        #[arg(long, help = "<KEY> is an IP address instead of a username")]
+        ip: bool,
+
+        #[arg(long, help = "Forget the failed logins of everyone", conflicts_with_all = ["key", "ip"])]
+        all: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Show the log of administrative changes, newest first")]
+pub struct AuditCmd {
+    #[arg(long, default_value_t = 50, help = "How many entries to show")]
+    pub limit: u64,
+
+    #[arg(long, help = "Only changes made by this user")]
+    pub actor: Option<String>,
+
+    #[arg(long, help = "Only changes made through api, web or cli")]
+    pub origin: Option<AuditOrigin>,
+
+    #[arg(
+        long,
+        help = "Only this kind of change, e.g. shift_created or user_role_changed"
+    )]
+    pub action: Option<AuditAction>,
+
+    #[arg(long, help = "Only changes to a shift, role or user")]
+    pub target_type: Option<AuditTarget>,
+
+    #[arg(long, help = "Only changes to the shift, role or user with this id")]
+    pub target: Option<String>,
+
+    #[arg(
+        long,
+        help = "Only entries older than the one with this id, to show the next page"
+    )]
+    pub before: Option<u32>,
+}
+
 #[derive(Debug, Subcommand)]
 #[command(about = "Debugging related commands")]
 pub enum DebugCmd {
@@ -1,13 +1,33 @@
-use std::env;
+use std::{env, path::Path, process::exit, str::FromStr};
 
+use chrono::{FixedOffset, NaiveDate};
 use clap::Parser;
 use cli::EngelCli;
 use engelsystem_rs_db::{
-    DatabaseConnection, UserView, connect,
-    role::RoleType,
-    user::{add_guest, get_all_user_views, get_role_by_username, set_role_by_username},
+    DatabaseConnection, LoginFailureKind, Role, UserView,
+    api_token::{create_service_token, get_service_tokens, revoke_service_token},
+    audit::{Actor, AuditQuery, get_audit_entries},
+    completion::recompute_all_user_totals,
+    connect,
+    email_verification::mark_email_verified,
+    login_throttle::{clear_all_login_failures, clear_login_failures, get_login_lockouts},
+    mail_outbox::{OutboxFilter, get_outbox_mails, retry_mail},
+    permission::PermissionType,
+    role::{
+        RoleType, add_role, delete_role, get_all_roles, get_role_by_id, get_role_by_name,
+        get_role_permission_views, rename_role, set_role_permission,
+    },
+    session::{end_user_sessions, get_live_session_count, prune_expired_sessions},
+    shift_import::{ImportFormat, import_shifts},
+    shift_template::{
+        delete_shift_template, expand_shift_template, get_all_shift_templates, parse_utc_offset,
+    },
+    user::{
+        add_guest, get_all_user_views, get_role_by_username, get_user_id_by_name,
+        set_role_by_username,
+    },
 };
-use log::{info, warn};
+use log::{error, info, warn};
 use rand::{Rng as _, distr::Alphanumeric};
 use ratatui::{
     Frame,
@@ -41,16 +61,182 @@ async fn main() {
             use cli::UsersCmd;
             match users_cmd {
                 UsersCmd::List => list_users_tui(&db).await,
+                UsersCmd::RecomputeTotals => {
+                    let count = recompute_all_user_totals(&db).await.unwrap();
+                    info!("Recomputed the shift time and points of {count} users");
+                }
+                UsersCmd::VerifyEmail { user } => verify_email(&user, &db).await,
+                UsersCmd::Logout { user } => logout_user(&user, &db).await,
                 UsersCmd::Role(role_cmd) => {
                     use cli::RoleAction;
 
                     match role_cmd.action {
                         None => get_role(&role_cmd.user, &db).await,
-                        Some(RoleAction::Set { role }) => set_role(&role_cmd.user, role, &db).await,
+                        Some(RoleAction::Set { role }) => {
+                            set_role(&role_cmd.user, &role, &db).await
+                        }
                     }
                 }
             }
         }
+        EngelCli::Roles(roles_cmd) => {
+            use cli::RolesCmd;
+
+            match roles_cmd {
+                RolesCmd::List => list_roles(&db).await,
+                RolesCmd::Add { name } => {
+                    let role = add_role(&name, &Actor::cli(), &db).await.unwrap();
+                    info!("Role {name:?} has been added with id {}", role.id);
+                }
+                RolesCmd::Rename { role, name } => {
+                    let role = find_role(&role, &db).await;
+                    rename_role(role.id, &name, &Actor::cli(), &db)
+                        .await
+                        .unwrap();
+                    info!("Role {:?} has been renamed to {name:?}", role.name);
+                }
+                RolesCmd::Delete { role } => {
+                    let role = find_role(&role, &db).await;
+                    delete_role(role.id, &Actor::cli(), &db).await.unwrap();
+                    info!("Role {:?} has been deleted", role.name);
+                }
+                RolesCmd::Permissions(permissions_cmd) => {
+                    use cli::PermissionAction;
+
+                    let role = find_role(&permissions_cmd.role, &db).await;
+                    match permissions_cmd.action {
+                        None => list_permissions(&role, &db).await,
+                        Some(PermissionAction::Enable { permission }) => {
+                            set_permission(&role, permission, true, &db).await
+                        }
+                        Some(PermissionAction::Disable { permission }) => {
+                            set_permission(&role, permission, false, &db).await
+                        }
+                    }
+                }
+            }
+        }
+        EngelCli::Shifts(shifts_cmd) => {
+            use cli::ShiftsCmd;
+
+            match shifts_cmd {
+                ShiftsCmd::Import {
+                    file,
+                    created_by,
+                    format,
+                    dry_run,
+                } => import(&file, &created_by, format, dry_run, &db).await,
+            }
+        }
+        EngelCli::Templates(templates_cmd) => {
+            use cli::TemplatesCmd;
+
+            match templates_cmd {
+                TemplatesCmd::List => list_templates(&db).await,
+                TemplatesCmd::Expand {
+                    template,
+                    from,
+                    until,
+                } => expand_template(template, from, until, &db).await,
+                TemplatesCmd::Delete { template } => {
+                    delete_shift_template(template, &db)
+                        .await
+                        .unwrap_or_else(|e| {
+                            error!("{e}");
+                            exit(1);
+                        });
+                    info!("Shift template {template} has been deleted");
+                }
+            }
+        }
+        EngelCli::Outbox(outbox_cmd) => {
+            use cli::OutboxCmd;
+
+            match outbox_cmd {
+                OutboxCmd::List { pending, failed } => {
+                    let filter = if pending {
+                        OutboxFilter::Pending
+                    } else if failed {
+                        OutboxFilter::Failed
+                    } else {
+                        OutboxFilter::All
+                    };
+                    list_outbox(filter, &db).await
+                }
+                OutboxCmd::Retry { mail } => {
+                    retry_mail(mail, &db).await.unwrap_or_else(|e| {
+                        error!("{e}");
+                        exit(1);
+                    });
+                    info!("Mail {mail} will be sent again");
+                }
+            }
+        }
+        EngelCli::Lockouts(lockouts_cmd) => {
+            use cli::LockoutsCmd;
+
+            match lockouts_cmd {
+                LockoutsCmd::List => list_lockouts(&db).await,
+                LockoutsCmd::Clear { all: true, .. } => {
+                    let count = clear_all_login_failures(&db).await.unwrap();
+                    info!("The failed logins of {count} usernames and addresses have been cleared");
+                }
+                LockoutsCmd::Clear { key, ip, .. } => {
+                    let key = key.expect("clap requires a key without --all");
+                    let kind = if ip {
+                        LoginFailureKind::Ip
+                    } else {
+                        LoginFailureKind::Account
+                    };
+
+                    clear_login_failures(kind, &key, &db)
+                        .await
+                        .unwrap_or_else(|e| {
+                            error!("{e}");
+                            exit(1);
+                        });
+                    info!("The failed logins of {key:?} have been cleared");
+                }
+            }
+        }
+        EngelCli::Sessions(sessions_cmd) => {
+            use cli::SessionsCmd;
+
+            warn_about_session_store();
+            match sessions_cmd {
+                SessionsCmd::Count => {
+                    let (live, logged_in) = get_live_session_count(&db).await.unwrap();
+                    info!("{live} live sessions, {logged_in} of them logged in");
+                }
+                SessionsCmd::Prune { batch_size } => {
+                    let count = prune_expired_sessions(batch_size, &db).await.unwrap();
+                    info!("Deleted {count} expired sessions");
+                }
+            }
+        }
+        EngelCli::Tokens(tokens_cmd) => {
+            use cli::TokensCmd;
+
+            match tokens_cmd {
+                TokensCmd::List => list_service_tokens(&db).await,
+                TokensCmd::Create {
+                    user,
+                    name,
+                    scopes,
+                    expires,
+                } => create_token(&user, name, &scopes, expires, &db).await,
+                TokensCmd::Revoke { token } => {
+                    revoke_service_token(token, &Actor::cli(), &db)
+                        .await
+                        .unwrap_or_else(|e| {
+                            error!("{e}");
+                            exit(1);
+                        });
+                    info!("Service token {token} has been revoked");
+                }
+            }
+        }
+        EngelCli::Audit(audit_cmd) => list_audit(audit_cmd, &db).await,
         EngelCli::Debug(debug_cmd) => {
             use cli::DebugCmd;
 
@@ -61,14 +247,319 @@ async fn main() {
     }
 }
 
-async fn set_role(username: &str, role: RoleType, db: &DatabaseConnection) {
-    set_role_by_username(username, role, db).await.unwrap();
-    info!("Role of User {username:?} has been changed to {role:?}");
+/// Looks up a role by its name. The default roles can also be referred to by the names
+/// [`RoleType`] accepts, e.g. "Admin".
+async fn find_role(name: &str, db: &DatabaseConnection) -> Role {
+    let role = match get_role_by_name(name, db).await.unwrap() {
+        Some(role) => Some(role),
+        None => match RoleType::from_str(name) {
+            Ok(role_type) => get_role_by_id(role_type as u32, db).await.unwrap(),
+            Err(_) => None,
+        },
+    };
+
+    role.unwrap_or_else(|| {
+        error!("There's no role with the name {name:?}");
+        exit(1);
+    })
+}
+
+async fn set_role(username: &str, role: &str, db: &DatabaseConnection) {
+    let role = find_role(role, db).await;
+    set_role_by_username(username, role.id, &Actor::cli(), db)
+        .await
+        .unwrap();
+    info!(
+        "Role of User {username:?} has been changed to {:?}",
+        role.name
+    );
+}
+
+async fn verify_email(username: &str, db: &DatabaseConnection) {
+    let Some(uid) = get_user_id_by_name(username, db).await.unwrap() else {
+        error!("There's no user with the username {username:?}");
+        exit(1);
+    };
+
+    mark_email_verified(uid, db).await.unwrap();
+    info!("The email of user {username:?} has been marked as verified");
+}
+
+async fn logout_user(username: &str, db: &DatabaseConnection) {
+    let Some(uid) = get_user_id_by_name(username, db).await.unwrap() else {
+        error!("There's no user with the username {username:?}");
+        exit(1);
+    };
+
+    warn_about_session_store();
+    let count = end_user_sessions(uid, &Actor::cli(), db).await.unwrap();
+    info!("Ended {count} sessions of user {username:?}");
+}
+
+/// The session commands only reach sessions kept in the database
+fn warn_about_session_store() {
+    if let Ok(store) = env::var("SESSION_STORE")
+        && store != "database"
+    {
+        warn!(
+            "SESSION_STORE is {store:?}, but only sessions in the database can be reached from here. Use the API instead."
+        );
+    }
+}
+
+async fn list_service_tokens(db: &DatabaseConnection) {
+    for token in get_service_tokens(db).await.unwrap() {
+        let expires = token
+            .expires_at
+            .map_or("never".to_string(), |at| at.format("%Y-%m-%d").to_string());
+        let last_used = token.last_used_at.map_or("never".to_string(), |at| {
+            at.format("%Y-%m-%d %H:%M").to_string()
+        });
+        info!(
+            "{} {:?} user {}, scopes [{}], expires {expires}, last used {last_used}",
+            token.id,
+            token.name,
+            token.user_id,
+            token.scopes.join(", ")
+        );
+    }
+}
+
+async fn create_token(
+    username: &str,
+    name: String,
+    scopes: &[PermissionType],
+    expires: Option<NaiveDate>,
+    db: &DatabaseConnection,
+) {
+    let Some(uid) = get_user_id_by_name(username, db).await.unwrap() else {
+        error!("There's no user with the username {username:?}");
+        exit(1);
+    };
+
+    // The token stays valid until the end of the given day
+    let expires_at = expires.map(|day| day.and_hms_opt(23, 59, 59).unwrap().and_utc());
+    let (view, token) = create_service_token(uid, name, scopes, expires_at, &Actor::cli(), db)
+        .await
+        .unwrap_or_else(|e| {
+            error!("{e}");
+            exit(1);
+        });
+
+    info!(
+        "Service token {} has been created for user {username:?}",
+        view.id
+    );
+    info!("Send it as \"Authorization: Bearer {token}\". It can't be shown again.");
+}
+
+async fn list_roles(db: &DatabaseConnection) {
+    for role in get_all_roles(db).await.unwrap() {
+        info!("{:>3} {}", role.id, role.name);
+    }
+}
+
+async fn list_permissions(role: &Role, db: &DatabaseConnection) {
+    info!("Permissions of role {:?}:", role.name);
+    for view in get_role_permission_views(role.id, db).await.unwrap() {
+        let state = if view.enabled { "enabled" } else { "disabled" };
+        info!("  {:<20} {state}", view.permission);
+    }
+}
+
+async fn set_permission(
+    role: &Role,
+    permission: PermissionType,
+    enabled: bool,
+    db: &DatabaseConnection,
+) {
+    set_role_permission(role.id, permission, enabled, &Actor::cli(), db)
+        .await
+        .unwrap();
+    let state = if enabled { "enabled" } else { "disabled" };
+    info!("{permission:?} has been {state} for role {:?}", role.name);
+}
+
+async fn import(
+    file: &Path,
+    created_by: &str,
+    format: Option<ImportFormat>,
+    dry_run: bool,
+    db: &DatabaseConnection,
+) {
+    let format = format.unwrap_or_else(|| {
+        file.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ext.parse().ok())
+            .unwrap_or_else(|| {
+                error!("Can't detect the format of {file:?}, please pass --format");
+                exit(1);
+            })
+    });
+
+    let Some(created_by) = get_user_id_by_name(created_by, db).await.unwrap() else {
+        error!("There's no user with the username {created_by:?}");
+        exit(1);
+    };
+
+    let data = std::fs::read(file).unwrap_or_else(|e| {
+        error!("Couldn't read {file:?}: {e}");
+        exit(1);
+    });
+
+    let report = import_shifts(&data, format, created_by, dry_run, &Actor::cli(), db)
+        .await
+        .unwrap_or_else(|e| {
+            error!("{e}");
+            exit(1);
+        });
+
+    for row_error in &report.errors {
+        error!("Row {}: {}", row_error.row, row_error.message);
+    }
+
+    if !report.errors.is_empty() {
+        error!(
+            "Found {} errors in {} rows, no shifts have been added",
+            report.errors.len(),
+            report.rows
+        );
+        exit(1);
+    } else if dry_run {
+        info!("All {} rows are valid", report.rows);
+    } else {
+        info!("{} shifts have been added", report.imported);
+    }
+}
+
+async fn list_templates(db: &DatabaseConnection) {
+    for template in get_all_shift_templates(db).await.unwrap() {
+        let until = template
+            .ends_on
+            .map_or_else(|| "open end".to_string(), |day| day.to_string());
+        info!(
+            "{:>3} {} every {} days at {} for {} minutes, {} to {until}",
+            template.id,
+            template.name,
+            template.interval_days,
+            template.start_time,
+            template.duration_minutes,
+            template.starts_on,
+        );
+    }
+}
+
+async fn list_outbox(filter: OutboxFilter, db: &DatabaseConnection) {
+    for mail in get_outbox_mails(filter, db).await.unwrap() {
+        let status = if let Some(sent_at) = mail.sent_at {
+            format!("sent at {sent_at}")
+        } else if let Some(failed_at) = mail.failed_at {
+            format!("given up at {failed_at}")
+        } else {
+            format!("next attempt at {}", mail.next_attempt_at)
+        };
+        let last_error = mail
+            .last_error
+            .map(|e| format!(", last error: {e}"))
+            .unwrap_or_default();
+
+        info!(
+            "{:>4} to {:?}: {:?}, {} failed attempts, {status}{last_error}",
+            mail.id, mail.recipient, mail.subject, mail.attempts,
+        );
+    }
+}
+
+async fn expand_template(
+    template: u32,
+    from: NaiveDate,
+    until: NaiveDate,
+    db: &DatabaseConnection,
+) {
+    // The start times of templates are local to the event, same as in the API
+    let utc_offset = env::var("EVENT_UTC_OFFSET_HOURS")
+        .ok()
+        .and_then(|hours| parse_utc_offset(&hours))
+        .unwrap_or(FixedOffset::east_opt(0).expect("UTC is a valid offset"));
+
+    let shifts = expand_shift_template(template, from, until, utc_offset, &Actor::cli(), db)
+        .await
+        .unwrap_or_else(|e| {
+            error!("{e}");
+            exit(1);
+        });
+
+    for shift in &shifts {
+        info!(
+            "{} {} - {} {}",
+            shift.id, shift.starts_at, shift.ends_at, shift.name
+        );
+    }
+    info!("{} shifts have been added", shifts.len());
+}
+
+async fn list_lockouts(db: &DatabaseConnection) {
+    for lockout in get_login_lockouts(db).await.unwrap() {
+        let kind = match lockout.kind {
+            LoginFailureKind::Account => "username",
+            LoginFailureKind::Ip => "address",
+        };
+
+        info!(
+            "{kind} {:?}: {} failed logins, last at {}, blocked until {}",
+            lockout.key, lockout.failures, lockout.last_failure_at, lockout.blocked_until
+        );
+    }
+}
+
+async fn list_audit(cmd: cli::AuditCmd, db: &DatabaseConnection) {
+    let actor_id = match cmd.actor {
+        Some(username) => {
+            let Some(uid) = get_user_id_by_name(&username, db).await.unwrap() else {
+                error!("There's no user with the username {username:?}");
+                exit(1);
+            };
+            Some(uid)
+        }
+        None => None,
+    };
+
+    let query = AuditQuery {
+        actor_id,
+        origin: cmd.origin,
+        action: cmd.action,
+        target_type: cmd.target_type,
+        target_id: cmd.target,
+        before_id: cmd.before,
+        limit: cmd.limit,
+        ..Default::default()
+    };
+
+    for entry in get_audit_entries(&query, db).await.unwrap() {
+        let actor = entry
+            .actor_id
+            .map_or_else(|| "-".to_string(), |id| id.to_string());
+        info!(
+            "{:>5} {} {:<3} {actor} {} {} {}: {} -> {}",
+            entry.id,
+            entry.created_at,
+            entry.origin,
+            entry.action,
+            entry.target_type,
+            entry.target_id,
+            describe_state(entry.before),
+            describe_state(entry.after),
+        );
+    }
+}
+
+fn describe_state(state: Option<impl std::fmt::Display>) -> String {
+    state.map_or_else(|| "-".to_string(), |state| state.to_string())
 }
 
 async fn get_role(username: &str, db: &DatabaseConnection) {
     let role = get_role_by_username(username, db).await.unwrap();
-    info!("User {username:?} has role {role:?}");
+    info!("User {username:?} has role {:?}", role.name);
 }
 
 async fn create_dummy_users(amount: u32, db: &DatabaseConnection) {
@@ -1,13 +1,17 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
 use sea_orm::DeriveEntityModel;
 use sea_orm::prelude::async_trait::async_trait;
 use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, DeriveEntityModel)]
+#[derive(Clone, Debug, DeriveEntityModel, Serialize, Deserialize, JsonSchema, ApiComponent)]
 #[sea_orm(table_name = "angel_type")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = true)]
     pub id: u32,
     pub created_at: DateTimeUtc,
+    #[sea_orm(unique_key)]
     pub name: String,
     pub needs_introduction: bool,
 }
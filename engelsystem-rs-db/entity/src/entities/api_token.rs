@@ -0,0 +1,102 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Who manages a token
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    ApiComponent,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenKind {
+    /// Created by a user for their own scripts
+    #[sea_orm(string_value = "personal")]
+    Personal,
+    /// Created by an administrator for an integration, usually for an account of its own
+    #[sea_orm(string_value = "service")]
+    Service,
+}
+
+/// A token for the `Authorization: Bearer` header. Only a hash of it is stored.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "api_token")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    /// The user the token acts as
+    pub user_id: Uuid,
+    pub kind: ApiTokenKind,
+    pub name: String,
+    #[sea_orm(unique)]
+    pub token_hash: String,
+    /// The names of the permissions the token may use, as a JSON list
+    pub scopes: Json,
+    pub created_at: DateTimeUtc,
+    /// Unset for tokens that don't expire
+    pub expires_at: Option<DateTimeUtc>,
+    pub last_used_at: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// A token as shown to users, without its hash
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, ApiComponent)]
+pub struct View {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: ApiTokenKind,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTimeUtc,
+    pub expires_at: Option<DateTimeUtc>,
+    pub last_used_at: Option<DateTimeUtc>,
+}
+
+impl From<Model> for View {
+    fn from(token: Model) -> Self {
+        Self {
+            id: token.id,
+            user_id: token.user_id,
+            kind: token.kind,
+            name: token.name,
+            scopes: token
+                .scopes
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|scope| scope.as_str().map(str::to_string))
+                .collect(),
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+        }
+    }
+}
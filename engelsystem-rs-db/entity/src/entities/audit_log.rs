@@ -0,0 +1,171 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
+use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Where an audited change was made
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    ApiComponent,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOrigin {
+    /// A direct request to the API
+    #[sea_orm(string_value = "api")]
+    Api,
+    /// The command line tool, which works on the database without a logged in user
+    #[sea_orm(string_value = "cli")]
+    Cli,
+    /// The web frontend
+    #[sea_orm(string_value = "web")]
+    Web,
+}
+
+/// The kind of entity an audited change was made to
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    ApiComponent,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditTarget {
+    #[sea_orm(string_value = "shift")]
+    Shift,
+    #[sea_orm(string_value = "role")]
+    Role,
+    #[sea_orm(string_value = "user")]
+    User,
+    #[sea_orm(string_value = "api_token")]
+    ApiToken,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    ApiComponent,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(32))")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    #[sea_orm(string_value = "shift_created")]
+    ShiftCreated,
+    #[sea_orm(string_value = "shift_updated")]
+    ShiftUpdated,
+    #[sea_orm(string_value = "shift_deleted")]
+    ShiftDeleted,
+    /// An angel was marked as present, absent or partially present for a shift, or marked again
+    #[sea_orm(string_value = "shift_completion_changed")]
+    ShiftCompletionChanged,
+    #[sea_orm(string_value = "role_created")]
+    RoleCreated,
+    #[sea_orm(string_value = "role_renamed")]
+    RoleRenamed,
+    #[sea_orm(string_value = "role_deleted")]
+    RoleDeleted,
+    /// A permission of a role was enabled or disabled
+    #[sea_orm(string_value = "role_permission_changed")]
+    RolePermissionChanged,
+    /// A user got a different role
+    #[sea_orm(string_value = "user_role_changed")]
+    UserRoleChanged,
+    /// A user was logged out of all sessions by someone else
+    #[sea_orm(string_value = "user_sessions_ended")]
+    UserSessionsEnded,
+    /// A service token was created for a user
+    #[sea_orm(string_value = "service_token_created")]
+    ServiceTokenCreated,
+    /// A service token was revoked
+    #[sea_orm(string_value = "service_token_revoked")]
+    ServiceTokenRevoked,
+}
+
+impl AuditAction {
+    pub fn target(&self) -> AuditTarget {
+        match self {
+            Self::ShiftCreated
+            | Self::ShiftUpdated
+            | Self::ShiftDeleted
+            | Self::ShiftCompletionChanged => AuditTarget::Shift,
+            Self::RoleCreated
+            | Self::RoleRenamed
+            | Self::RoleDeleted
+            | Self::RolePermissionChanged => AuditTarget::Role,
+            Self::UserRoleChanged | Self::UserSessionsEnded => AuditTarget::User,
+            Self::ServiceTokenCreated | Self::ServiceTokenRevoked => AuditTarget::ApiToken,
+        }
+    }
+}
+
+/// Parses and prints the enums by their database value, e.g. `shift_created`
+macro_rules! string_value_conversions {
+    ($( $enum:ident ),*) => {
+        $(
+            impl std::str::FromStr for $enum {
+                type Err = DbErr;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    Self::try_from_value(&s.to_string())
+                }
+            }
+
+            impl std::fmt::Display for $enum {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.write_str(&self.to_value())
+                }
+            }
+        )*
+    };
+}
+
+string_value_conversions!(AuditOrigin, AuditTarget, AuditAction);
+
+/// An administrative change. Entries are never changed or removed.
+#[derive(Clone, Debug, DeriveEntityModel, Serialize, Deserialize, JsonSchema, ApiComponent)]
+#[sea_orm(table_name = "audit_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u32,
+    pub created_at: DateTimeUtc,
+    /// The user that made the change. Unset for changes through the command line tool.
+    pub actor_id: Option<Uuid>,
+    pub origin: AuditOrigin,
+    pub action: AuditAction,
+    pub target_type: AuditTarget,
+    pub target_id: String,
+    /// The affected state before the change, unset for created entities
+    pub before: Option<Json>,
+    /// The affected state after the change, unset for deleted entities
+    pub after: Option<Json>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,37 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
+use sea_orm::DeriveEntityModel;
+use sea_orm::prelude::async_trait::async_trait;
+use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A room or place where shifts happen
+#[derive(Clone, Debug, DeriveEntityModel, Serialize, Deserialize, JsonSchema, ApiComponent)]
+#[sea_orm(table_name = "location")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: u32,
+    pub created_at: DateTimeUtc,
+    #[sea_orm(unique_key)]
+    pub name: String,
+    pub description: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Link to the location on a map or a site plan
+    pub map_url: Option<String>,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::shift::Entity")]
+    Shift,
+}
+
+impl Related<super::shift::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Shift.def()
+    }
+}
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,51 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What failed logins are counted by
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    ApiComponent,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum LoginFailureKind {
+    /// The username that was tried, whether a user with it exists or not
+    #[sea_orm(string_value = "account")]
+    Account,
+    /// The IP address the login came from
+    #[sea_orm(string_value = "ip")]
+    Ip,
+}
+
+/// Failed logins in a row for one account or IP address
+#[derive(
+    Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize, JsonSchema, ApiComponent,
+)]
+#[sea_orm(table_name = "login_failure")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub kind: LoginFailureKind,
+    /// The username or IP address
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub key: String,
+    pub failures: u32,
+    pub last_failure_at: DateTimeUtc,
+    /// No logins are accepted until then, not even with the right password
+    pub blocked_until: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,28 @@
+use sea_orm::entity::prelude::*;
+
+/// A mail waiting to be sent, or one that was sent or given up on
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "mail_outbox")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: u32,
+    pub created_at: DateTimeUtc,
+    pub recipient: String,
+    pub subject: String,
+    /// Emptied once the mail was sent or given up on if it contains a secret
+    pub body: String,
+    /// The body holds a token, e.g. for a password reset
+    pub contains_secret: bool,
+    /// How often sending failed
+    pub attempts: u32,
+    pub next_attempt_at: DateTimeUtc,
+    pub last_error: Option<String>,
+    pub sent_at: Option<DateTimeUtc>,
+    /// Set once sending failed too often
+    pub failed_at: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,9 +1,22 @@
 pub mod angel_type;
+pub mod api_token;
+pub mod audit_log;
+pub mod calendar_token;
+pub mod email_verification_token;
+pub mod location;
+pub mod login_failure;
+pub mod mail_outbox;
+pub mod notification;
+pub mod password_reset_token;
 pub mod permission;
+pub mod reminder_preference;
 pub mod role;
 pub mod role_permission;
 pub mod session;
 pub mod shift;
+pub mod shift_completion;
+pub mod shift_template;
+pub mod shift_template_instance;
 pub mod user;
 pub mod user_angel_type;
 pub mod user_shift;
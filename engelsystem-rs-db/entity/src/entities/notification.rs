@@ -0,0 +1,88 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
+use sea_orm::ActiveValue::Set;
+use sea_orm::prelude::async_trait::async_trait;
+use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    ApiComponent,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(32))")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// The time, capacity or details of a shift the user signed up for changed
+    #[sea_orm(string_value = "shift_updated")]
+    ShiftUpdated,
+    /// A shift the user signed up for was deleted
+    #[sea_orm(string_value = "shift_cancelled")]
+    ShiftCancelled,
+    /// A shift the user signed up for starts soon
+    #[sea_orm(string_value = "shift_reminder")]
+    ShiftReminder,
+    /// A supporter confirmed the users membership in an angel type
+    #[sea_orm(string_value = "angel_type_confirmed")]
+    AngelTypeConfirmed,
+    /// The user was made supporter of an angel type, or is no longer one
+    #[sea_orm(string_value = "angel_type_supporter")]
+    AngelTypeSupporter,
+    /// The user got a different role
+    #[sea_orm(string_value = "role_changed")]
+    RoleChanged,
+}
+
+#[derive(Clone, Debug, DeriveEntityModel, Serialize, Deserialize, JsonSchema, ApiComponent)]
+#[sea_orm(table_name = "notification")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: NotificationKind,
+    /// The shift the notification is about. It is kept once the shift is deleted, so cancellations
+    /// still refer to the shift.
+    pub shift_id: Option<Uuid>,
+    pub message: String,
+    pub created_at: DateTimeUtc,
+    pub read_at: Option<DateTimeUtc>,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::shift::Entity",
+        from = "Column::ShiftId",
+        to = "super::shift::Column::Id"
+    )]
+    Shift,
+}
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _: &C, _: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if self.id.is_not_set() {
+            self.id = Set(Uuid::new_v4());
+        }
+
+        Ok(self)
+    }
+}
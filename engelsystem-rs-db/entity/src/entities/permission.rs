@@ -4,12 +4,21 @@ use sea_orm::entity::prelude::*;
 #[sea_orm(table_name = "permission")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    id: u32,
+    pub id: u32,
     #[sea_orm(unique_key)]
-    name: String,
+    pub name: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::role_permission::Entity")]
+    RolePermission,
+}
+
+impl Related<super::role_permission::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RolePermission.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
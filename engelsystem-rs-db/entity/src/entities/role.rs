@@ -1,8 +1,13 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub type RoleId = u32;
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize, JsonSchema, ApiComponent,
+)]
 #[sea_orm(table_name = "role")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -15,6 +20,8 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "super::user::Entity")]
     User,
+    #[sea_orm(has_many = "super::role_permission::Entity")]
+    RolePermission,
 }
 
 impl Related<super::user::Entity> for Entity {
@@ -23,4 +30,10 @@ impl Related<super::user::Entity> for Entity {
     }
 }
 
+impl Related<super::role_permission::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RolePermission.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
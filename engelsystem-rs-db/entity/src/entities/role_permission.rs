@@ -1,18 +1,53 @@
-use sea_orm::DeriveEntityModel;
-use sea_orm::prelude::async_trait::async_trait;
-use sea_orm::prelude::*;
+use apistos::ApiComponent;
+use schemars::JsonSchema;
+use sea_orm::FromQueryResult;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, DeriveEntityModel)]
+use super::role::RoleId;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
 #[sea_orm(table_name = "role_permission")]
 pub struct Model {
-    #[sea_orm(primary_key)]
-    pub user_id: Uuid,
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub role_id: RoleId,
+    #[sea_orm(primary_key, auto_increment = false)]
     pub permission_id: u32,
+    pub enabled: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::role::Entity",
+        from = "Column::RoleId",
+        to = "super::role::Column::Id"
+    )]
+    Role,
+    #[sea_orm(
+        belongs_to = "super::permission::Entity",
+        from = "Column::PermissionId",
+        to = "super::permission::Column::Id"
+    )]
+    Permission,
+}
+
+impl Related<super::role::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Role.def()
+    }
 }
 
-#[derive(Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+impl Related<super::permission::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Permission.def()
+    }
+}
 
-#[async_trait]
 impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(FromQueryResult, Serialize, Deserialize, Debug, JsonSchema, ApiComponent)]
+pub struct View {
+    pub permission: String,
+    pub enabled: bool,
+}
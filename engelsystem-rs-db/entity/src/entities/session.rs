@@ -11,6 +11,15 @@ pub struct Model {
     pub data: String,
     #[sea_orm(nullable)]
     pub expires_at: OffsetDateTime,
+    /// The logged in user, `None` for anonymous sessions
+    pub user_id: Option<Uuid>,
+    /// Identifies the session towards its user, as the id is the secret in the cookie
+    #[sea_orm(unique)]
+    pub public_id: Uuid,
+    pub last_active_at: Option<DateTimeUtc>,
+    /// The address of the client when the session was last saved
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
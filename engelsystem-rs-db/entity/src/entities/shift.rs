@@ -1,5 +1,7 @@
 use apistos::ApiComponent;
 use schemars::JsonSchema;
+use sea_orm::ActiveValue::Set;
+use sea_orm::FromQueryResult;
 use sea_orm::prelude::*;
 use sea_orm::{DeriveEntityModel, prelude::async_trait::async_trait};
 use serde::{Deserialize, Serialize};
@@ -19,6 +21,10 @@ pub struct Model {
     pub description: Option<String>,
     pub angels_needed: u32,
     pub angel_type_id: Option<u32>,
+    pub location_id: Option<u32>,
+    /// Incremented on every edit, used as the iCalendar `SEQUENCE`
+    pub sequence: u32,
+    pub updated_at: Option<DateTimeUtc>,
 }
 
 #[derive(Debug, EnumIter, DeriveRelation)]
@@ -30,6 +36,13 @@ pub enum Relation {
     )]
     AngelTypeId,
 
+    #[sea_orm(
+        belongs_to = "super::location::Entity",
+        from = "Column::LocationId",
+        to = "super::location::Column::Id"
+    )]
+    Location,
+
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::ManagedBy",
@@ -45,5 +58,47 @@ pub enum Relation {
     CreatedBy,
 }
 
+impl Related<super::location::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Location.def()
+    }
+}
+
 #[async_trait]
-impl ActiveModelBehavior for ActiveModel {}
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if self.id.is_not_set() {
+            self.id = Set(Uuid::new_v4());
+        }
+
+        if !insert {
+            if let Some(sequence) = self.sequence.try_as_ref() {
+                self.sequence = Set(sequence + 1);
+            }
+            self.updated_at = Set(Some(chrono::Utc::now()));
+        }
+
+        Ok(self)
+    }
+}
+
+#[derive(Clone, Debug, FromQueryResult, Serialize, Deserialize, JsonSchema, ApiComponent)]
+pub struct View {
+    pub id: Uuid,
+    pub created_at: DateTimeUtc,
+    pub created_by: Uuid,
+    pub managed_by: Option<Uuid>,
+    pub starts_at: DateTimeUtc,
+    pub ends_at: DateTimeUtc,
+    pub name: String,
+    pub description: Option<String>,
+    pub angels_needed: u32,
+    pub angel_type_id: Option<u32>,
+    pub location_id: Option<u32>,
+
+    pub signed_up: u32,
+    pub free_slots: u32,
+}
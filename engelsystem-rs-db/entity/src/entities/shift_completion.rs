@@ -0,0 +1,79 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    ApiComponent,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionStatus {
+    #[sea_orm(string_value = "present")]
+    Present,
+    #[sea_orm(string_value = "absent")]
+    Absent,
+    /// Only present for part of the shift, the worked hours are given explicitly
+    #[sea_orm(string_value = "partial")]
+    Partial,
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize, JsonSchema, ApiComponent,
+)]
+#[sea_orm(table_name = "shift_completion")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub shift_id: Uuid,
+    pub status: CompletionStatus,
+    /// The time the angel actually worked
+    pub worked_seconds: u32,
+    /// The worked time with multipliers like night shifts applied. This is added to
+    /// `user.shift_time`.
+    pub credited_seconds: u32,
+    pub points: u32,
+    pub completed_by: Option<Uuid>,
+    pub completed_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::shift::Entity",
+        from = "Column::ShiftId",
+        to = "super::shift::Column::Id"
+    )]
+    Shift,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::shift::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Shift.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
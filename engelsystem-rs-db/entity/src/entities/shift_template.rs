@@ -0,0 +1,55 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
+use sea_orm::DeriveEntityModel;
+use sea_orm::prelude::async_trait::async_trait;
+use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Describes a shift that repeats every `interval_days` days, starting on `starts_on`. The start
+/// time is in the local time of the event.
+#[derive(Clone, Debug, DeriveEntityModel, Serialize, Deserialize, JsonSchema, ApiComponent)]
+#[sea_orm(table_name = "shift_template")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: u32,
+    pub created_at: DateTimeUtc,
+    pub created_by: Uuid,
+    pub managed_by: Option<Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub angels_needed: u32,
+    pub angel_type_id: Option<u32>,
+    pub location_id: Option<u32>,
+    pub starts_on: ChronoDate,
+    /// The last day a shift may be generated for
+    pub ends_on: Option<ChronoDate>,
+    pub interval_days: u32,
+    pub start_time: ChronoTime,
+    pub duration_minutes: u32,
+}
+
+impl Model {
+    /// Whether the template generates a shift on the given day
+    pub fn occurs_on(&self, day: ChronoDate) -> bool {
+        let days = (day - self.starts_on).num_days();
+
+        days >= 0
+            && days % self.interval_days.max(1) as i64 == 0
+            && self.ends_on.is_none_or(|ends_on| day <= ends_on)
+    }
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::shift_template_instance::Entity")]
+    Instance,
+}
+
+impl Related<super::shift_template_instance::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Instance.def()
+    }
+}
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,47 @@
+use sea_orm::DeriveEntityModel;
+use sea_orm::prelude::async_trait::async_trait;
+use sea_orm::prelude::*;
+
+/// Links a shift to the template it was generated from
+#[derive(Clone, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "shift_template_instance")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub shift_id: Uuid,
+    pub template_id: u32,
+    /// The day of the event the shift was generated for
+    pub occurs_on: ChronoDate,
+}
+
+#[derive(Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::shift::Entity",
+        from = "Column::ShiftId",
+        to = "super::shift::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Shift,
+    #[sea_orm(
+        belongs_to = "super::shift_template::Entity",
+        from = "Column::TemplateId",
+        to = "super::shift_template::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Template,
+}
+
+impl Related<super::shift::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Shift.def()
+    }
+}
+
+impl Related<super::shift_template::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Template.def()
+    }
+}
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {}
@@ -25,6 +25,8 @@ pub struct Model {
 
     #[sea_orm(default_value = 0)]
     pub points: u32,
+    /// Unset until the user confirmed their email
+    pub email_verified_at: Option<DateTimeUtc>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -78,4 +80,5 @@ pub struct View {
     pub shift_time: u32,
 
     pub points: u32,
+    pub email_verified_at: Option<DateTimeUtc>,
 }
@@ -1,18 +1,69 @@
+use apistos::ApiComponent;
+use schemars::JsonSchema;
 use sea_orm::DeriveEntityModel;
+use sea_orm::FromQueryResult;
 use sea_orm::prelude::async_trait::async_trait;
 use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, DeriveEntityModel)]
 #[sea_orm(table_name = "user_angel_type")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub user_id: Uuid,
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub angel_type_id: u32,
+    pub created_at: DateTimeUtc,
+    pub confirmed_at: Option<DateTimeUtc>,
+    pub confirmed_by: Option<Uuid>,
+    pub supporter: bool,
+}
+
+impl Model {
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
 }
 
 #[derive(Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::angel_type::Entity",
+        from = "Column::AngelTypeId",
+        to = "super::angel_type::Column::Id"
+    )]
+    AngelType,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::angel_type::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AngelType.def()
+    }
+}
 
 #[async_trait]
 impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Clone, Debug, FromQueryResult, Serialize, Deserialize, JsonSchema, ApiComponent)]
+pub struct View {
+    pub user_id: Uuid,
+    pub username: String,
+    pub angel_type_id: u32,
+    pub angel_type: String,
+    pub created_at: DateTimeUtc,
+    pub confirmed_at: Option<DateTimeUtc>,
+    pub confirmed_by: Option<Uuid>,
+    pub supporter: bool,
+}
@@ -7,7 +7,11 @@ use sea_orm::prelude::*;
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub user_id: Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
     pub shift_id: Uuid,
+    pub created_at: DateTimeUtc,
+    /// When the angel was reminded of the shift, unset until then
+    pub reminded_at: Option<DateTimeUtc>,
 }
 
 #[derive(Debug, EnumIter, DeriveRelation)]
@@ -4,11 +4,26 @@ pub mod intern {
     pub use crate::entities::*;
 
     pub use angel_type::Entity as AngelType;
+    pub use api_token::Entity as ApiToken;
+    pub use audit_log::Entity as AuditLog;
+    pub use calendar_token::Entity as CalendarToken;
+    pub use email_verification_token::Entity as EmailVerificationToken;
+    pub use location::Entity as Location;
+    pub use login_failure::Entity as LoginFailure;
+    pub use mail_outbox::Entity as MailOutbox;
+    pub use notification::Entity as Notification;
+    pub use password_reset_token::Entity as PasswordResetToken;
     pub use permission::Entity as Permission;
+    pub use reminder_preference::Entity as ReminderPreference;
     pub use role::Entity as Role;
+    pub use role_permission::Entity as RolePermission;
     pub use session::Entity as Session;
     pub use shift::Entity as Shift;
+    pub use shift_completion::Entity as ShiftCompletion;
+    pub use shift_template::Entity as ShiftTemplate;
+    pub use shift_template_instance::Entity as ShiftTemplateInstance;
     pub use user::Entity as User;
+    pub use user_angel_type::Entity as UserAngelType;
     pub use user_shift::Entity as UserShift;
 }
 
@@ -19,21 +34,58 @@ pub mod public {
     pub use user::Model as User;
     pub use user::View as UserView;
 
+    pub use api_token::ApiTokenKind;
+    pub use api_token::Model as ApiToken;
+    pub use api_token::View as ApiTokenView;
+
+    pub use audit_log::Model as AuditEntry;
+    pub use audit_log::{AuditAction, AuditOrigin, AuditTarget};
+
+    pub use location::ActiveModel as ActiveLocation;
+    pub use location::Model as Location;
+
+    pub use login_failure::LoginFailureKind;
+    pub use login_failure::Model as LoginFailure;
+
+    pub use mail_outbox::Model as OutboxMail;
+
+    pub use notification::ActiveModel as ActiveNotification;
+    pub use notification::Model as Notification;
+    pub use notification::NotificationKind;
+
     pub use permission::ActiveModel as ActivePermission;
     pub use permission::Model as PermissionModel;
 
+    pub use reminder_preference::Model as ReminderPreference;
+
     pub use role::ActiveModel as ActiveRole;
     pub use role::Model as Role;
 
+    pub use role_permission::ActiveModel as ActiveRolePermission;
+    pub use role_permission::Model as RolePermission;
+    pub use role_permission::View as RolePermissionView;
+
     pub use session::ActiveModel as ActiveSession;
     pub use session::Model as Session;
 
     pub use shift::ActiveModel as ActiveShift;
     pub use shift::Model as Shift;
+    pub use shift::View as ShiftView;
+
+    pub use shift_completion::ActiveModel as ActiveShiftCompletion;
+    pub use shift_completion::CompletionStatus;
+    pub use shift_completion::Model as ShiftCompletion;
+
+    pub use shift_template::ActiveModel as ActiveShiftTemplate;
+    pub use shift_template::Model as ShiftTemplate;
 
     pub use user_shift::ActiveModel as ActiveUserShift;
     pub use user_shift::Model as UserShift;
 
     pub use angel_type::ActiveModel as ActiveAngelType;
     pub use angel_type::Model as AngelType;
+
+    pub use user_angel_type::ActiveModel as ActiveUserAngelType;
+    pub use user_angel_type::Model as UserAngelType;
+    pub use user_angel_type::View as UserAngelTypeView;
 }
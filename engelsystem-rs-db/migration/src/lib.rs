@@ -1,12 +1,50 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20250524_120831_initial;
+mod m20261018_100000_user_shift_keys;
+mod m20261018_110000_angel_type_membership;
+mod m20261018_120000_role_permissions;
+mod m20261018_130000_manage_roles_permission;
+mod m20261018_140000_shift_completion;
+mod m20261018_150000_calendar_token;
+mod m20261018_160000_notification;
+mod m20261018_170000_shift_template;
+mod m20261018_180000_location;
+mod m20261018_190000_session_user;
+mod m20261018_200000_password_reset_token;
+mod m20261018_210000_email_verification;
+mod m20261018_220000_mail_outbox;
+mod m20261018_230000_shift_reminder;
+mod m20261018_231000_audit_log;
+mod m20261018_232000_login_failure;
+mod m20261018_233000_session_metadata;
+mod m20261018_234000_api_token;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20250524_120831_initial::Migration)]
+        vec![
+            Box::new(m20250524_120831_initial::Migration),
+            Box::new(m20261018_100000_user_shift_keys::Migration),
+            Box::new(m20261018_110000_angel_type_membership::Migration),
+            Box::new(m20261018_120000_role_permissions::Migration),
+            Box::new(m20261018_130000_manage_roles_permission::Migration),
+            Box::new(m20261018_140000_shift_completion::Migration),
+            Box::new(m20261018_150000_calendar_token::Migration),
+            Box::new(m20261018_160000_notification::Migration),
+            Box::new(m20261018_170000_shift_template::Migration),
+            Box::new(m20261018_180000_location::Migration),
+            Box::new(m20261018_190000_session_user::Migration),
+            Box::new(m20261018_200000_password_reset_token::Migration),
+            Box::new(m20261018_210000_email_verification::Migration),
+            Box::new(m20261018_220000_mail_outbox::Migration),
+            Box::new(m20261018_230000_shift_reminder::Migration),
+            Box::new(m20261018_231000_audit_log::Migration),
+            Box::new(m20261018_232000_login_failure::Migration),
+            Box::new(m20261018_233000_session_metadata::Migration),
+            Box::new(m20261018_234000_api_token::Migration),
+        ]
     }
 }
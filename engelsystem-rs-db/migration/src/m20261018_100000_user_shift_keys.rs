@@ -0,0 +1,90 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::{Shift, User};
+
+/// The initial `user_shift` table stored the shift id as an integer, even though shifts are keyed
+/// by uuid. Nothing ever wrote to it, so it's recreated with the right column types.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserShift::Table).to_owned())
+            .await?;
+
+        let mut user_shift_user = ForeignKey::create()
+            .name("FK-user_shift-user")
+            .from(UserShift::Table, UserShift::UserId)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        let mut user_shift_shift = ForeignKey::create()
+            .name("FK-user_shift-shift")
+            .from(UserShift::Table, UserShift::ShiftId)
+            .to(Shift::Table, Shift::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserShift::Table)
+                    .if_not_exists()
+                    .col(uuid(UserShift::UserId))
+                    .col(uuid(UserShift::ShiftId))
+                    .col(timestamp(UserShift::CreatedAt).default(Expr::current_timestamp()))
+                    .primary_key(
+                        Index::create()
+                            .col(UserShift::UserId)
+                            .col(UserShift::ShiftId),
+                    )
+                    .foreign_key(&mut user_shift_user)
+                    .foreign_key(&mut user_shift_shift)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-user_shift-shift_id")
+                    .table(UserShift::Table)
+                    .col(UserShift::ShiftId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserShift::Table).to_owned())
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserShift::Table)
+                    .if_not_exists()
+                    .col(uuid(UserShift::UserId))
+                    .col(integer(UserShift::ShiftId))
+                    .primary_key(
+                        Index::create()
+                            .col(UserShift::UserId)
+                            .col(UserShift::ShiftId),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum UserShift {
+    Table,
+    UserId,
+    ShiftId,
+    CreatedAt,
+}
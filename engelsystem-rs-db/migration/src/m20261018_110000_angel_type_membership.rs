@@ -0,0 +1,94 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::{AngelType, User};
+
+/// Memberships in an angel type now have to be confirmed by a supporter if the angel type needs
+/// an introduction. The old table was never written to, so it's recreated with the new columns.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserAngelType::Table).to_owned())
+            .await?;
+
+        let mut user_angel_type_user = ForeignKey::create()
+            .name("FK-user_angel_type-user")
+            .from(UserAngelType::Table, UserAngelType::UserId)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        let mut user_angel_type_angel_type = ForeignKey::create()
+            .name("FK-user_angel_type-angel_type")
+            .from(UserAngelType::Table, UserAngelType::AngelTypeId)
+            .to(AngelType::Table, AngelType::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        let mut user_angel_type_confirmed_by = ForeignKey::create()
+            .name("FK-user_angel_type-confirmed_by")
+            .from(UserAngelType::Table, UserAngelType::ConfirmedBy)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::SetNull)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserAngelType::Table)
+                    .if_not_exists()
+                    .col(uuid(UserAngelType::UserId))
+                    .col(integer(UserAngelType::AngelTypeId))
+                    .col(timestamp(UserAngelType::CreatedAt).default(Expr::current_timestamp()))
+                    .col(timestamp_null(UserAngelType::ConfirmedAt))
+                    .col(uuid_null(UserAngelType::ConfirmedBy))
+                    .col(boolean(UserAngelType::Supporter).default(false))
+                    .primary_key(
+                        Index::create()
+                            .col(UserAngelType::UserId)
+                            .col(UserAngelType::AngelTypeId),
+                    )
+                    .foreign_key(&mut user_angel_type_user)
+                    .foreign_key(&mut user_angel_type_angel_type)
+                    .foreign_key(&mut user_angel_type_confirmed_by)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserAngelType::Table).to_owned())
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserAngelType::Table)
+                    .if_not_exists()
+                    .col(uuid(UserAngelType::UserId))
+                    .col(integer(UserAngelType::AngelTypeId))
+                    .primary_key(
+                        Index::create()
+                            .col(UserAngelType::UserId)
+                            .col(UserAngelType::AngelTypeId),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum UserAngelType {
+    Table,
+    UserId,
+    AngelTypeId,
+    CreatedAt,
+    ConfirmedAt,
+    ConfirmedBy,
+    Supporter,
+}
@@ -0,0 +1,83 @@
+use entity::intern::*;
+use sea_orm::entity::*;
+use sea_orm::query::*;
+use sea_orm_migration::prelude::*;
+
+const PERMISSION_NAMES: [&str; 5] = [
+    "ViewUsers",
+    "SignUpForShifts",
+    "ManageShifts",
+    "JoinAngelTypes",
+    "ManageAngelTypes",
+];
+
+/// Permissions that are enabled for the default "User" role. The "Administrator" role gets every
+/// permission, the "Guest" role none.
+const USER_PERMISSION_NAMES: [&str; 2] = ["SignUpForShifts", "JoinAngelTypes"];
+
+/// Seeds the permissions the API checks for and fills `role_permission` for the default roles, so
+/// that every role has an explicit entry for every permission.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+
+        for permission in PERMISSION_NAMES {
+            permission::ActiveModel {
+                id: NotSet,
+                name: Set(permission.to_string()),
+            }
+            .insert(conn)
+            .await?;
+        }
+
+        let roles = Role::find().all(conn).await?;
+        let permissions = Permission::find().all(conn).await?;
+
+        for role in &roles {
+            for permission in &permissions {
+                let enabled = match role.name.as_str() {
+                    "Administrator" => true,
+                    "User" => USER_PERMISSION_NAMES.contains(&permission.name.as_str()),
+                    _ => false,
+                };
+
+                role_permission::ActiveModel {
+                    role_id: Set(role.id),
+                    permission_id: Set(permission.id),
+                    enabled: Set(enabled),
+                }
+                .insert(conn)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+
+        let permission_ids: Vec<u32> = Permission::find()
+            .filter(permission::Column::Name.is_in(PERMISSION_NAMES))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|permission| permission.id)
+            .collect();
+
+        RolePermission::delete_many()
+            .filter(role_permission::Column::PermissionId.is_in(permission_ids))
+            .exec(conn)
+            .await?;
+        Permission::delete_many()
+            .filter(permission::Column::Name.is_in(PERMISSION_NAMES))
+            .exec(conn)
+            .await?;
+
+        Ok(())
+    }
+}
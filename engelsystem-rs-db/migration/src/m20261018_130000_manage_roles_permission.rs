@@ -0,0 +1,56 @@
+use entity::intern::*;
+use sea_orm::entity::*;
+use sea_orm::query::*;
+use sea_orm_migration::prelude::*;
+
+const PERMISSION_NAME: &str = "ManageRoles";
+
+/// Adds the permission to manage roles and enables it for the "Administrator" role
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+
+        let permission = permission::ActiveModel {
+            id: NotSet,
+            name: Set(PERMISSION_NAME.to_string()),
+        }
+        .insert(conn)
+        .await?;
+
+        for role in Role::find().all(conn).await? {
+            role_permission::ActiveModel {
+                role_id: Set(role.id),
+                permission_id: Set(permission.id),
+                enabled: Set(role.name == "Administrator"),
+            }
+            .insert(conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+
+        let Some(permission) = Permission::find()
+            .filter(permission::Column::Name.eq(PERMISSION_NAME))
+            .one(conn)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        RolePermission::delete_many()
+            .filter(role_permission::Column::PermissionId.eq(permission.id))
+            .exec(conn)
+            .await?;
+        Permission::delete_by_id(permission.id).exec(conn).await?;
+
+        Ok(())
+    }
+}
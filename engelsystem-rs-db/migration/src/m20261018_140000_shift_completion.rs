@@ -0,0 +1,74 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::User;
+use crate::m20261018_100000_user_shift_keys::UserShift;
+
+/// Records how each signed up angel completed a shift and what they were credited for it. The
+/// credited values are kept so `user.shift_time` and `user.points` can be recomputed from them.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut shift_completion_user_shift = ForeignKey::create()
+            .name("FK-shift_completion-user_shift")
+            .from(
+                ShiftCompletion::Table,
+                (ShiftCompletion::UserId, ShiftCompletion::ShiftId),
+            )
+            .to(UserShift::Table, (UserShift::UserId, UserShift::ShiftId))
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        let mut shift_completion_completed_by = ForeignKey::create()
+            .name("FK-shift_completion-completed_by")
+            .from(ShiftCompletion::Table, ShiftCompletion::CompletedBy)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::SetNull)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShiftCompletion::Table)
+                    .if_not_exists()
+                    .col(uuid(ShiftCompletion::UserId))
+                    .col(uuid(ShiftCompletion::ShiftId))
+                    .col(string_len(ShiftCompletion::Status, 16))
+                    .col(integer(ShiftCompletion::WorkedSeconds))
+                    .col(integer(ShiftCompletion::CreditedSeconds))
+                    .col(integer(ShiftCompletion::Points))
+                    .col(uuid_null(ShiftCompletion::CompletedBy))
+                    .col(timestamp(ShiftCompletion::CompletedAt).default(Expr::current_timestamp()))
+                    .primary_key(
+                        Index::create()
+                            .col(ShiftCompletion::UserId)
+                            .col(ShiftCompletion::ShiftId),
+                    )
+                    .foreign_key(&mut shift_completion_user_shift)
+                    .foreign_key(&mut shift_completion_completed_by)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ShiftCompletion::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum ShiftCompletion {
+    Table,
+    UserId,
+    ShiftId,
+    Status,
+    WorkedSeconds,
+    CreditedSeconds,
+    Points,
+    CompletedBy,
+    CompletedAt,
+}
@@ -0,0 +1,89 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::{Shift, User};
+
+/// Adds secret tokens for the calendar feed of each user and tracks edits of shifts, so calendar
+/// apps pick up changed shifts.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut calendar_token_user = ForeignKey::create()
+            .name("FK-calendar_token-user")
+            .from(CalendarToken::Table, CalendarToken::UserId)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(CalendarToken::Table)
+                    .if_not_exists()
+                    .col(uuid(CalendarToken::UserId).primary_key())
+                    .col(string_uniq(CalendarToken::TokenHash))
+                    .col(timestamp(CalendarToken::CreatedAt).default(Expr::current_timestamp()))
+                    .foreign_key(&mut calendar_token_user)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Shift::Table)
+                    .add_column(integer(ShiftRevision::Sequence).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Shift::Table)
+                    .add_column(timestamp_null(ShiftRevision::UpdatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Shift::Table)
+                    .drop_column(ShiftRevision::UpdatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Shift::Table)
+                    .drop_column(ShiftRevision::Sequence)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(CalendarToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum CalendarToken {
+    Table,
+    UserId,
+    TokenHash,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ShiftRevision {
+    Sequence,
+    UpdatedAt,
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::User;
+
+/// Stores notifications for users, e.g. about changes to shifts they signed up for
+///
+/// The shift id isn't a foreign key, so a cancellation still refers to the shift once it is
+/// deleted.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut notification_user = ForeignKey::create()
+            .name("FK-notification-user")
+            .from(Notification::Table, Notification::UserId)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Notification::Table)
+                    .if_not_exists()
+                    .col(uuid(Notification::Id).primary_key())
+                    .col(uuid(Notification::UserId))
+                    .col(string_len(Notification::Kind, 32))
+                    .col(uuid_null(Notification::ShiftId))
+                    .col(text(Notification::Message))
+                    .col(timestamp(Notification::CreatedAt).default(Expr::current_timestamp()))
+                    .col(timestamp_null(Notification::ReadAt))
+                    .foreign_key(&mut notification_user)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-notification-user_id")
+                    .table(Notification::Table)
+                    .col(Notification::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Notification::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Notification {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    ShiftId,
+    Message,
+    CreatedAt,
+    ReadAt,
+}
@@ -0,0 +1,136 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::{AngelType, Shift, User};
+
+/// Adds templates for recurring shifts and links the shifts generated from them back to the
+/// template and the day they were generated for.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut shift_template_angel_type = ForeignKey::create()
+            .name("FK-shift_template-angel_type")
+            .from(ShiftTemplate::Table, ShiftTemplate::AngelTypeId)
+            .to(AngelType::Table, AngelType::Id)
+            .to_owned();
+
+        let mut shift_template_managed_by = ForeignKey::create()
+            .name("FK-shift_template-managed_by")
+            .from(ShiftTemplate::Table, ShiftTemplate::ManagedBy)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::SetNull)
+            .to_owned();
+
+        let mut shift_template_created_by = ForeignKey::create()
+            .name("FK-shift_template-created_by")
+            .from(ShiftTemplate::Table, ShiftTemplate::CreatedBy)
+            .to(User::Table, User::Id)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShiftTemplate::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ShiftTemplate::Id))
+                    .col(timestamp(ShiftTemplate::CreatedAt).default(Expr::current_timestamp()))
+                    .col(uuid(ShiftTemplate::CreatedBy))
+                    .col(uuid_null(ShiftTemplate::ManagedBy))
+                    .col(string(ShiftTemplate::Name))
+                    .col(string_null(ShiftTemplate::Description))
+                    .col(integer(ShiftTemplate::AngelsNeeded))
+                    .col(integer_null(ShiftTemplate::AngelTypeId))
+                    .col(date(ShiftTemplate::StartsOn))
+                    .col(date_null(ShiftTemplate::EndsOn))
+                    .col(integer(ShiftTemplate::IntervalDays))
+                    .col(time(ShiftTemplate::StartTime))
+                    .col(integer(ShiftTemplate::DurationMinutes))
+                    .foreign_key(&mut shift_template_angel_type)
+                    .foreign_key(&mut shift_template_managed_by)
+                    .foreign_key(&mut shift_template_created_by)
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut instance_shift = ForeignKey::create()
+            .name("FK-shift_template_instance-shift")
+            .from(ShiftTemplateInstance::Table, ShiftTemplateInstance::ShiftId)
+            .to(Shift::Table, Shift::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        let mut instance_template = ForeignKey::create()
+            .name("FK-shift_template_instance-template")
+            .from(
+                ShiftTemplateInstance::Table,
+                ShiftTemplateInstance::TemplateId,
+            )
+            .to(ShiftTemplate::Table, ShiftTemplate::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShiftTemplateInstance::Table)
+                    .if_not_exists()
+                    .col(uuid(ShiftTemplateInstance::ShiftId).primary_key())
+                    .col(integer(ShiftTemplateInstance::TemplateId))
+                    .col(date(ShiftTemplateInstance::OccursOn))
+                    .foreign_key(&mut instance_shift)
+                    .foreign_key(&mut instance_template)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-shift_template_instance-template_id-occurs_on")
+                    .table(ShiftTemplateInstance::Table)
+                    .col(ShiftTemplateInstance::TemplateId)
+                    .col(ShiftTemplateInstance::OccursOn)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ShiftTemplateInstance::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ShiftTemplate::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum ShiftTemplate {
+    Table,
+    Id,
+    CreatedAt,
+    CreatedBy,
+    ManagedBy,
+    Name,
+    Description,
+    AngelsNeeded,
+    AngelTypeId,
+    StartsOn,
+    EndsOn,
+    IntervalDays,
+    StartTime,
+    DurationMinutes,
+}
+
+#[derive(DeriveIden)]
+pub enum ShiftTemplateInstance {
+    Table,
+    ShiftId,
+    TemplateId,
+    OccursOn,
+}
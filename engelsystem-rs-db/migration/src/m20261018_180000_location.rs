@@ -0,0 +1,151 @@
+use entity::intern::*;
+use sea_orm::entity::*;
+use sea_orm::query::*;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::Shift;
+use crate::m20261018_170000_shift_template::ShiftTemplate;
+
+const PERMISSION_NAME: &str = "ManageLocations";
+
+/// Adds locations that shifts and shift templates can refer to, and the permission to manage them,
+/// which is enabled for the "Administrator" role.
+///
+/// SQLite can't add foreign keys to existing tables, so the `location_id` columns are checked when
+/// shifts are written and locations that are still in use can't be deleted.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Location::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Location::Id))
+                    .col(timestamp(Location::CreatedAt).default(Expr::current_timestamp()))
+                    .col(string_uniq(Location::Name))
+                    .col(string_null(Location::Description))
+                    .col(double_null(Location::Latitude))
+                    .col(double_null(Location::Longitude))
+                    .col(string_null(Location::MapUrl))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Shift::Table)
+                    .add_column(integer_null(LocationRef::LocationId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-shift-location_id")
+                    .table(Shift::Table)
+                    .col(LocationRef::LocationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShiftTemplate::Table)
+                    .add_column(integer_null(LocationRef::LocationId))
+                    .to_owned(),
+            )
+            .await?;
+
+        let conn = manager.get_connection();
+
+        let permission = permission::ActiveModel {
+            id: NotSet,
+            name: Set(PERMISSION_NAME.to_string()),
+        }
+        .insert(conn)
+        .await?;
+
+        for role in Role::find().all(conn).await? {
+            role_permission::ActiveModel {
+                role_id: Set(role.id),
+                permission_id: Set(permission.id),
+                enabled: Set(role.name == "Administrator"),
+            }
+            .insert(conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+
+        if let Some(permission) = Permission::find()
+            .filter(permission::Column::Name.eq(PERMISSION_NAME))
+            .one(conn)
+            .await?
+        {
+            RolePermission::delete_many()
+                .filter(role_permission::Column::PermissionId.eq(permission.id))
+                .exec(conn)
+                .await?;
+            Permission::delete_by_id(permission.id).exec(conn).await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShiftTemplate::Table)
+                    .drop_column(LocationRef::LocationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("IDX-shift-location_id")
+                    .table(Shift::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Shift::Table)
+                    .drop_column(LocationRef::LocationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Location::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Location {
+    Table,
+    Id,
+    CreatedAt,
+    Name,
+    Description,
+    Latitude,
+    Longitude,
+    MapUrl,
+}
+
+#[derive(DeriveIden)]
+enum LocationRef {
+    LocationId,
+}
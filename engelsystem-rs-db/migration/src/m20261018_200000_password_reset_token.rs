@@ -0,0 +1,62 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::User;
+
+/// Stores the hashes of tokens that allow resetting the password of a user
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut password_reset_token_user = ForeignKey::create()
+            .name("FK-password_reset_token-user")
+            .from(PasswordResetToken::Table, PasswordResetToken::UserId)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PasswordResetToken::Table)
+                    .if_not_exists()
+                    .col(string(PasswordResetToken::TokenHash).primary_key())
+                    .col(uuid(PasswordResetToken::UserId))
+                    .col(
+                        timestamp(PasswordResetToken::CreatedAt).default(Expr::current_timestamp()),
+                    )
+                    .col(timestamp(PasswordResetToken::ExpiresAt))
+                    .col(timestamp_null(PasswordResetToken::UsedAt))
+                    .foreign_key(&mut password_reset_token_user)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-password_reset_token-user_id")
+                    .table(PasswordResetToken::Table)
+                    .col(PasswordResetToken::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PasswordResetToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum PasswordResetToken {
+    Table,
+    TokenHash,
+    UserId,
+    CreatedAt,
+    ExpiresAt,
+    UsedAt,
+}
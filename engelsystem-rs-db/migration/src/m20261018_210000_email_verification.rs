@@ -0,0 +1,101 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::User;
+
+/// Tracks whether users confirmed their email, and stores the hashes of the tokens sent to confirm
+/// it. Users that already exist are treated as verified.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(timestamp_null(UserVerification::EmailVerifiedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(User::Table)
+                    .value(UserVerification::EmailVerifiedAt, Expr::current_timestamp())
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut email_verification_token_user = ForeignKey::create()
+            .name("FK-email_verification_token-user")
+            .from(
+                EmailVerificationToken::Table,
+                EmailVerificationToken::UserId,
+            )
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailVerificationToken::Table)
+                    .if_not_exists()
+                    .col(string(EmailVerificationToken::TokenHash).primary_key())
+                    .col(uuid(EmailVerificationToken::UserId))
+                    .col(
+                        timestamp(EmailVerificationToken::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(timestamp(EmailVerificationToken::ExpiresAt))
+                    .foreign_key(&mut email_verification_token_user)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-email_verification_token-user_id")
+                    .table(EmailVerificationToken::Table)
+                    .col(EmailVerificationToken::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(EmailVerificationToken::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(UserVerification::EmailVerifiedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum EmailVerificationToken {
+    Table,
+    TokenHash,
+    UserId,
+    CreatedAt,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum UserVerification {
+    EmailVerifiedAt,
+}
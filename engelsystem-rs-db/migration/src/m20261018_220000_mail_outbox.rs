@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Stores outgoing mails until they were delivered, so they can be retried when sending fails.
+/// Mails with a token in their body are marked, the body is removed once they were sent or given
+/// up on.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MailOutbox::Table)
+                    .if_not_exists()
+                    .col(pk_auto(MailOutbox::Id))
+                    .col(timestamp(MailOutbox::CreatedAt).default(Expr::current_timestamp()))
+                    .col(string(MailOutbox::Recipient))
+                    .col(string(MailOutbox::Subject))
+                    .col(text(MailOutbox::Body))
+                    .col(integer(MailOutbox::Attempts).default(0))
+                    .col(timestamp(MailOutbox::NextAttemptAt).default(Expr::current_timestamp()))
+                    .col(text_null(MailOutbox::LastError))
+                    .col(timestamp_null(MailOutbox::SentAt))
+                    .col(timestamp_null(MailOutbox::FailedAt))
+                    .col(boolean(MailOutbox::ContainsSecret).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-mail_outbox-next_attempt_at")
+                    .table(MailOutbox::Table)
+                    .col(MailOutbox::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MailOutbox::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum MailOutbox {
+    Table,
+    Id,
+    CreatedAt,
+    Recipient,
+    Subject,
+    Body,
+    Attempts,
+    NextAttemptAt,
+    LastError,
+    SentAt,
+    FailedAt,
+    ContainsSecret,
+}
@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::User;
+use crate::m20261018_100000_user_shift_keys::UserShift;
+
+/// Records which angels were already reminded of their shifts and stores how each user wants to be
+/// reminded. Users without stored preferences get reminders with the default lead time.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserShift::Table)
+                    .add_column(timestamp_null(UserShiftReminder::RemindedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut reminder_preference_user = ForeignKey::create()
+            .name("FK-reminder_preference-user")
+            .from(ReminderPreference::Table, ReminderPreference::UserId)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReminderPreference::Table)
+                    .if_not_exists()
+                    .col(uuid(ReminderPreference::UserId).primary_key())
+                    .col(boolean(ReminderPreference::Enabled).default(true))
+                    .col(integer_null(ReminderPreference::LeadMinutes))
+                    .foreign_key(&mut reminder_preference_user)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ReminderPreference::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserShift::Table)
+                    .drop_column(UserShiftReminder::RemindedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum ReminderPreference {
+    Table,
+    UserId,
+    Enabled,
+    LeadMinutes,
+}
+
+#[derive(DeriveIden)]
+enum UserShiftReminder {
+    RemindedAt,
+}
@@ -0,0 +1,151 @@
+use entity::intern::*;
+use sea_orm::entity::*;
+use sea_orm::query::*;
+use sea_orm::DbBackend;
+use sea_orm_migration::{prelude::*, schema::*};
+
+const PERMISSION_NAME: &str = "ViewAuditLog";
+
+/// Adds the audit log of administrative actions and the permission to read it, which is enabled
+/// for the "Administrator" role.
+///
+/// The log is append-only. Triggers refuse to change or remove entries, so not even a bug in the
+/// application can rewrite history. The actor isn't a foreign key, so entries outlive their users.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(pk_auto(AuditLog::Id))
+                    .col(timestamp(AuditLog::CreatedAt).default(Expr::current_timestamp()))
+                    .col(uuid_null(AuditLog::ActorId))
+                    .col(string_len(AuditLog::Origin, 16))
+                    .col(string_len(AuditLog::Action, 32))
+                    .col(string_len(AuditLog::TargetType, 16))
+                    .col(string(AuditLog::TargetId))
+                    .col(json_null(AuditLog::Before))
+                    .col(json_null(AuditLog::After))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-audit_log-target")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::TargetType)
+                    .col(AuditLog::TargetId)
+                    .to_owned(),
+            )
+            .await?;
+
+        let conn = manager.get_connection();
+
+        for statement in append_only_triggers(manager.get_database_backend()) {
+            conn.execute_unprepared(&statement).await?;
+        }
+
+        let permission = permission::ActiveModel {
+            id: NotSet,
+            name: Set(PERMISSION_NAME.to_string()),
+        }
+        .insert(conn)
+        .await?;
+
+        for role in Role::find().all(conn).await? {
+            role_permission::ActiveModel {
+                role_id: Set(role.id),
+                permission_id: Set(permission.id),
+                enabled: Set(role.name == "Administrator"),
+            }
+            .insert(conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+
+        if let Some(permission) = Permission::find()
+            .filter(permission::Column::Name.eq(PERMISSION_NAME))
+            .one(conn)
+            .await?
+        {
+            RolePermission::delete_many()
+                .filter(role_permission::Column::PermissionId.eq(permission.id))
+                .exec(conn)
+                .await?;
+            Permission::delete_by_id(permission.id).exec(conn).await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await?;
+
+        // The triggers went with the table, only Postgres keeps their function
+        if manager.get_database_backend() == DbBackend::Postgres {
+            conn.execute_unprepared("DROP FUNCTION IF EXISTS audit_log_append_only()")
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The statements creating the triggers that refuse to change or remove log entries
+fn append_only_triggers(backend: DbBackend) -> Vec<String> {
+    const MESSAGE: &str = "the audit log is append-only";
+
+    match backend {
+        DbBackend::Sqlite => ["UPDATE", "DELETE"]
+            .map(|event| {
+                format!(
+                    "CREATE TRIGGER IF NOT EXISTS audit_log_no_{} BEFORE {event} ON audit_log \
+                     BEGIN SELECT RAISE(ABORT, '{MESSAGE}'); END",
+                    event.to_lowercase()
+                )
+            })
+            .into(),
+        DbBackend::MySql => ["UPDATE", "DELETE"]
+            .map(|event| {
+                format!(
+                    "CREATE TRIGGER audit_log_no_{} BEFORE {event} ON audit_log \
+                     FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = '{MESSAGE}'",
+                    event.to_lowercase()
+                )
+            })
+            .into(),
+        DbBackend::Postgres => vec![
+            format!(
+                "CREATE OR REPLACE FUNCTION audit_log_append_only() RETURNS trigger AS $$ \
+                 BEGIN RAISE EXCEPTION '{MESSAGE}'; END $$ LANGUAGE plpgsql"
+            ),
+            "CREATE TRIGGER audit_log_append_only BEFORE UPDATE OR DELETE ON audit_log \
+             FOR EACH ROW EXECUTE FUNCTION audit_log_append_only()"
+                .to_string(),
+        ],
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum AuditLog {
+    Table,
+    Id,
+    CreatedAt,
+    ActorId,
+    Origin,
+    Action,
+    TargetType,
+    TargetId,
+    Before,
+    After,
+}
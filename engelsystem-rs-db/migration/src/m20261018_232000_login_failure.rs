@@ -0,0 +1,89 @@
+use entity::intern::*;
+use sea_orm::entity::*;
+use sea_orm::query::*;
+use sea_orm_migration::{prelude::*, schema::*};
+
+const PERMISSION_NAME: &str = "ViewLoginLockouts";
+
+/// Adds the counters of failed logins per account and per IP address, and the permission to see
+/// the active lockouts, which is enabled for the "Administrator" role.
+///
+/// Accounts are counted by the username that was tried, whether it exists or not, so the counters
+/// don't tell which usernames are taken.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginFailure::Table)
+                    .if_not_exists()
+                    .col(string_len(LoginFailure::Kind, 16))
+                    .col(string(LoginFailure::Key))
+                    .col(unsigned(LoginFailure::Failures))
+                    .col(timestamp(LoginFailure::LastFailureAt))
+                    .col(timestamp(LoginFailure::BlockedUntil))
+                    .primary_key(
+                        Index::create()
+                            .col(LoginFailure::Kind)
+                            .col(LoginFailure::Key),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let conn = manager.get_connection();
+
+        let permission = permission::ActiveModel {
+            id: NotSet,
+            name: Set(PERMISSION_NAME.to_string()),
+        }
+        .insert(conn)
+        .await?;
+
+        for role in Role::find().all(conn).await? {
+            role_permission::ActiveModel {
+                role_id: Set(role.id),
+                permission_id: Set(permission.id),
+                enabled: Set(role.name == "Administrator"),
+            }
+            .insert(conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+
+        if let Some(permission) = Permission::find()
+            .filter(permission::Column::Name.eq(PERMISSION_NAME))
+            .one(conn)
+            .await?
+        {
+            RolePermission::delete_many()
+                .filter(role_permission::Column::PermissionId.eq(permission.id))
+                .exec(conn)
+                .await?;
+            Permission::delete_by_id(permission.id).exec(conn).await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(LoginFailure::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum LoginFailure {
+    Table,
+    Kind,
+    Key,
+    Failures,
+    LastFailureAt,
+    BlockedUntil,
+}
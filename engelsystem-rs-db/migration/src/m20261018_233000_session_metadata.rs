@@ -0,0 +1,140 @@
+use entity::intern::*;
+use sea_orm::entity::*;
+use sea_orm::prelude::Uuid;
+use sea_orm::query::*;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::Session;
+
+const PERMISSION_NAME: &str = "EndUserSessions";
+
+/// Records when a session was last used, and from which address and browser, so users can see
+/// their logins and end the ones they don't recognize. Sessions get a public id for that, as the
+/// session key is the secret in the cookie. Also adds the permission to log out other users,
+/// which is enabled for the "Administrator" role.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can only add one column per statement
+        for column in [
+            uuid_null(SessionMetadata::PublicId),
+            timestamp_null(SessionMetadata::LastActiveAt),
+            string_null(SessionMetadata::Ip),
+            string_null(SessionMetadata::UserAgent),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Session::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        let conn = manager.get_connection();
+        let builder = conn.get_database_backend();
+
+        let existing = conn
+            .query_all(builder.build(Query::select().column(Session::Id).from(Session::Table)))
+            .await?;
+
+        for row in existing {
+            let id: String = row.try_get("", "id")?;
+
+            conn.execute(
+                builder.build(
+                    Query::update()
+                        .table(Session::Table)
+                        .value(SessionMetadata::PublicId, Uuid::new_v4())
+                        .and_where(Expr::col(Session::Id).eq(id)),
+                ),
+            )
+            .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-session-public_id")
+                    .table(Session::Table)
+                    .col(SessionMetadata::PublicId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        let permission = permission::ActiveModel {
+            id: NotSet,
+            name: Set(PERMISSION_NAME.to_string()),
+        }
+        .insert(conn)
+        .await?;
+
+        for role in Role::find().all(conn).await? {
+            role_permission::ActiveModel {
+                role_id: Set(role.id),
+                permission_id: Set(permission.id),
+                enabled: Set(role.name == "Administrator"),
+            }
+            .insert(conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+
+        if let Some(permission) = Permission::find()
+            .filter(permission::Column::Name.eq(PERMISSION_NAME))
+            .one(conn)
+            .await?
+        {
+            RolePermission::delete_many()
+                .filter(role_permission::Column::PermissionId.eq(permission.id))
+                .exec(conn)
+                .await?;
+            Permission::delete_by_id(permission.id).exec(conn).await?;
+        }
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("IDX-session-public_id")
+                    .table(Session::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in [
+            SessionMetadata::PublicId,
+            SessionMetadata::LastActiveAt,
+            SessionMetadata::Ip,
+            SessionMetadata::UserAgent,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Session::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SessionMetadata {
+    PublicId,
+    LastActiveAt,
+    Ip,
+    UserAgent,
+}
@@ -0,0 +1,114 @@
+use entity::intern::{permission, role_permission, Permission, Role, RolePermission};
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, EntityTrait, QueryFilter,
+};
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250524_120831_initial::User;
+
+const PERMISSION_NAME: &str = "ManageApiTokens";
+
+/// Adds tokens for scripts and integrations, sent as `Authorization: Bearer`. Personal tokens are
+/// created by users for themselves, service tokens by administrators. Only a hash of each token is
+/// stored, with the permissions it may use as a JSON list of names. Also adds the permission to
+/// manage service tokens, which is enabled for the "Administrator" role.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut api_token_user = ForeignKey::create()
+            .name("FK-api_token-user")
+            .from(ApiToken::Table, ApiToken::UserId)
+            .to(User::Table, User::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiToken::Table)
+                    .if_not_exists()
+                    .col(uuid(ApiToken::Id).primary_key())
+                    .col(uuid(ApiToken::UserId))
+                    .col(string_len(ApiToken::Kind, 16))
+                    .col(string(ApiToken::Name))
+                    .col(string_uniq(ApiToken::TokenHash))
+                    .col(json(ApiToken::Scopes))
+                    .col(timestamp(ApiToken::CreatedAt).default(Expr::current_timestamp()))
+                    .col(timestamp_null(ApiToken::ExpiresAt))
+                    .col(timestamp_null(ApiToken::LastUsedAt))
+                    .foreign_key(&mut api_token_user)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-api_token-user_id")
+                    .table(ApiToken::Table)
+                    .col(ApiToken::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        let conn = manager.get_connection();
+
+        let permission = permission::ActiveModel {
+            id: NotSet,
+            name: Set(PERMISSION_NAME.to_string()),
+        }
+        .insert(conn)
+        .await?;
+
+        for role in Role::find().all(conn).await? {
+            role_permission::ActiveModel {
+                role_id: Set(role.id),
+                permission_id: Set(permission.id),
+                enabled: Set(role.name == "Administrator"),
+            }
+            .insert(conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+
+        if let Some(permission) = Permission::find()
+            .filter(permission::Column::Name.eq(PERMISSION_NAME))
+            .one(conn)
+            .await?
+        {
+            RolePermission::delete_many()
+                .filter(role_permission::Column::PermissionId.eq(permission.id))
+                .exec(conn)
+                .await?;
+            Permission::delete_by_id(permission.id).exec(conn).await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(ApiToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ApiToken {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    Name,
+    TokenHash,
+    Scopes,
+    CreatedAt,
+    ExpiresAt,
+    LastUsedAt,
+}
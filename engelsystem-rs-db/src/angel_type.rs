@@ -0,0 +1,400 @@
+use chrono::Utc;
+use entity::intern::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    IntoActiveModel, JoinType, QueryOrder, QuerySelect, SqlErr, TransactionTrait, prelude::*,
+};
+
+use crate::Error;
+use crate::notification::emit_notification;
+
+fn map_unique_violation(err: DbErr) -> Error {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => Error::AngelTypeExists,
+        _ => err.into(),
+    }
+}
+
+pub async fn get_all_angel_types(db: &DatabaseConnection) -> crate::Result<Vec<angel_type::Model>> {
+    Ok(AngelType::find()
+        .order_by_asc(angel_type::Column::Name)
+        .all(db)
+        .await?)
+}
+
+pub async fn get_angel_type_by_id(
+    angel_type_id: u32,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<angel_type::Model>> {
+    Ok(AngelType::find_by_id(angel_type_id).one(db).await?)
+}
+
+async fn get_angel_type_name<C: ConnectionTrait>(
+    angel_type_id: u32,
+    db: &C,
+) -> crate::Result<String> {
+    Ok(AngelType::find_by_id(angel_type_id)
+        .one(db)
+        .await?
+        .ok_or(Error::AngelTypeNotFound)?
+        .name)
+}
+
+pub async fn add_angel_type(
+    name: impl Into<String>,
+    needs_introduction: bool,
+    db: &DatabaseConnection,
+) -> crate::Result<angel_type::Model> {
+    angel_type::ActiveModel {
+        id: NotSet,
+        created_at: NotSet,
+        name: Set(name.into()),
+        needs_introduction: Set(needs_introduction),
+    }
+    .insert(db)
+    .await
+    .map_err(map_unique_violation)
+}
+
+pub async fn update_angel_type(
+    angel_type_id: u32,
+    name: Option<String>,
+    needs_introduction: Option<bool>,
+    db: &DatabaseConnection,
+) -> crate::Result<angel_type::Model> {
+    let mut angel_type = get_angel_type_by_id(angel_type_id, db)
+        .await?
+        .ok_or(Error::AngelTypeNotFound)?
+        .into_active_model();
+
+    if let Some(name) = name {
+        angel_type.name = Set(name);
+    }
+
+    if let Some(needs_introduction) = needs_introduction {
+        angel_type.needs_introduction = Set(needs_introduction);
+    }
+
+    angel_type.update(db).await.map_err(map_unique_violation)
+}
+
+/// Deletes an angel type together with all memberships. Angel types that shifts or shift
+/// templates still refer to can't be deleted.
+pub async fn delete_angel_type(angel_type_id: u32, db: &DatabaseConnection) -> crate::Result<()> {
+    let in_use = Shift::find()
+        .filter(shift::Column::AngelTypeId.eq(angel_type_id))
+        .count(db)
+        .await?
+        > 0
+        || ShiftTemplate::find()
+            .filter(shift_template::Column::AngelTypeId.eq(angel_type_id))
+            .count(db)
+            .await?
+            > 0;
+
+    if in_use {
+        return Err(Error::AngelTypeInUse);
+    }
+
+    if AngelType::delete_by_id(angel_type_id)
+        .exec(db)
+        .await?
+        .rows_affected
+        == 0
+    {
+        return Err(Error::AngelTypeNotFound);
+    }
+
+    Ok(())
+}
+
+async fn get_membership<C: ConnectionTrait>(
+    user_id: Uuid,
+    angel_type_id: u32,
+    db: &C,
+) -> crate::Result<Option<user_angel_type::Model>> {
+    Ok(UserAngelType::find_by_id((user_id, angel_type_id))
+        .one(db)
+        .await?)
+}
+
+/// Requests membership in an angel type. Angel types that don't need an introduction are joined
+/// right away, all others stay pending until a supporter confirms the membership.
+pub async fn request_angel_type_membership(
+    user_id: Uuid,
+    angel_type_id: u32,
+    db: &DatabaseConnection,
+) -> crate::Result<user_angel_type::Model> {
+    let angel_type = get_angel_type_by_id(angel_type_id, db)
+        .await?
+        .ok_or(Error::AngelTypeNotFound)?;
+
+    if get_membership(user_id, angel_type_id, db).await?.is_some() {
+        return Err(Error::AlreadyMember);
+    }
+
+    let confirmed_at = (!angel_type.needs_introduction).then(Utc::now);
+
+    Ok(user_angel_type::ActiveModel {
+        user_id: Set(user_id),
+        angel_type_id: Set(angel_type_id),
+        created_at: NotSet,
+        confirmed_at: Set(confirmed_at),
+        confirmed_by: Set(None),
+        supporter: Set(false),
+    }
+    .insert(db)
+    .await?)
+}
+
+/// Confirms a pending membership and notifies the user about it
+pub async fn confirm_angel_type_membership(
+    user_id: Uuid,
+    angel_type_id: u32,
+    confirmed_by: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<user_angel_type::Model> {
+    let txn = db.begin().await?;
+
+    let membership = get_membership(user_id, angel_type_id, &txn)
+        .await?
+        .ok_or(Error::MembershipNotFound)?;
+
+    if membership.is_confirmed() {
+        return Ok(membership);
+    }
+
+    let mut membership = membership.into_active_model();
+    membership.confirmed_at = Set(Some(Utc::now()));
+    membership.confirmed_by = Set(Some(confirmed_by));
+    let membership = membership.update(&txn).await?;
+
+    let angel_type = get_angel_type_name(angel_type_id, &txn).await?;
+    emit_notification(
+        &[user_id],
+        notification::NotificationKind::AngelTypeConfirmed,
+        None,
+        &format!("Deine Mitgliedschaft bei „{angel_type}“ wurde bestätigt."),
+        &txn,
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(membership)
+}
+
+/// Appoints or removes a supporter of an angel type. Appointing a supporter also confirms their
+/// membership if it was still pending. The user is notified if anything changed.
+pub async fn set_angel_type_supporter(
+    user_id: Uuid,
+    angel_type_id: u32,
+    supporter: bool,
+    set_by: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<user_angel_type::Model> {
+    let txn = db.begin().await?;
+
+    let membership = get_membership(user_id, angel_type_id, &txn)
+        .await?
+        .ok_or(Error::MembershipNotFound)?;
+
+    if membership.supporter == supporter {
+        return Ok(membership);
+    }
+
+    let confirmed = membership.is_confirmed();
+    let mut membership = membership.into_active_model();
+    membership.supporter = Set(supporter);
+
+    if supporter && !confirmed {
+        membership.confirmed_at = Set(Some(Utc::now()));
+        membership.confirmed_by = Set(Some(set_by));
+    }
+
+    let membership = membership.update(&txn).await?;
+
+    let angel_type = get_angel_type_name(angel_type_id, &txn).await?;
+    let message = if supporter {
+        format!("Du bist jetzt Supporter für „{angel_type}“.")
+    } else {
+        format!("Du bist nicht mehr Supporter für „{angel_type}“.")
+    };
+    emit_notification(
+        &[user_id],
+        notification::NotificationKind::AngelTypeSupporter,
+        None,
+        &message,
+        &txn,
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(membership)
+}
+
+/// Removes a membership, no matter if it was confirmed or still pending
+pub async fn delete_angel_type_membership(
+    user_id: Uuid,
+    angel_type_id: u32,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    if UserAngelType::delete_by_id((user_id, angel_type_id))
+        .exec(db)
+        .await?
+        .rows_affected
+        == 0
+    {
+        return Err(Error::MembershipNotFound);
+    }
+
+    Ok(())
+}
+
+pub async fn is_angel_type_supporter(
+    user_id: Uuid,
+    angel_type_id: u32,
+    db: &DatabaseConnection,
+) -> crate::Result<bool> {
+    Ok(get_membership(user_id, angel_type_id, db)
+        .await?
+        .is_some_and(|m| m.is_confirmed() && m.supporter))
+}
+
+pub async fn is_confirmed_for_angel_type<C: ConnectionTrait>(
+    user_id: Uuid,
+    angel_type_id: u32,
+    db: &C,
+) -> crate::Result<bool> {
+    Ok(get_membership(user_id, angel_type_id, db)
+        .await?
+        .is_some_and(|m| m.is_confirmed()))
+}
+
+fn select_membership_views() -> sea_orm::Select<UserAngelType> {
+    UserAngelType::find()
+        .join(JoinType::InnerJoin, user_angel_type::Relation::User.def())
+        .join(
+            JoinType::InnerJoin,
+            user_angel_type::Relation::AngelType.def(),
+        )
+        .column_as(user::Column::Username, "username")
+        .column_as(angel_type::Column::Name, "angel_type")
+}
+
+pub async fn get_angel_type_membership_view(
+    user_id: Uuid,
+    angel_type_id: u32,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<user_angel_type::View>> {
+    Ok(select_membership_views()
+        .filter(user_angel_type::Column::UserId.eq(user_id))
+        .filter(user_angel_type::Column::AngelTypeId.eq(angel_type_id))
+        .into_model::<user_angel_type::View>()
+        .one(db)
+        .await?)
+}
+
+pub async fn get_angel_type_members(
+    angel_type_id: u32,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<user_angel_type::View>> {
+    Ok(select_membership_views()
+        .filter(user_angel_type::Column::AngelTypeId.eq(angel_type_id))
+        .order_by_asc(user::Column::Username)
+        .into_model::<user_angel_type::View>()
+        .all(db)
+        .await?)
+}
+
+pub async fn get_angel_types_of_user(
+    user_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<user_angel_type::View>> {
+    Ok(select_membership_views()
+        .filter(user_angel_type::Column::UserId.eq(user_id))
+        .order_by_asc(angel_type::Column::Name)
+        .into_model::<user_angel_type::View>()
+        .all(db)
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::{add_admin, add_user};
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn angel_type_crud() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let bar = add_angel_type("Bar", false, &db).await.unwrap();
+        assert!(matches!(
+            add_angel_type("Bar", true, &db).await,
+            Err(Error::AngelTypeExists)
+        ));
+
+        let bar = update_angel_type(bar.id, Some("Theke".to_string()), Some(true), &db)
+            .await
+            .unwrap();
+        assert_eq!(bar.name, "Theke");
+        assert!(bar.needs_introduction);
+
+        delete_angel_type(bar.id, &db).await.unwrap();
+        assert!(get_all_angel_types(&db).await.unwrap().is_empty());
+        assert!(matches!(
+            delete_angel_type(bar.id, &db).await,
+            Err(Error::AngelTypeNotFound)
+        ));
+    }
+
+    #[test(tokio::test)]
+    async fn membership_needs_confirmation() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let admin = add_admin("Admin", "admin@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let open = add_angel_type("Runner", false, &db).await.unwrap();
+        let restricted = add_angel_type("Tech", true, &db).await.unwrap();
+
+        let runner = request_angel_type_membership(user.id, open.id, &db)
+            .await
+            .unwrap();
+        assert!(runner.is_confirmed());
+
+        let tech = request_angel_type_membership(user.id, restricted.id, &db)
+            .await
+            .unwrap();
+        assert!(!tech.is_confirmed());
+        assert!(
+            !is_confirmed_for_angel_type(user.id, restricted.id, &db)
+                .await
+                .unwrap()
+        );
+        assert!(matches!(
+            request_angel_type_membership(user.id, restricted.id, &db).await,
+            Err(Error::AlreadyMember)
+        ));
+
+        let tech = confirm_angel_type_membership(user.id, restricted.id, admin.id, &db)
+            .await
+            .unwrap();
+        assert_eq!(tech.confirmed_by, Some(admin.id));
+        assert!(
+            is_confirmed_for_angel_type(user.id, restricted.id, &db)
+                .await
+                .unwrap()
+        );
+
+        let memberships = get_angel_types_of_user(user.id, &db).await.unwrap();
+        assert_eq!(memberships.len(), 2);
+        assert_eq!(memberships[0].angel_type, "Runner");
+    }
+}
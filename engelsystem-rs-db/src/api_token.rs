@@ -0,0 +1,407 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use chrono::{TimeDelta, Utc};
+use entity::intern::*;
+use entity::public::{ApiTokenKind, ApiTokenView, AuditAction};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{QueryOrder, TransactionTrait, prelude::*};
+
+use crate::audit::{Actor, record_audit, snapshot};
+use crate::permission::PermissionType;
+use crate::token::{generate_token, hash_token};
+
+/// How outdated the last use of a token may be, so not every request has to write it
+const LAST_USED_PRECISION: TimeDelta = TimeDelta::minutes(1);
+
+/// A valid token presented with a request
+#[derive(Debug, Clone)]
+pub struct ApiTokenAuth {
+    pub token_id: Uuid,
+    /// The user the token acts as
+    pub user_id: Uuid,
+    /// The permissions the token may use, if the role of the user has them enabled
+    pub scopes: HashSet<PermissionType>,
+}
+
+/// The permissions of a token. Names this version doesn't know about are skipped.
+fn token_scopes(token: &api_token::Model) -> HashSet<PermissionType> {
+    ApiTokenView::from(token.clone())
+        .scopes
+        .iter()
+        .filter_map(|name| PermissionType::from_str(name).ok())
+        .collect()
+}
+
+async fn insert_api_token<C: ConnectionTrait>(
+    user_id: Uuid,
+    kind: ApiTokenKind,
+    name: String,
+    scopes: &[PermissionType],
+    expires_at: Option<DateTimeUtc>,
+    db: &C,
+) -> crate::Result<(ApiTokenView, String)> {
+    if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(crate::Error::InvalidTokenExpiry);
+    }
+
+    let mut names: Vec<&str> = scopes.iter().map(PermissionType::name).collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let token = generate_token();
+    let model = api_token::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        kind: Set(kind),
+        name: Set(name),
+        token_hash: Set(hash_token(&token)),
+        scopes: Set(serde_json::json!(names)),
+        created_at: Set(Utc::now()),
+        expires_at: Set(expires_at),
+        last_used_at: NotSet,
+    }
+    .insert(db)
+    .await?;
+
+    Ok((model.into(), token))
+}
+
+/// Creates a token for the scripts of a user. Returns the token, which can't be shown again.
+pub async fn create_personal_token(
+    user_id: Uuid,
+    name: String,
+    scopes: &[PermissionType],
+    expires_at: Option<DateTimeUtc>,
+    db: &DatabaseConnection,
+) -> crate::Result<(ApiTokenView, String)> {
+    insert_api_token(
+        user_id,
+        ApiTokenKind::Personal,
+        name,
+        scopes,
+        expires_at,
+        db,
+    )
+    .await
+}
+
+/// Creates a token for an integration that acts as `user_id`. Returns the token, which can't be
+/// shown again.
+pub async fn create_service_token<C: ConnectionTrait + TransactionTrait>(
+    user_id: Uuid,
+    name: String,
+    scopes: &[PermissionType],
+    expires_at: Option<DateTimeUtc>,
+    actor: &Actor,
+    db: &C,
+) -> crate::Result<(ApiTokenView, String)> {
+    let txn = db.begin().await?;
+
+    User::find_by_id(user_id)
+        .one(&txn)
+        .await?
+        .ok_or(crate::Error::UserNotFound)?;
+
+    let (view, token) = insert_api_token(
+        user_id,
+        ApiTokenKind::Service,
+        name,
+        scopes,
+        expires_at,
+        &txn,
+    )
+    .await?;
+    record_audit(
+        actor,
+        AuditAction::ServiceTokenCreated,
+        view.id,
+        None,
+        snapshot(&view),
+        &txn,
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok((view, token))
+}
+
+/// The personal tokens of a user, newest first
+pub async fn get_personal_tokens(
+    user_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<ApiTokenView>> {
+    Ok(ApiToken::find()
+        .filter(api_token::Column::UserId.eq(user_id))
+        .filter(api_token::Column::Kind.eq(ApiTokenKind::Personal))
+        .order_by_desc(api_token::Column::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(ApiTokenView::from)
+        .collect())
+}
+
+/// All service tokens, newest first
+pub async fn get_service_tokens(db: &DatabaseConnection) -> crate::Result<Vec<ApiTokenView>> {
+    Ok(ApiToken::find()
+        .filter(api_token::Column::Kind.eq(ApiTokenKind::Service))
+        .order_by_desc(api_token::Column::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(ApiTokenView::from)
+        .collect())
+}
+
+/// Revokes a personal token of the user. Fails if the user has no such token.
+pub async fn revoke_personal_token(
+    user_id: Uuid,
+    token_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    let result = ApiToken::delete_many()
+        .filter(api_token::Column::Id.eq(token_id))
+        .filter(api_token::Column::UserId.eq(user_id))
+        .filter(api_token::Column::Kind.eq(ApiTokenKind::Personal))
+        .exec(db)
+        .await?;
+
+    if result.rows_affected == 0 {
+        return Err(crate::Error::ApiTokenNotFound);
+    }
+
+    Ok(())
+}
+
+/// Revokes a service token. Fails if there is no service token with the id.
+pub async fn revoke_service_token<C: ConnectionTrait + TransactionTrait>(
+    token_id: Uuid,
+    actor: &Actor,
+    db: &C,
+) -> crate::Result<()> {
+    let txn = db.begin().await?;
+
+    let token = ApiToken::find_by_id(token_id)
+        .filter(api_token::Column::Kind.eq(ApiTokenKind::Service))
+        .one(&txn)
+        .await?
+        .ok_or(crate::Error::ApiTokenNotFound)?;
+
+    ApiToken::delete_by_id(token_id).exec(&txn).await?;
+    record_audit(
+        actor,
+        AuditAction::ServiceTokenRevoked,
+        token_id,
+        snapshot(&ApiTokenView::from(token)),
+        None,
+        &txn,
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Looks up a token presented with a request and records that it was used. Returns `None` for
+/// unknown and expired tokens.
+pub async fn authenticate_api_token(
+    token: &str,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<ApiTokenAuth>> {
+    let now = Utc::now();
+
+    let Some(model) = ApiToken::find()
+        .filter(api_token::Column::TokenHash.eq(hash_token(token)))
+        .one(db)
+        .await?
+        .filter(|model| model.expires_at.is_none_or(|expires_at| expires_at > now))
+    else {
+        return Ok(None);
+    };
+
+    if model
+        .last_used_at
+        .is_none_or(|last_used_at| now - last_used_at >= LAST_USED_PRECISION)
+    {
+        ApiToken::update_many()
+            .col_expr(api_token::Column::LastUsedAt, Expr::value(now))
+            .filter(api_token::Column::Id.eq(model.id))
+            .exec(db)
+            .await?;
+    }
+
+    Ok(Some(ApiTokenAuth {
+        token_id: model.id,
+        user_id: model.user_id,
+        scopes: token_scopes(&model),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditQuery, get_audit_entries};
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::{add_admin, add_user};
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn personal_token_lifecycle() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let meow = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let nyan = add_user("Nyan", "nyan@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        let (view, token) = create_personal_token(
+            meow.id,
+            "backup script".to_string(),
+            &[
+                PermissionType::SignUpForShifts,
+                PermissionType::SignUpForShifts,
+            ],
+            None,
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(view.scopes, vec!["SignUpForShifts".to_string()]);
+        assert_eq!(view.last_used_at, None);
+
+        let auth = authenticate_api_token(&token, &db).await.unwrap().unwrap();
+        assert_eq!(auth.user_id, meow.id);
+        assert_eq!(
+            auth.scopes,
+            HashSet::from([PermissionType::SignUpForShifts])
+        );
+        assert!(
+            authenticate_api_token("wrong", &db)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let tokens = get_personal_tokens(meow.id, &db).await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert!(tokens[0].last_used_at.is_some());
+
+        // Only the owner can revoke it
+        assert!(matches!(
+            revoke_personal_token(nyan.id, view.id, &db).await,
+            Err(crate::Error::ApiTokenNotFound)
+        ));
+        revoke_personal_token(meow.id, view.id, &db).await.unwrap();
+        assert!(authenticate_api_token(&token, &db).await.unwrap().is_none());
+    }
+
+    #[test(tokio::test)]
+    async fn expired_tokens_are_rejected() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let meow = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            create_personal_token(
+                meow.id,
+                "old".to_string(),
+                &[],
+                Some(Utc::now() - TimeDelta::minutes(1)),
+                &db,
+            )
+            .await,
+            Err(crate::Error::InvalidTokenExpiry)
+        ));
+
+        let (view, token) = create_personal_token(
+            meow.id,
+            "short".to_string(),
+            &[],
+            Some(Utc::now() + TimeDelta::hours(1)),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(authenticate_api_token(&token, &db).await.unwrap().is_some());
+
+        ApiToken::update_many()
+            .col_expr(
+                api_token::Column::ExpiresAt,
+                Expr::value(Utc::now() - TimeDelta::seconds(1)),
+            )
+            .filter(api_token::Column::Id.eq(view.id))
+            .exec(&db)
+            .await
+            .unwrap();
+        assert!(authenticate_api_token(&token, &db).await.unwrap().is_none());
+    }
+
+    #[test(tokio::test)]
+    async fn service_tokens_are_audited() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let admin = add_admin("Admin", "admin@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let bot = add_user("Bot", "bot@meow.de", "awawa", &db).await.unwrap();
+        let actor = Actor::new(admin.id, entity::public::AuditOrigin::Api);
+
+        assert!(matches!(
+            create_service_token(Uuid::new_v4(), "ghost".to_string(), &[], None, &actor, &db).await,
+            Err(crate::Error::UserNotFound)
+        ));
+
+        let (view, token) = create_service_token(
+            bot.id,
+            "calendar sync".to_string(),
+            &[PermissionType::ManageShifts],
+            None,
+            &actor,
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(view.kind, ApiTokenKind::Service);
+        assert_eq!(get_service_tokens(&db).await.unwrap().len(), 1);
+
+        // Service tokens aren't managed by the account they act as
+        assert!(get_personal_tokens(bot.id, &db).await.unwrap().is_empty());
+        assert!(matches!(
+            revoke_personal_token(bot.id, view.id, &db).await,
+            Err(crate::Error::ApiTokenNotFound)
+        ));
+
+        revoke_service_token(view.id, &actor, &db).await.unwrap();
+        assert!(authenticate_api_token(&token, &db).await.unwrap().is_none());
+        assert!(matches!(
+            revoke_service_token(view.id, &actor, &db).await,
+            Err(crate::Error::ApiTokenNotFound)
+        ));
+
+        let log = get_audit_entries(
+            &AuditQuery {
+                target_id: Some(view.id.to_string()),
+                limit: 10,
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        let actions: Vec<_> = log.iter().map(|entry| entry.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                AuditAction::ServiceTokenRevoked,
+                AuditAction::ServiceTokenCreated
+            ]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use entity::intern::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{QueryOrder, QuerySelect, prelude::*};
+use serde::Serialize;
+
+use entity::public::{AuditAction, AuditOrigin, AuditTarget};
+
+/// Who made a change and through which way. Passed to every function that makes an
+/// administrative change, which records it in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Actor {
+    pub user_id: Option<Uuid>,
+    pub origin: AuditOrigin,
+}
+
+impl Actor {
+    pub fn new(user_id: Uuid, origin: AuditOrigin) -> Self {
+        Self {
+            user_id: Some(user_id),
+            origin,
+        }
+    }
+
+    /// The command line tool, which works on the database directly without a logged in user
+    pub fn cli() -> Self {
+        Self {
+            user_id: None,
+            origin: AuditOrigin::Cli,
+        }
+    }
+}
+
+/// Turns an entity into the JSON stored as state before or after a change
+pub(crate) fn snapshot(value: &impl Serialize) -> Option<Json> {
+    serde_json::to_value(value).ok()
+}
+
+/// Appends an entry to the audit log. Meant to be called in the same transaction as the change.
+pub(crate) async fn record_audit<C: ConnectionTrait>(
+    actor: &Actor,
+    action: AuditAction,
+    target_id: impl ToString,
+    before: Option<Json>,
+    after: Option<Json>,
+    db: &C,
+) -> crate::Result<()> {
+    audit_log::ActiveModel {
+        id: NotSet,
+        created_at: NotSet,
+        actor_id: Set(actor.user_id),
+        origin: Set(actor.origin),
+        action: Set(action),
+        target_type: Set(action.target()),
+        target_id: Set(target_id.to_string()),
+        before: Set(before),
+        after: Set(after),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Filters for [`get_audit_entries`]. Unset filters match every entry.
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub actor_id: Option<Uuid>,
+    pub origin: Option<AuditOrigin>,
+    pub action: Option<AuditAction>,
+    pub target_type: Option<AuditTarget>,
+    pub target_id: Option<String>,
+    /// Only entries created at or after this point in time
+    pub since: Option<DateTimeUtc>,
+    /// Only entries created before this point in time
+    pub until: Option<DateTimeUtc>,
+    /// Only entries older than the one with this id, to continue after the last page
+    pub before_id: Option<u32>,
+    pub limit: u64,
+}
+
+/// Returns the matching entries of the audit log, newest first
+pub async fn get_audit_entries(
+    query: &AuditQuery,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<audit_log::Model>> {
+    let mut select = AuditLog::find()
+        .order_by_desc(audit_log::Column::Id)
+        .limit(query.limit);
+
+    if let Some(actor_id) = query.actor_id {
+        select = select.filter(audit_log::Column::ActorId.eq(actor_id));
+    }
+    if let Some(origin) = query.origin {
+        select = select.filter(audit_log::Column::Origin.eq(origin));
+    }
+    if let Some(action) = query.action {
+        select = select.filter(audit_log::Column::Action.eq(action));
+    }
+    if let Some(target_type) = query.target_type {
+        select = select.filter(audit_log::Column::TargetType.eq(target_type));
+    }
+    if let Some(target_id) = &query.target_id {
+        select = select.filter(audit_log::Column::TargetId.eq(target_id));
+    }
+    if let Some(since) = query.since {
+        select = select.filter(audit_log::Column::CreatedAt.gte(since));
+    }
+    if let Some(until) = query.until {
+        select = select.filter(audit_log::Column::CreatedAt.lt(until));
+    }
+    if let Some(before_id) = query.before_id {
+        select = select.filter(audit_log::Column::Id.lt(before_id));
+    }
+
+    Ok(select.all(db).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::role::{RoleType, add_role, rename_role};
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::{add_user, set_role_by_username};
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn record_and_filter() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let admin = add_user("Admin", "admin@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let web = Actor::new(admin.id, AuditOrigin::Web);
+
+        let role = add_role("Teamleitung", &web, &db).await.unwrap();
+        rename_role(role.id, "Schichtleitung", &web, &db)
+            .await
+            .unwrap();
+        // Nothing changes, so nothing is recorded
+        rename_role(role.id, "Schichtleitung", &web, &db)
+            .await
+            .unwrap();
+        set_role_by_username("Admin", RoleType::Admin as u32, &Actor::cli(), &db)
+            .await
+            .unwrap();
+
+        let all = get_audit_entries(
+            &AuditQuery {
+                limit: 10,
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            all.iter().map(|entry| entry.action).collect::<Vec<_>>(),
+            [
+                AuditAction::UserRoleChanged,
+                AuditAction::RoleRenamed,
+                AuditAction::RoleCreated
+            ]
+        );
+        assert_eq!(all[0].origin, AuditOrigin::Cli);
+        assert_eq!(all[0].actor_id, None);
+        assert_eq!(all[0].target_type, AuditTarget::User);
+        assert_eq!(all[0].target_id, admin.id.to_string());
+        assert_eq!(all[1].before.as_ref().unwrap()["name"], "Teamleitung");
+        assert_eq!(all[1].after.as_ref().unwrap()["name"], "Schichtleitung");
+
+        let by_admin = get_audit_entries(
+            &AuditQuery {
+                actor_id: Some(admin.id),
+                target_type: Some(AuditTarget::Role),
+                limit: 10,
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(by_admin.len(), 2);
+
+        let page = get_audit_entries(
+            &AuditQuery {
+                before_id: Some(all[0].id),
+                limit: 1,
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, all[1].id);
+    }
+
+    #[test(tokio::test)]
+    async fn append_only() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        add_role("Teamleitung", &Actor::cli(), &db).await.unwrap();
+
+        assert!(
+            AuditLog::update_many()
+                .col_expr(audit_log::Column::TargetId, Expr::value("forged"))
+                .exec(&db)
+                .await
+                .is_err()
+        );
+        assert!(AuditLog::delete_many().exec(&db).await.is_err());
+    }
+}
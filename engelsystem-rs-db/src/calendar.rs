@@ -0,0 +1,88 @@
+use entity::intern::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::prelude::*;
+use sea_orm::sea_query::OnConflict;
+
+use crate::token::{generate_token, hash_token};
+
+/// Creates a new calendar token for the user and returns it. A previous token of the user stops
+/// working.
+pub async fn create_calendar_token(
+    user_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<String> {
+    let token = generate_token();
+
+    CalendarToken::insert(calendar_token::ActiveModel {
+        user_id: Set(user_id),
+        token_hash: Set(hash_token(&token)),
+        created_at: NotSet,
+    })
+    .on_conflict(
+        OnConflict::column(calendar_token::Column::UserId)
+            .update_column(calendar_token::Column::TokenHash)
+            .value(calendar_token::Column::CreatedAt, Expr::current_timestamp())
+            .to_owned(),
+    )
+    .exec(db)
+    .await?;
+
+    Ok(token)
+}
+
+/// Removes the calendar token of the user, so the feed can't be accessed anymore
+pub async fn revoke_calendar_token(user_id: Uuid, db: &DatabaseConnection) -> crate::Result<()> {
+    CalendarToken::delete_by_id(user_id).exec(db).await?;
+    Ok(())
+}
+
+pub async fn get_user_id_by_calendar_token(
+    token: &str,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<Uuid>> {
+    Ok(CalendarToken::find()
+        .filter(calendar_token::Column::TokenHash.eq(hash_token(token)))
+        .one(db)
+        .await?
+        .map(|entry| entry.user_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::add_user;
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn rotate_and_revoke_token() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        let first = create_calendar_token(user.id, &db).await.unwrap();
+        assert_eq!(
+            get_user_id_by_calendar_token(&first, &db).await.unwrap(),
+            Some(user.id)
+        );
+
+        let second = create_calendar_token(user.id, &db).await.unwrap();
+        assert_ne!(first, second);
+        assert_eq!(
+            get_user_id_by_calendar_token(&first, &db).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            get_user_id_by_calendar_token(&second, &db).await.unwrap(),
+            Some(user.id)
+        );
+
+        revoke_calendar_token(user.id, &db).await.unwrap();
+        assert_eq!(
+            get_user_id_by_calendar_token(&second, &db).await.unwrap(),
+            None
+        );
+    }
+}
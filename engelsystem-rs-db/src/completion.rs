@@ -0,0 +1,457 @@
+use chrono::{Days, FixedOffset, NaiveTime, Utc};
+use entity::intern::{shift_completion::CompletionStatus, *};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{IntoActiveModel, QueryOrder, QuerySelect, TransactionTrait, prelude::*};
+
+use crate::audit::{Actor, record_audit, snapshot};
+use crate::{AuditAction, Error};
+
+const SECONDS_PER_HOUR: f64 = 3_600.0;
+
+/// How worked time is turned into credited time and points
+#[derive(Debug, Clone)]
+pub struct CreditRules {
+    /// Hour of the day at which night time starts, in `utc_offset`
+    pub night_start_hour: u32,
+    /// Hour of the day at which night time ends, in `utc_offset`. Night time spans midnight if this
+    /// is before `night_start_hour`.
+    pub night_end_hour: u32,
+    /// Factor that worked time during the night is multiplied with
+    pub night_multiplier: f64,
+    /// Points for one hour of credited time
+    pub points_per_hour: f64,
+    /// Offset of the local time of the event, which the night hours refer to
+    pub utc_offset: FixedOffset,
+}
+
+impl Default for CreditRules {
+    fn default() -> Self {
+        CreditRules {
+            night_start_hour: 22,
+            night_end_hour: 6,
+            night_multiplier: 2.0,
+            points_per_hour: 1.0,
+            utc_offset: FixedOffset::east_opt(0).unwrap(),
+        }
+    }
+}
+
+impl CreditRules {
+    /// Returns how many seconds of the given time span fall into the night
+    pub fn night_seconds(&self, starts_at: DateTimeUtc, ends_at: DateTimeUtc) -> i64 {
+        let (Some(night_start), Some(night_end)) = (
+            NaiveTime::from_hms_opt(self.night_start_hour, 0, 0),
+            NaiveTime::from_hms_opt(self.night_end_hour, 0, 0),
+        ) else {
+            return 0;
+        };
+
+        if night_start == night_end || ends_at <= starts_at {
+            return 0;
+        }
+
+        let starts_at = starts_at.with_timezone(&self.utc_offset);
+        let ends_at = ends_at.with_timezone(&self.utc_offset);
+
+        // A night that spans midnight can start on the day before the shift starts
+        let mut day = starts_at.date_naive() - Days::new(1);
+        let mut seconds = 0;
+
+        while day <= ends_at.date_naive() {
+            let window_start = day.and_time(night_start);
+            let window_end = if night_end < night_start {
+                (day + Days::new(1)).and_time(night_end)
+            } else {
+                day.and_time(night_end)
+            };
+
+            let overlap_start = window_start.max(starts_at.naive_local());
+            let overlap_end = window_end.min(ends_at.naive_local());
+            if overlap_end > overlap_start {
+                seconds += (overlap_end - overlap_start).num_seconds();
+            }
+
+            day = day + Days::new(1);
+        }
+
+        seconds
+    }
+
+    /// Returns the credited seconds and points for working `worked_seconds` of the given shift.
+    /// Partial attendance is credited with the average multiplier of the whole shift.
+    pub fn credit(&self, shift: &shift::Model, worked_seconds: u32) -> (u32, u32) {
+        let duration = (shift.ends_at - shift.starts_at).num_seconds();
+        let multiplier = if duration > 0 {
+            let night = self.night_seconds(shift.starts_at, shift.ends_at);
+            (duration - night) as f64 / duration as f64
+                + night as f64 / duration as f64 * self.night_multiplier
+        } else {
+            1.0
+        };
+
+        let credited_seconds = (worked_seconds as f64 * multiplier).round();
+        let points = (credited_seconds / SECONDS_PER_HOUR * self.points_per_hour).round();
+
+        (credited_seconds as u32, points as u32)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompletionEntry {
+    pub user_id: Uuid,
+    pub status: CompletionStatus,
+    /// The hours the angel was present, only for [`CompletionStatus::Partial`]
+    pub worked_hours: Option<f64>,
+}
+
+/// Partial attendance can't be longer than the shift itself
+fn worked_seconds(shift: &shift::Model, entry: &CompletionEntry) -> crate::Result<u32> {
+    let shift_seconds = (shift.ends_at - shift.starts_at).num_seconds().max(0) as u32;
+
+    match (entry.status, entry.worked_hours) {
+        (CompletionStatus::Present, None) => Ok(shift_seconds),
+        (CompletionStatus::Absent, None) => Ok(0),
+        (CompletionStatus::Partial, Some(hours))
+            if hours.is_finite()
+                && hours >= 0.0
+                && hours * SECONDS_PER_HOUR <= shift_seconds as f64 =>
+        {
+            Ok((hours * SECONDS_PER_HOUR).round() as u32)
+        }
+        _ => Err(Error::InvalidWorkedHours),
+    }
+}
+
+/// Marks how the given angels completed a shift and credits their shift time and points.
+///
+/// Angels can be marked again, in which case the previous credit is replaced. Every change is
+/// recorded in the audit log with the previous and new completion, so earlier statuses and who set
+/// them stay traceable. All entries are stored in one transaction, so either every angel is
+/// credited or none is.
+pub async fn complete_shift(
+    shift_id: Uuid,
+    entries: Vec<CompletionEntry>,
+    actor: &Actor,
+    rules: &CreditRules,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<shift_completion::Model>> {
+    let txn = db.begin().await?;
+
+    let shift = Shift::find_by_id(shift_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(Error::ShiftNotFound)?;
+
+    if shift.starts_at > Utc::now() {
+        return Err(Error::ShiftNotStarted);
+    }
+
+    let mut completions = Vec::with_capacity(entries.len());
+
+    for entry in entries {
+        if UserShift::find_by_id((entry.user_id, shift_id))
+            .one(&txn)
+            .await?
+            .is_none()
+        {
+            return Err(Error::NotSignedUp);
+        }
+
+        let worked_seconds = worked_seconds(&shift, &entry)?;
+        let (credited_seconds, points) = rules.credit(&shift, worked_seconds);
+
+        let previous = ShiftCompletion::find_by_id((entry.user_id, shift_id))
+            .one(&txn)
+            .await?;
+
+        let completion = shift_completion::ActiveModel {
+            user_id: Set(entry.user_id),
+            shift_id: Set(shift_id),
+            status: Set(entry.status),
+            worked_seconds: Set(worked_seconds),
+            credited_seconds: Set(credited_seconds),
+            points: Set(points),
+            completed_by: Set(actor.user_id),
+            completed_at: Set(Utc::now()),
+        };
+
+        let completion = if previous.is_some() {
+            completion.update(&txn).await?
+        } else {
+            completion.insert(&txn).await?
+        };
+
+        record_audit(
+            actor,
+            AuditAction::ShiftCompletionChanged,
+            shift_id,
+            previous.as_ref().and_then(snapshot),
+            snapshot(&completion),
+            &txn,
+        )
+        .await?;
+        update_user_totals(entry.user_id, &txn).await?;
+
+        completions.push(completion);
+    }
+
+    txn.commit().await?;
+
+    Ok(completions)
+}
+
+pub async fn get_shift_completions(
+    shift_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<shift_completion::Model>> {
+    Ok(ShiftCompletion::find()
+        .filter(shift_completion::Column::ShiftId.eq(shift_id))
+        .order_by_asc(shift_completion::Column::CompletedAt)
+        .all(db)
+        .await?)
+}
+
+/// Recomputes `shift_time` and `points` of a user from their shift completion records
+pub async fn recompute_user_totals(
+    user_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<user::Model> {
+    let txn = db.begin().await?;
+    let user = update_user_totals(user_id, &txn).await?;
+    txn.commit().await?;
+
+    Ok(user)
+}
+
+/// Sets `shift_time` and `points` of a user to the sums of their shift completion records
+async fn update_user_totals<C: ConnectionTrait>(
+    user_id: Uuid,
+    db: &C,
+) -> crate::Result<user::Model> {
+    let user = User::find_by_id(user_id)
+        .one(db)
+        .await?
+        .ok_or(Error::UserNotFound)?;
+
+    let (shift_time, points): (Option<i64>, Option<i64>) = ShiftCompletion::find()
+        .select_only()
+        .column_as(
+            shift_completion::Column::CreditedSeconds.sum(),
+            "shift_time",
+        )
+        .column_as(shift_completion::Column::Points.sum(), "points")
+        .filter(shift_completion::Column::UserId.eq(user_id))
+        .into_tuple()
+        .one(db)
+        .await?
+        .unwrap_or_default();
+
+    let mut user = user.into_active_model();
+    user.shift_time = Set(shift_time.unwrap_or(0).clamp(0, u32::MAX as i64) as u32);
+    user.points = Set(points.unwrap_or(0).clamp(0, u32::MAX as i64) as u32);
+
+    Ok(user.update(db).await?)
+}
+
+/// Recomputes the totals of every user, see [`recompute_user_totals`]. Returns the amount of users.
+pub async fn recompute_all_user_totals(db: &DatabaseConnection) -> crate::Result<u64> {
+    let user_ids: Vec<Uuid> = User::find()
+        .select_only()
+        .column(user::Column::Id)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    for user_id in &user_ids {
+        recompute_user_totals(*user_id, db).await?;
+    }
+
+    Ok(user_ids.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AuditOrigin;
+    use crate::audit::{AuditQuery, get_audit_entries};
+    use crate::shift::tests::add_dummy_shift;
+    use crate::shift::{OverlapPolicy, sign_up_for_shift};
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::{add_user, get_user_by_id};
+    use chrono::TimeDelta;
+    use sea_orm::ActiveValue::Set;
+    use test_log::test;
+
+    #[test]
+    fn night_seconds_span_midnight() {
+        let rules = CreditRules::default();
+        let at = |s: &str| s.parse::<DateTimeUtc>().unwrap();
+
+        assert_eq!(
+            rules.night_seconds(at("2026-10-18T20:00:00Z"), at("2026-10-19T02:00:00Z")),
+            4 * 3_600
+        );
+        assert_eq!(
+            rules.night_seconds(at("2026-10-19T04:00:00Z"), at("2026-10-19T08:00:00Z")),
+            2 * 3_600
+        );
+        assert_eq!(
+            rules.night_seconds(at("2026-10-19T10:00:00Z"), at("2026-10-19T14:00:00Z")),
+            0
+        );
+
+        let shifted = CreditRules {
+            utc_offset: FixedOffset::east_opt(2 * 3_600).unwrap(),
+            ..CreditRules::default()
+        };
+        assert_eq!(
+            shifted.night_seconds(at("2026-10-18T20:00:00Z"), at("2026-10-18T22:00:00Z")),
+            2 * 3_600
+        );
+    }
+
+    #[test(tokio::test)]
+    async fn completion_credits_and_recomputes() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+        let rules = CreditRules::default();
+
+        let manager = add_user("Manager", "manager@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let present = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let partial = add_user("Meow2", "meow2@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        let actor = Actor::new(manager.id, AuditOrigin::Web);
+        let shift = add_dummy_shift(manager.id, TimeDelta::days(1), 2, &db).await;
+        sign_up_for_shift(shift.id, present.id, OverlapPolicy::Allow, &db)
+            .await
+            .unwrap();
+        sign_up_for_shift(shift.id, partial.id, OverlapPolicy::Allow, &db)
+            .await
+            .unwrap();
+
+        let entries = vec![
+            CompletionEntry {
+                user_id: present.id,
+                status: CompletionStatus::Present,
+                worked_hours: None,
+            },
+            CompletionEntry {
+                user_id: partial.id,
+                status: CompletionStatus::Partial,
+                worked_hours: Some(1.0),
+            },
+        ];
+
+        assert!(matches!(
+            complete_shift(shift.id, entries.clone(), &actor, &rules, &db).await,
+            Err(Error::ShiftNotStarted)
+        ));
+
+        // Move the shift into the past, so it can be completed. 14:00 to 16:00 UTC is never at night.
+        let mut started = shift.into_active_model();
+        started.starts_at = Set("2025-10-18T14:00:00Z".parse().unwrap());
+        started.ends_at = Set("2025-10-18T16:00:00Z".parse().unwrap());
+        let shift = started.update(&db).await.unwrap();
+
+        complete_shift(shift.id, entries, &actor, &rules, &db)
+            .await
+            .unwrap();
+
+        let present_user = get_user_by_id(present.id, &db).await.unwrap().unwrap();
+        assert_eq!(present_user.shift_time, 2 * 3_600);
+        assert_eq!(present_user.points, 2);
+        let partial_user = get_user_by_id(partial.id, &db).await.unwrap().unwrap();
+        assert_eq!(partial_user.shift_time, 3_600);
+
+        // Marking again replaces the previous credit instead of adding to it
+        complete_shift(
+            shift.id,
+            vec![CompletionEntry {
+                user_id: present.id,
+                status: CompletionStatus::Absent,
+                worked_hours: None,
+            }],
+            &actor,
+            &rules,
+            &db,
+        )
+        .await
+        .unwrap();
+        let present_user = get_user_by_id(present.id, &db).await.unwrap().unwrap();
+        assert_eq!(present_user.shift_time, 0);
+
+        // Every change is kept in the audit log, with who made it
+        let history = get_audit_entries(
+            &AuditQuery {
+                action: Some(AuditAction::ShiftCompletionChanged),
+                target_id: Some(shift.id.to_string()),
+                limit: 10,
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(history.len(), 3);
+        assert!(
+            history
+                .iter()
+                .all(|entry| entry.actor_id == Some(manager.id))
+        );
+        let remarked = &history[0];
+        assert_eq!(remarked.before.as_ref().unwrap()["status"], "present");
+        assert_eq!(remarked.after.as_ref().unwrap()["status"], "absent");
+        assert_eq!(
+            remarked.after.as_ref().unwrap()["user_id"],
+            present.id.to_string()
+        );
+        assert!(history[1].before.is_none());
+
+        assert!(matches!(
+            complete_shift(
+                shift.id,
+                vec![CompletionEntry {
+                    user_id: present.id,
+                    status: CompletionStatus::Partial,
+                    worked_hours: None,
+                }],
+                &actor,
+                &rules,
+                &db,
+            )
+            .await,
+            Err(Error::InvalidWorkedHours)
+        ));
+        assert!(matches!(
+            complete_shift(
+                shift.id,
+                vec![CompletionEntry {
+                    user_id: present.id,
+                    status: CompletionStatus::Partial,
+                    worked_hours: Some(1e9),
+                }],
+                &actor,
+                &rules,
+                &db,
+            )
+            .await,
+            Err(Error::InvalidWorkedHours)
+        ));
+
+        User::update_many()
+            .col_expr(user::Column::ShiftTime, Expr::value(42))
+            .exec(&db)
+            .await
+            .unwrap();
+        assert_eq!(recompute_all_user_totals(&db).await.unwrap(), 3);
+        let partial_user = get_user_by_id(partial.id, &db).await.unwrap().unwrap();
+        assert_eq!(partial_user.shift_time, 3_600);
+        assert_eq!(get_shift_completions(shift.id, &db).await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::{TimeDelta, Utc};
+use entity::intern::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{IntoActiveModel, TransactionTrait, prelude::*};
+
+use crate::Error;
+use crate::token::{generate_token, hash_token};
+
+/// Creates a token to confirm the email of the user, which is valid for `ttl`. Earlier tokens of
+/// the user stop working.
+pub async fn create_email_verification_token(
+    user_id: Uuid,
+    ttl: TimeDelta,
+    db: &DatabaseConnection,
+) -> crate::Result<String> {
+    let token = generate_token();
+
+    let txn = db.begin().await?;
+
+    EmailVerificationToken::delete_many()
+        .filter(email_verification_token::Column::UserId.eq(user_id))
+        .exec(&txn)
+        .await?;
+
+    email_verification_token::ActiveModel {
+        token_hash: Set(hash_token(&token)),
+        user_id: Set(user_id),
+        created_at: NotSet,
+        expires_at: Set(Utc::now() + ttl),
+    }
+    .insert(&txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(token)
+}
+
+/// Marks the email of the user the token belongs to as verified. The token can only be used once
+/// and only before it expires, otherwise [`Error::InvalidVerificationToken`] is returned.
+pub async fn verify_email(token: &str, db: &DatabaseConnection) -> crate::Result<user::Model> {
+    let txn = db.begin().await?;
+
+    let Some(entry) = EmailVerificationToken::find_by_id(hash_token(token))
+        .one(&txn)
+        .await?
+    else {
+        return Err(Error::InvalidVerificationToken);
+    };
+
+    EmailVerificationToken::delete_by_id(entry.token_hash)
+        .exec(&txn)
+        .await?;
+
+    if entry.expires_at <= Utc::now() {
+        txn.commit().await?;
+        return Err(Error::InvalidVerificationToken);
+    }
+
+    let user = mark_email_verified(entry.user_id, &txn).await?;
+
+    txn.commit().await?;
+
+    Ok(user)
+}
+
+/// Marks the email of a user as verified without a token, e.g. for accounts created by admins
+pub async fn mark_email_verified<C: ConnectionTrait>(
+    user_id: Uuid,
+    db: &C,
+) -> crate::Result<user::Model> {
+    let user = User::find_by_id(user_id)
+        .one(db)
+        .await?
+        .ok_or(Error::UserNotFound)?;
+
+    if user.email_verified_at.is_some() {
+        return Ok(user);
+    }
+
+    let mut user = user.into_active_model();
+    user.email_verified_at = Set(Some(Utc::now()));
+
+    Ok(user.update(db).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ActiveUser;
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::{add_guest, update_user};
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn verify_once_and_reset_on_email_change() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_guest("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        assert_eq!(user.email_verified_at, None);
+
+        let first = create_email_verification_token(user.id, TimeDelta::hours(1), &db)
+            .await
+            .unwrap();
+        let second = create_email_verification_token(user.id, TimeDelta::hours(1), &db)
+            .await
+            .unwrap();
+        assert!(matches!(
+            verify_email(&first, &db).await,
+            Err(Error::InvalidVerificationToken)
+        ));
+
+        let verified = verify_email(&second, &db).await.unwrap();
+        assert!(verified.email_verified_at.is_some());
+        assert!(matches!(
+            verify_email(&second, &db).await,
+            Err(Error::InvalidVerificationToken)
+        ));
+
+        let changed = update_user(
+            user.id,
+            ActiveUser {
+                email: Set("nyan@meow.de".to_string()),
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(changed.email_verified_at, None);
+    }
+
+    #[test(tokio::test)]
+    async fn expired_token_is_used_up() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_guest("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        let token = create_email_verification_token(user.id, TimeDelta::seconds(-1), &db)
+            .await
+            .unwrap();
+        assert!(matches!(
+            verify_email(&token, &db).await,
+            Err(Error::InvalidVerificationToken)
+        ));
+        assert_eq!(EmailVerificationToken::find().count(&db).await.unwrap(), 0);
+    }
+}
@@ -19,4 +19,154 @@ pub enum Error {
 
     #[snafu(display("Hashing Error"))]
     Hashing,
+
+    #[snafu(display("The current password is wrong"))]
+    WrongPassword,
+
+    #[snafu(display("The password reset token is invalid, expired or was already used"))]
+    InvalidResetToken,
+
+    #[snafu(display("The email verification token is invalid, expired or was already used"))]
+    InvalidVerificationToken,
+
+    #[snafu(display("The requested shift was not found"))]
+    ShiftNotFound,
+
+    #[snafu(display("The shift has no free slots left"))]
+    ShiftFull,
+
+    #[snafu(display("The user is already signed up for this shift"))]
+    AlreadySignedUp,
+
+    #[snafu(display(
+        "The shift overlaps with the shift {name:?} ({shift_id}) the user is signed up for"
+    ))]
+    ShiftConflict {
+        shift_id: sea_orm::prelude::Uuid,
+        name: String,
+    },
+
+    #[snafu(display("The user is not signed up for this shift"))]
+    NotSignedUp,
+
+    #[snafu(display("The user is not a confirmed member of the angel type this shift needs"))]
+    NotQualified,
+
+    #[snafu(display("The shift hasn't started yet"))]
+    ShiftNotStarted,
+
+    #[snafu(display(
+        "The shift already started or angels were credited for it, so it can't be cancelled"
+    ))]
+    ShiftAlreadyStarted,
+
+    #[snafu(display(
+        "Worked hours have to be given for partial attendance, and only for it. They can't be longer than the shift"
+    ))]
+    InvalidWorkedHours,
+
+    #[snafu(display("The requested angel type was not found"))]
+    AngelTypeNotFound,
+
+    #[snafu(display("An angel type with this name already exists"))]
+    AngelTypeExists,
+
+    #[snafu(display("The angel type is still used by shifts"))]
+    AngelTypeInUse,
+
+    #[snafu(display("The user already is or requested to be a member of this angel type"))]
+    AlreadyMember,
+
+    #[snafu(display("The user is not a member of this angel type"))]
+    MembershipNotFound,
+
+    #[snafu(display("The requested location was not found"))]
+    LocationNotFound,
+
+    #[snafu(display("A location with this name already exists"))]
+    LocationExists,
+
+    #[snafu(display("The location is still used by shifts"))]
+    LocationInUse,
+
+    #[snafu(display(
+        "Coordinates need both a latitude between -90 and 90 and a longitude between -180 and 180"
+    ))]
+    InvalidCoordinates,
+
+    #[snafu(display("A map link has to be an http or https URL"))]
+    InvalidMapUrl,
+
+    #[snafu(display("The requested role was not found"))]
+    RoleNotFound,
+
+    #[snafu(display("A role with this name already exists"))]
+    RoleExists,
+
+    #[snafu(display("The role is still assigned to users"))]
+    RoleInUse,
+
+    #[snafu(display("The default roles can't be deleted"))]
+    RoleProtected,
+
+    #[snafu(display("The requested permission was not found"))]
+    PermissionNotFound,
+
+    #[snafu(display("The given pagination cursor is invalid"))]
+    InvalidCursor,
+
+    #[snafu(display("Signing off is no longer possible this close to the start of the shift"))]
+    SignOffCutoffPassed,
+
+    #[snafu(display("A shift has to end after it starts"))]
+    InvalidShiftTime,
+
+    #[snafu(display("The requested shift template was not found"))]
+    ShiftTemplateNotFound,
+
+    #[snafu(display(
+        "A shift template needs an interval and duration of at least one, and can't end before it starts"
+    ))]
+    InvalidRecurrence,
+
+    #[snafu(display("The date range has to end after it starts and can be at most a year long"))]
+    InvalidDateRange,
+
+    #[snafu(display("The requested mail is not in the outbox"))]
+    OutboxMailNotFound,
+
+    #[snafu(display("The mail was already sent"))]
+    OutboxMailSent,
+
+    #[snafu(display("The mail contained a secret and was removed after it was given up on"))]
+    OutboxMailSecret,
+
+    #[snafu(display("The requested notification was not found"))]
+    NotificationNotFound,
+
+    #[snafu(display("The reminder lead time has to be between 5 minutes and 7 days"))]
+    InvalidReminderLeadTime,
+
+    #[snafu(display("Too many failed logins, logging in is blocked until {until}"))]
+    LoginBlocked {
+        until: chrono::DateTime<chrono::Utc>,
+    },
+
+    #[snafu(display("There are no failed logins for this account or address"))]
+    LoginFailuresNotFound,
+
+    #[snafu(display("The requested session was not found"))]
+    SessionNotFound,
+
+    #[snafu(display("The requested API token was not found"))]
+    ApiTokenNotFound,
+
+    #[snafu(display("A token has to expire in the future"))]
+    InvalidTokenExpiry,
+
+    #[snafu(display("Unknown import format {format:?}, expected csv or json"))]
+    UnknownImportFormat { format: String },
+
+    #[snafu(display("The import file couldn't be read: {reason}"))]
+    InvalidImportFile { reason: String },
 }
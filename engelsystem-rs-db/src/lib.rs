@@ -1,8 +1,23 @@
+pub mod angel_type;
+pub mod api_token;
+pub mod audit;
+pub mod calendar;
+pub mod completion;
+pub mod email_verification;
 pub mod error;
+pub mod location;
+pub mod login_throttle;
+pub mod mail_outbox;
+pub mod notification;
+pub mod password_reset;
 pub mod permission;
+pub mod reminder;
 pub mod role;
 pub mod session;
 pub mod shift;
+pub mod shift_import;
+pub mod shift_template;
+pub mod token;
 pub mod user;
 
 pub use error::*;
@@ -0,0 +1,352 @@
+use entity::intern::*;
+use sea_orm::{IntoActiveModel, QueryOrder, QuerySelect, SqlErr, prelude::*};
+
+use crate::Error;
+
+fn map_unique_violation(err: DbErr) -> Error {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => Error::LocationExists,
+        _ => err.into(),
+    }
+}
+
+/// Map links are rendered as links by clients, so only web URLs are accepted
+fn is_web_url(url: &str) -> bool {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return false;
+    };
+
+    (scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https"))
+        && !rest.is_empty()
+        && !url.chars().any(char::is_whitespace)
+}
+
+/// Checks a location before it is written. Coordinates are optional, but if given both have to be
+/// there and on the globe.
+fn validate(location: &location::ActiveModel) -> crate::Result<()> {
+    let latitude = location.latitude.try_as_ref().copied().flatten();
+    let longitude = location.longitude.try_as_ref().copied().flatten();
+
+    match (latitude, longitude) {
+        (None, None) => {}
+        (Some(latitude), Some(longitude))
+            if (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude) => {}
+        _ => return Err(Error::InvalidCoordinates),
+    }
+
+    if let Some(Some(map_url)) = location.map_url.try_as_ref()
+        && !is_web_url(map_url)
+    {
+        return Err(Error::InvalidMapUrl);
+    }
+
+    Ok(())
+}
+
+/// Changes to a location. Fields that are `None` stay as they are.
+#[derive(Debug, Clone, Default)]
+pub struct LocationChanges {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub latitude: Option<Option<f64>>,
+    pub longitude: Option<Option<f64>>,
+    pub map_url: Option<Option<String>>,
+}
+
+pub async fn get_all_locations(db: &DatabaseConnection) -> crate::Result<Vec<location::Model>> {
+    Ok(Location::find()
+        .order_by_asc(location::Column::Name)
+        .all(db)
+        .await?)
+}
+
+pub async fn get_location_by_id(
+    location_id: u32,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<location::Model>> {
+    Ok(Location::find_by_id(location_id).one(db).await?)
+}
+
+pub async fn get_location_id_by_name(
+    name: &str,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<u32>> {
+    Ok(Location::find()
+        .select_only()
+        .column(location::Column::Id)
+        .filter(location::Column::Name.eq(name))
+        .into_tuple()
+        .one(db)
+        .await?)
+}
+
+/// Fails with [`Error::LocationNotFound`] if a shift refers to a location that doesn't exist
+pub(crate) async fn check_location_exists<C: ConnectionTrait>(
+    location_id: Option<u32>,
+    db: &C,
+) -> crate::Result<()> {
+    if let Some(id) = location_id
+        && Location::find_by_id(id).one(db).await?.is_none()
+    {
+        return Err(Error::LocationNotFound);
+    }
+
+    Ok(())
+}
+
+pub async fn add_location(
+    location: location::ActiveModel,
+    db: &DatabaseConnection,
+) -> crate::Result<location::Model> {
+    validate(&location)?;
+
+    location.insert(db).await.map_err(map_unique_violation)
+}
+
+pub async fn update_location(
+    location_id: u32,
+    changes: LocationChanges,
+    db: &DatabaseConnection,
+) -> crate::Result<location::Model> {
+    let location = Location::find_by_id(location_id)
+        .one(db)
+        .await?
+        .ok_or(Error::LocationNotFound)?;
+
+    let mut active = location.clone().into_active_model();
+
+    if let Some(name) = changes.name {
+        active.name.set_if_not_equals(name);
+    }
+    if let Some(description) = changes.description {
+        active.description.set_if_not_equals(description);
+    }
+    if let Some(latitude) = changes.latitude {
+        active.latitude.set_if_not_equals(latitude);
+    }
+    if let Some(longitude) = changes.longitude {
+        active.longitude.set_if_not_equals(longitude);
+    }
+    if let Some(map_url) = changes.map_url {
+        active.map_url.set_if_not_equals(map_url);
+    }
+
+    if !active.is_changed() {
+        return Ok(location);
+    }
+
+    validate(&active)?;
+
+    active.update(db).await.map_err(map_unique_violation)
+}
+
+/// Deletes a location. Locations that shifts or shift templates still refer to can't be deleted.
+pub async fn delete_location(location_id: u32, db: &DatabaseConnection) -> crate::Result<()> {
+    let in_use = Shift::find()
+        .filter(shift::Column::LocationId.eq(location_id))
+        .count(db)
+        .await?
+        > 0
+        || ShiftTemplate::find()
+            .filter(shift_template::Column::LocationId.eq(location_id))
+            .count(db)
+            .await?
+            > 0;
+
+    if in_use {
+        return Err(Error::LocationInUse);
+    }
+
+    if Location::delete_by_id(location_id)
+        .exec(db)
+        .await?
+        .rows_affected
+        == 0
+    {
+        return Err(Error::LocationNotFound);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::Actor;
+    use crate::shift::{ShiftQuery, add_shift, get_shift_views};
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::add_user;
+    use chrono::{TimeDelta, Utc};
+    use sea_orm::ActiveValue::Set;
+    use test_log::test;
+
+    fn new_location(name: &str) -> location::ActiveModel {
+        location::ActiveModel {
+            name: Set(name.to_string()),
+            description: Set(None),
+            latitude: Set(None),
+            longitude: Set(None),
+            map_url: Set(None),
+            ..Default::default()
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn add_and_update_location() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let stage = add_location(new_location("Stage"), &db).await.unwrap();
+        assert!(matches!(
+            add_location(new_location("Stage"), &db).await,
+            Err(Error::LocationExists)
+        ));
+
+        let mut half = new_location("Field");
+        half.latitude = Set(Some(53.0));
+        assert!(matches!(
+            add_location(half, &db).await,
+            Err(Error::InvalidCoordinates)
+        ));
+        assert_eq!(get_location_id_by_name("Field", &db).await.unwrap(), None);
+
+        let mut linked = new_location("Gate");
+        linked.map_url = Set(Some("javascript:alert(1)".to_string()));
+        assert!(matches!(
+            add_location(linked, &db).await,
+            Err(Error::InvalidMapUrl)
+        ));
+        assert_eq!(get_location_id_by_name("Gate", &db).await.unwrap(), None);
+
+        let stage = update_location(
+            stage.id,
+            LocationChanges {
+                latitude: Some(Some(53.55)),
+                longitude: Some(Some(9.99)),
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stage.latitude, Some(53.55));
+
+        assert!(matches!(
+            update_location(
+                stage.id,
+                LocationChanges {
+                    longitude: Some(Some(200.0)),
+                    ..Default::default()
+                },
+                &db,
+            )
+            .await,
+            Err(Error::InvalidCoordinates)
+        ));
+
+        let stage = update_location(
+            stage.id,
+            LocationChanges {
+                map_url: Some(Some("https://osm.org/go/0MbEUi".to_string())),
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            update_location(
+                stage.id,
+                LocationChanges {
+                    map_url: Some(Some("JavaScript://%0Aalert(1)".to_string())),
+                    ..Default::default()
+                },
+                &db,
+            )
+            .await,
+            Err(Error::InvalidMapUrl)
+        ));
+        // Nothing was written
+        let stage = get_location_by_id(stage.id, &db).await.unwrap().unwrap();
+        assert_eq!(stage.longitude, Some(9.99));
+        assert_eq!(stage.map_url.as_deref(), Some("https://osm.org/go/0MbEUi"));
+    }
+
+    #[test(tokio::test)]
+    async fn filter_shifts_and_refuse_deleting_used_location() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let stage = add_location(new_location("Stage"), &db).await.unwrap();
+        let bar = add_location(new_location("Bar"), &db).await.unwrap();
+
+        let now = Utc::now();
+        for location_id in [Some(stage.id), None] {
+            add_shift(
+                shift::ActiveModel {
+                    created_by: Set(user.id),
+                    managed_by: Set(None),
+                    starts_at: Set(now),
+                    ends_at: Set(now + TimeDelta::hours(2)),
+                    name: Set("Shift".to_string()),
+                    description: Set(None),
+                    angels_needed: Set(1),
+                    angel_type_id: Set(None),
+                    location_id: Set(location_id),
+                    ..Default::default()
+                },
+                &Actor::cli(),
+                &db,
+            )
+            .await
+            .unwrap();
+        }
+
+        assert!(matches!(
+            add_shift(
+                shift::ActiveModel {
+                    created_by: Set(user.id),
+                    starts_at: Set(now),
+                    ends_at: Set(now + TimeDelta::hours(2)),
+                    name: Set("Shift".to_string()),
+                    angels_needed: Set(1),
+                    location_id: Set(Some(bar.id + 1)),
+                    ..Default::default()
+                },
+                &Actor::cli(),
+                &db,
+            )
+            .await,
+            Err(Error::LocationNotFound)
+        ));
+
+        let page = get_shift_views(
+            ShiftQuery {
+                from: None,
+                until: None,
+                angel_type_id: None,
+                location_id: Some(stage.id),
+                free_only: false,
+                search: None,
+                after: None,
+                limit: 10,
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.shifts.len(), 1);
+        assert_eq!(page.shifts[0].location_id, Some(stage.id));
+
+        assert!(matches!(
+            delete_location(stage.id, &db).await,
+            Err(Error::LocationInUse)
+        ));
+        delete_location(bar.id, &db).await.unwrap();
+        assert!(matches!(
+            delete_location(bar.id, &db).await,
+            Err(Error::LocationNotFound)
+        ));
+    }
+}
@@ -0,0 +1,351 @@
+use chrono::{TimeDelta, Utc};
+use entity::intern::*;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{Condition, IntoActiveModel, QueryOrder, TransactionTrait, prelude::*};
+
+use crate::user::verify_user;
+use crate::{Error, LoginFailureKind};
+
+/// How failed logins slow down further attempts for the same account or from the same IP address
+#[derive(Debug, Clone)]
+pub struct LoginThrottle {
+    /// After this many failures in a row an account is locked for [`Self::lockout`]
+    pub max_account_failures: u32,
+    /// After this many failures in a row an IP address is locked for [`Self::lockout`]. Higher
+    /// than for accounts, since many angels can share the address of an event network.
+    pub max_ip_failures: u32,
+    /// The wait after the first failure, which doubles with every further failure
+    pub base_delay: TimeDelta,
+    pub max_delay: TimeDelta,
+    /// How long a lockout lasts. Counters also start over after this long without failures.
+    pub lockout: TimeDelta,
+}
+
+impl Default for LoginThrottle {
+    fn default() -> Self {
+        Self {
+            max_account_failures: 5,
+            max_ip_failures: 50,
+            base_delay: TimeDelta::seconds(1),
+            max_delay: TimeDelta::minutes(1),
+            lockout: TimeDelta::minutes(15),
+        }
+    }
+}
+
+impl LoginThrottle {
+    fn max_failures(&self, kind: LoginFailureKind) -> u32 {
+        match kind {
+            LoginFailureKind::Account => self.max_account_failures,
+            LoginFailureKind::Ip => self.max_ip_failures,
+        }
+    }
+
+    /// How long logins are blocked after `failures` failed ones in a row
+    pub fn block_duration(&self, kind: LoginFailureKind, failures: u32) -> TimeDelta {
+        if failures >= self.max_failures(kind) {
+            return self.lockout;
+        }
+
+        let factor = 1_i32 << failures.saturating_sub(1).min(20);
+        (self.base_delay * factor).min(self.max_delay)
+    }
+}
+
+fn blocking_condition(username: &str, ip: Option<&str>) -> Condition {
+    let mut keys = Condition::any().add(
+        login_failure::Column::Kind
+            .eq(LoginFailureKind::Account)
+            .and(login_failure::Column::Key.eq(username)),
+    );
+    if let Some(ip) = ip {
+        keys = keys.add(
+            login_failure::Column::Kind
+                .eq(LoginFailureKind::Ip)
+                .and(login_failure::Column::Key.eq(ip)),
+        );
+    }
+
+    Condition::all()
+        .add(keys)
+        .add(login_failure::Column::BlockedUntil.gt(Utc::now()))
+}
+
+async fn record_failure(
+    kind: LoginFailureKind,
+    key: &str,
+    throttle: &LoginThrottle,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    let now = Utc::now();
+    let txn = db.begin().await?;
+
+    let existing = LoginFailure::find_by_id((kind, key.to_string()))
+        .one(&txn)
+        .await?;
+
+    match existing {
+        Some(existing) => {
+            // A long enough pause without failures starts the count over
+            let failures = if existing.last_failure_at + throttle.lockout > now {
+                existing.failures + 1
+            } else {
+                1
+            };
+
+            let mut entry = existing.into_active_model();
+            entry.failures = Set(failures);
+            entry.last_failure_at = Set(now);
+            entry.blocked_until = Set(now + throttle.block_duration(kind, failures));
+            entry.update(&txn).await?;
+        }
+        None => {
+            login_failure::ActiveModel {
+                kind: Set(kind),
+                key: Set(key.to_string()),
+                failures: Set(1),
+                last_failure_at: Set(now),
+                blocked_until: Set(now + throttle.block_duration(kind, 1)),
+            }
+            .insert(&txn)
+            .await?;
+        }
+    }
+
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Checks the credentials of a login from `ip`, unless the account or the address is blocked
+/// because of earlier failures. Blocked logins fail with [`Error::LoginBlocked`] without looking
+/// at the password, the same way for existing and unknown usernames.
+///
+/// Failures count for both the account and the address. A successful login clears the count of
+/// the account but not of the address, so one valid account can't be used to keep guessing
+/// others.
+pub async fn attempt_login(
+    username: &str,
+    plain_password: &str,
+    ip: Option<&str>,
+    throttle: &LoginThrottle,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<user::Model>> {
+    let blocked_until = LoginFailure::find()
+        .filter(blocking_condition(username, ip))
+        .order_by_desc(login_failure::Column::BlockedUntil)
+        .one(db)
+        .await?
+        .map(|entry| entry.blocked_until);
+
+    if let Some(until) = blocked_until {
+        return Err(Error::LoginBlocked { until });
+    }
+
+    if let Some(user) = verify_user(username, plain_password, db).await {
+        LoginFailure::delete_by_id((LoginFailureKind::Account, username.to_string()))
+            .exec(db)
+            .await?;
+
+        return Ok(Some(user));
+    }
+
+    record_failure(LoginFailureKind::Account, username, throttle, db).await?;
+    if let Some(ip) = ip {
+        record_failure(LoginFailureKind::Ip, ip, throttle, db).await?;
+    }
+
+    Ok(None)
+}
+
+/// Returns the accounts and addresses that can't log in right now, the longest blocked first
+pub async fn get_login_lockouts(
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<login_failure::Model>> {
+    Ok(LoginFailure::find()
+        .filter(login_failure::Column::BlockedUntil.gt(Utc::now()))
+        .order_by_desc(login_failure::Column::BlockedUntil)
+        .all(db)
+        .await?)
+}
+
+/// Forgets the failed logins of an account or address, which lifts its lockout
+pub async fn clear_login_failures(
+    kind: LoginFailureKind,
+    key: &str,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    if LoginFailure::delete_by_id((kind, key.to_string()))
+        .exec(db)
+        .await?
+        .rows_affected
+        == 0
+    {
+        return Err(Error::LoginFailuresNotFound);
+    }
+
+    Ok(())
+}
+
+/// Forgets failed logins that no longer block anything and would start over with the next failure.
+/// Returns how many accounts and addresses were affected.
+pub async fn delete_stale_login_failures(
+    throttle: &LoginThrottle,
+    db: &DatabaseConnection,
+) -> crate::Result<u64> {
+    let now = Utc::now();
+
+    Ok(LoginFailure::delete_many()
+        .filter(login_failure::Column::BlockedUntil.lte(now))
+        .filter(login_failure::Column::LastFailureAt.lte(now - throttle.lockout))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+/// Forgets all failed logins and returns how many accounts and addresses were affected
+pub async fn clear_all_login_failures(db: &DatabaseConnection) -> crate::Result<u64> {
+    Ok(LoginFailure::delete_many().exec(db).await?.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::add_user;
+    use test_log::test;
+
+    fn throttle(base_delay: TimeDelta) -> LoginThrottle {
+        LoginThrottle {
+            max_account_failures: 3,
+            max_ip_failures: 10,
+            base_delay,
+            max_delay: TimeDelta::hours(1),
+            lockout: TimeDelta::hours(1),
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn lockout_after_failures() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+        add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let throttle = throttle(TimeDelta::zero());
+
+        // A success in between starts the count of the account over
+        for _ in 0..2 {
+            let login = attempt_login("Meow", "wrong", Some("10.0.0.1"), &throttle, &db);
+            assert!(login.await.unwrap().is_none());
+        }
+        let login = attempt_login("Meow", "awawa", Some("10.0.0.1"), &throttle, &db);
+        assert!(login.await.unwrap().is_some());
+
+        for _ in 0..3 {
+            let login = attempt_login("Meow", "wrong", Some("10.0.0.1"), &throttle, &db);
+            assert!(login.await.unwrap().is_none());
+        }
+
+        // Locked, even with the right password and from another address
+        assert!(matches!(
+            attempt_login("Meow", "awawa", Some("10.0.0.2"), &throttle, &db).await,
+            Err(Error::LoginBlocked { .. })
+        ));
+
+        // Unknown usernames are counted and locked the same way
+        for _ in 0..3 {
+            let login = attempt_login("Nobody", "wrong", None, &throttle, &db);
+            assert!(login.await.unwrap().is_none());
+        }
+        assert!(matches!(
+            attempt_login("Nobody", "wrong", None, &throttle, &db).await,
+            Err(Error::LoginBlocked { .. })
+        ));
+
+        let lockouts = get_login_lockouts(&db).await.unwrap();
+        assert_eq!(lockouts.len(), 2);
+        // The address has 5 failures, which is below its limit
+        assert!(
+            lockouts
+                .iter()
+                .all(|entry| entry.kind == LoginFailureKind::Account)
+        );
+
+        clear_login_failures(LoginFailureKind::Account, "Meow", &db)
+            .await
+            .unwrap();
+        let login = attempt_login("Meow", "awawa", Some("10.0.0.1"), &throttle, &db);
+        assert!(login.await.unwrap().is_some());
+
+        assert!(matches!(
+            clear_login_failures(LoginFailureKind::Account, "Meow", &db).await,
+            Err(Error::LoginFailuresNotFound)
+        ));
+        assert_eq!(clear_all_login_failures(&db).await.unwrap(), 2);
+    }
+
+    #[test(tokio::test)]
+    async fn backoff_blocks_address() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+        add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let throttle = throttle(TimeDelta::minutes(1));
+
+        let login = attempt_login("Other", "wrong", Some("10.0.0.1"), &throttle, &db);
+        assert!(login.await.unwrap().is_none());
+
+        // The address has to wait before it can try any account
+        assert!(matches!(
+            attempt_login("Meow", "awawa", Some("10.0.0.1"), &throttle, &db).await,
+            Err(Error::LoginBlocked { .. })
+        ));
+        let login = attempt_login("Meow", "awawa", Some("10.0.0.2"), &throttle, &db);
+        assert!(login.await.unwrap().is_some());
+    }
+
+    #[test(tokio::test)]
+    async fn stale_failures_are_deleted() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+        let throttle = throttle(TimeDelta::zero());
+
+        let login = attempt_login("Nobody", "wrong", Some("10.0.0.1"), &throttle, &db);
+        assert!(login.await.unwrap().is_none());
+
+        // Still within the lockout window, the next failure would count on
+        assert_eq!(
+            delete_stale_login_failures(&throttle, &db).await.unwrap(),
+            0
+        );
+
+        let throttle = LoginThrottle {
+            lockout: TimeDelta::zero(),
+            ..throttle
+        };
+        assert_eq!(
+            delete_stale_login_failures(&throttle, &db).await.unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn block_duration() {
+        let throttle = throttle(TimeDelta::seconds(1));
+
+        assert_eq!(
+            throttle.block_duration(LoginFailureKind::Account, 1),
+            TimeDelta::seconds(1)
+        );
+        assert_eq!(
+            throttle.block_duration(LoginFailureKind::Account, 2),
+            TimeDelta::seconds(2)
+        );
+        assert_eq!(
+            throttle.block_duration(LoginFailureKind::Account, 3),
+            TimeDelta::hours(1)
+        );
+        assert_eq!(
+            throttle.block_duration(LoginFailureKind::Ip, 3),
+            TimeDelta::seconds(4)
+        );
+    }
+}
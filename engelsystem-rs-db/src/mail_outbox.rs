@@ -0,0 +1,342 @@
+use chrono::{TimeDelta, Utc};
+use entity::intern::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{IntoActiveModel, QueryOrder, QuerySelect, prelude::*};
+
+use crate::Error;
+
+/// Which mails [`get_outbox_mails`] returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxFilter {
+    All,
+    Pending,
+    Sent,
+    Failed,
+}
+
+/// How often and how fast failed mails are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// After this many failed attempts a mail is given up on
+    pub max_attempts: u32,
+    /// The wait after the first failure, which doubles with every further failure
+    pub base_delay: TimeDelta,
+    pub max_delay: TimeDelta,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            base_delay: TimeDelta::minutes(1),
+            max_delay: TimeDelta::hours(6),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait before the next attempt after `attempts` failed ones
+    pub fn delay(&self, attempts: u32) -> TimeDelta {
+        let factor = 1_i32 << attempts.saturating_sub(1).min(20);
+        (self.base_delay * factor).min(self.max_delay)
+    }
+}
+
+/// Adds a mail to the outbox, from where it's sent as soon as possible. The body of a mail that
+/// `contains_secret` isn't kept once the mail was sent or given up on.
+pub async fn enqueue_mail<C: ConnectionTrait>(
+    recipient: impl Into<String>,
+    subject: impl Into<String>,
+    body: impl Into<String>,
+    contains_secret: bool,
+    db: &C,
+) -> crate::Result<mail_outbox::Model> {
+    Ok(mail_outbox::ActiveModel {
+        id: NotSet,
+        created_at: NotSet,
+        recipient: Set(recipient.into()),
+        subject: Set(subject.into()),
+        body: Set(body.into()),
+        contains_secret: Set(contains_secret),
+        attempts: Set(0),
+        next_attempt_at: Set(Utc::now()),
+        last_error: Set(None),
+        sent_at: Set(None),
+        failed_at: Set(None),
+    }
+    .insert(db)
+    .await?)
+}
+
+fn pending() -> sea_orm::Condition {
+    sea_orm::Condition::all()
+        .add(mail_outbox::Column::SentAt.is_null())
+        .add(mail_outbox::Column::FailedAt.is_null())
+}
+
+/// Returns up to `limit` mails that are due to be sent. They are not returned again for the
+/// duration of `lease`, so several senders can share an outbox without sending a mail twice.
+pub async fn claim_due_mails(
+    lease: TimeDelta,
+    limit: u64,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<mail_outbox::Model>> {
+    let now = Utc::now();
+
+    let due = MailOutbox::find()
+        .filter(pending())
+        .filter(mail_outbox::Column::NextAttemptAt.lte(now))
+        .order_by_asc(mail_outbox::Column::NextAttemptAt)
+        .limit(limit)
+        .all(db)
+        .await?;
+
+    let mut claimed = Vec::with_capacity(due.len());
+
+    for mail in due {
+        // Whoever moves the next attempt first gets the mail
+        let rows = MailOutbox::update_many()
+            .col_expr(mail_outbox::Column::NextAttemptAt, Expr::value(now + lease))
+            .filter(mail_outbox::Column::Id.eq(mail.id))
+            .filter(mail_outbox::Column::NextAttemptAt.eq(mail.next_attempt_at))
+            .exec(db)
+            .await?
+            .rows_affected;
+
+        if rows == 1 {
+            claimed.push(mail);
+        }
+    }
+
+    Ok(claimed)
+}
+
+/// Removes the body of a mail that contains a secret
+fn clear_secret(mail: &mut mail_outbox::ActiveModel) {
+    if mail.contains_secret.as_ref() == &true {
+        mail.body = Set(String::new());
+    }
+}
+
+pub async fn mark_mail_sent(mail_id: u32, db: &DatabaseConnection) -> crate::Result<()> {
+    let mail = MailOutbox::find_by_id(mail_id)
+        .one(db)
+        .await?
+        .ok_or(Error::OutboxMailNotFound)?;
+
+    let mut mail = mail.into_active_model();
+    mail.sent_at = Set(Some(Utc::now()));
+    mail.last_error = Set(None);
+    clear_secret(&mut mail);
+    mail.update(db).await?;
+
+    Ok(())
+}
+
+/// Records a failed attempt. The mail is retried later according to the policy, or given up on
+/// once it failed too often. Returns the updated mail.
+pub async fn mark_mail_failed(
+    mail_id: u32,
+    error: &str,
+    policy: &RetryPolicy,
+    db: &DatabaseConnection,
+) -> crate::Result<mail_outbox::Model> {
+    let mail = MailOutbox::find_by_id(mail_id)
+        .one(db)
+        .await?
+        .ok_or(Error::OutboxMailNotFound)?;
+
+    let attempts = mail.attempts + 1;
+    let now = Utc::now();
+
+    let mut mail = mail.into_active_model();
+    mail.attempts = Set(attempts);
+    mail.last_error = Set(Some(error.to_string()));
+
+    if attempts >= policy.max_attempts {
+        mail.failed_at = Set(Some(now));
+        clear_secret(&mut mail);
+    } else {
+        mail.next_attempt_at = Set(now + policy.delay(attempts));
+    }
+
+    Ok(mail.update(db).await?)
+}
+
+/// Puts a mail that was given up on back into the outbox with a fresh set of attempts
+pub async fn retry_mail(mail_id: u32, db: &DatabaseConnection) -> crate::Result<()> {
+    let mail = MailOutbox::find_by_id(mail_id)
+        .one(db)
+        .await?
+        .ok_or(Error::OutboxMailNotFound)?;
+
+    if mail.sent_at.is_some() {
+        return Err(Error::OutboxMailSent);
+    }
+    if mail.contains_secret && mail.failed_at.is_some() {
+        return Err(Error::OutboxMailSecret);
+    }
+
+    let mut mail = mail.into_active_model();
+    mail.attempts = Set(0);
+    mail.failed_at = Set(None);
+    mail.next_attempt_at = Set(Utc::now());
+    mail.update(db).await?;
+
+    Ok(())
+}
+
+pub async fn get_outbox_mails(
+    filter: OutboxFilter,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<mail_outbox::Model>> {
+    let query = MailOutbox::find().order_by_desc(mail_outbox::Column::Id);
+
+    let query = match filter {
+        OutboxFilter::All => query,
+        OutboxFilter::Pending => query.filter(pending()),
+        OutboxFilter::Sent => query.filter(mail_outbox::Column::SentAt.is_not_null()),
+        OutboxFilter::Failed => query.filter(mail_outbox::Column::FailedAt.is_not_null()),
+    };
+
+    Ok(query.all(db).await?)
+}
+
+/// Removes mails that were sent before `older_than`. Returns the amount of removed mails.
+pub async fn delete_sent_mails(
+    older_than: TimeDelta,
+    db: &DatabaseConnection,
+) -> crate::Result<u64> {
+    Ok(MailOutbox::delete_many()
+        .filter(mail_outbox::Column::SentAt.lt(Utc::now() - older_than))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::connect_and_migrate_dummy;
+    use test_log::test;
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.delay(1), TimeDelta::minutes(1));
+        assert_eq!(policy.delay(2), TimeDelta::minutes(2));
+        assert_eq!(policy.delay(4), TimeDelta::minutes(8));
+        assert_eq!(policy.delay(30), TimeDelta::hours(6));
+    }
+
+    #[test(tokio::test)]
+    async fn claim_retry_and_give_up() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let mail = enqueue_mail("meow@meow.de", "Meow", "Meow meow", false, &db)
+            .await
+            .unwrap();
+
+        let claimed = claim_due_mails(TimeDelta::minutes(5), 10, &db)
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        // A claimed mail isn't handed out twice
+        assert!(
+            claim_due_mails(TimeDelta::minutes(5), 10, &db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            base_delay: TimeDelta::zero(),
+            max_delay: TimeDelta::zero(),
+        };
+
+        let failed = mark_mail_failed(mail.id, "Connection refused", &policy, &db)
+            .await
+            .unwrap();
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.failed_at, None);
+        assert_eq!(
+            claim_due_mails(TimeDelta::minutes(5), 10, &db)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let failed = mark_mail_failed(mail.id, "Connection refused", &policy, &db)
+            .await
+            .unwrap();
+        assert!(failed.failed_at.is_some());
+        assert!(
+            claim_due_mails(TimeDelta::minutes(5), 10, &db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            get_outbox_mails(OutboxFilter::Failed, &db)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        retry_mail(mail.id, &db).await.unwrap();
+        let claimed = claim_due_mails(TimeDelta::minutes(5), 10, &db)
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+
+        mark_mail_sent(mail.id, &db).await.unwrap();
+        assert!(
+            get_outbox_mails(OutboxFilter::Pending, &db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(matches!(
+            retry_mail(mail.id, &db).await,
+            Err(Error::OutboxMailSent)
+        ));
+
+        assert_eq!(delete_sent_mails(TimeDelta::zero(), &db).await.unwrap(), 1);
+    }
+
+    #[test(tokio::test)]
+    async fn secret_is_removed_after_sending_or_giving_up() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let sent = enqueue_mail("meow@meow.de", "Reset", "Token: meow", true, &db)
+            .await
+            .unwrap();
+        let failed = enqueue_mail("meow@meow.de", "Reset", "Token: mrrp", true, &db)
+            .await
+            .unwrap();
+
+        mark_mail_sent(sent.id, &db).await.unwrap();
+
+        let policy = RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        };
+        let failed = mark_mail_failed(failed.id, "Connection refused", &policy, &db)
+            .await
+            .unwrap();
+        assert!(failed.failed_at.is_some());
+        assert_eq!(failed.body, "");
+        assert!(matches!(
+            retry_mail(failed.id, &db).await,
+            Err(Error::OutboxMailSecret)
+        ));
+
+        let sent = get_outbox_mails(OutboxFilter::Sent, &db).await.unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].body, "");
+    }
+}
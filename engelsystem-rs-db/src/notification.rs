@@ -0,0 +1,183 @@
+use chrono::Utc;
+use entity::intern::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{IntoActiveModel, PaginatorTrait, QueryOrder, prelude::*};
+
+use crate::Error;
+
+/// Records the same notification for every given user
+pub async fn emit_notification<C: ConnectionTrait>(
+    user_ids: &[Uuid],
+    kind: notification::NotificationKind,
+    shift_id: Option<Uuid>,
+    message: &str,
+    db: &C,
+) -> crate::Result<()> {
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+
+    Notification::insert_many(user_ids.iter().map(|user_id| notification::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(*user_id),
+        kind: Set(kind),
+        shift_id: Set(shift_id),
+        message: Set(message.to_string()),
+        created_at: NotSet,
+        read_at: Set(None),
+    }))
+    .exec(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the notifications of a user, newest first
+pub async fn get_notifications_by_user(
+    user_id: Uuid,
+    unread_only: bool,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<notification::Model>> {
+    let query = Notification::find()
+        .filter(notification::Column::UserId.eq(user_id))
+        .order_by_desc(notification::Column::CreatedAt);
+
+    let query = if unread_only {
+        query.filter(notification::Column::ReadAt.is_null())
+    } else {
+        query
+    };
+
+    Ok(query.all(db).await?)
+}
+
+pub async fn get_unread_notification_count(
+    user_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<u64> {
+    Ok(Notification::find()
+        .filter(notification::Column::UserId.eq(user_id))
+        .filter(notification::Column::ReadAt.is_null())
+        .count(db)
+        .await?)
+}
+
+/// Marks a notification of the user as read. Notifications of other users count as not found.
+pub async fn mark_notification_read(
+    user_id: Uuid,
+    notification_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    let notification = Notification::find_by_id(notification_id)
+        .filter(notification::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .ok_or(Error::NotificationNotFound)?;
+
+    if notification.read_at.is_none() {
+        let mut notification = notification.into_active_model();
+        notification.read_at = Set(Some(Utc::now()));
+        notification.update(db).await?;
+    }
+
+    Ok(())
+}
+
+/// Marks all notifications of the user as read. Returns how many were unread.
+pub async fn mark_all_notifications_read(
+    user_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<u64> {
+    Ok(Notification::update_many()
+        .col_expr(notification::Column::ReadAt, Expr::value(Utc::now()))
+        .filter(notification::Column::UserId.eq(user_id))
+        .filter(notification::Column::ReadAt.is_null())
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::angel_type::{
+        add_angel_type, confirm_angel_type_membership, request_angel_type_membership,
+        set_angel_type_supporter,
+    };
+    use crate::audit::Actor;
+    use crate::role::RoleType;
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::{add_admin, add_user, set_role_by_username};
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn read_notifications() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let admin = add_admin("Admin", "admin@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let tech = add_angel_type("Tech", true, &db).await.unwrap();
+
+        request_angel_type_membership(user.id, tech.id, &db)
+            .await
+            .unwrap();
+        confirm_angel_type_membership(user.id, tech.id, admin.id, &db)
+            .await
+            .unwrap();
+        // Confirming twice doesn't notify twice
+        confirm_angel_type_membership(user.id, tech.id, admin.id, &db)
+            .await
+            .unwrap();
+        set_angel_type_supporter(user.id, tech.id, true, admin.id, &db)
+            .await
+            .unwrap();
+        set_role_by_username("Meow", RoleType::Guest as u32, &Actor::cli(), &db)
+            .await
+            .unwrap();
+
+        let notifications = get_notifications_by_user(user.id, false, &db)
+            .await
+            .unwrap();
+        let mut kinds: Vec<_> = notifications.iter().map(|n| n.kind).collect();
+        kinds.sort_by_key(|kind| *kind as u8);
+        assert_eq!(
+            kinds,
+            [
+                notification::NotificationKind::AngelTypeConfirmed,
+                notification::NotificationKind::AngelTypeSupporter,
+                notification::NotificationKind::RoleChanged,
+            ]
+        );
+        assert_eq!(
+            get_unread_notification_count(user.id, &db).await.unwrap(),
+            3
+        );
+
+        mark_notification_read(user.id, notifications[0].id, &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_notifications_by_user(user.id, true, &db)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // Other users can't touch the notification
+        assert!(matches!(
+            mark_notification_read(admin.id, notifications[1].id, &db).await,
+            Err(Error::NotificationNotFound)
+        ));
+
+        assert_eq!(mark_all_notifications_read(user.id, &db).await.unwrap(), 2);
+        assert_eq!(
+            get_unread_notification_count(user.id, &db).await.unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use chrono::{TimeDelta, Utc};
+use entity::intern::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{IntoActiveModel, TransactionTrait, prelude::*};
+
+use crate::Error;
+use crate::session::delete_user_sessions;
+use crate::token::{generate_token, hash_token};
+use crate::user::hash_password;
+
+/// Creates a password reset token for the user with the given email, which is valid for `ttl`.
+/// Earlier tokens of the user stop working.
+///
+/// Returns `None` if there's no user with this email. Callers must not tell this apart from a
+/// successful request, otherwise anyone could find out which emails are registered.
+pub async fn create_password_reset_token(
+    email: &str,
+    ttl: TimeDelta,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<(user::Model, String)>> {
+    let Some(user) = User::find()
+        .filter(user::Column::Email.eq(email))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let token = generate_token();
+
+    let txn = db.begin().await?;
+
+    PasswordResetToken::delete_many()
+        .filter(password_reset_token::Column::UserId.eq(user.id))
+        .exec(&txn)
+        .await?;
+
+    password_reset_token::ActiveModel {
+        token_hash: Set(hash_token(&token)),
+        user_id: Set(user.id),
+        created_at: NotSet,
+        expires_at: Set(Utc::now() + ttl),
+        used_at: Set(None),
+    }
+    .insert(&txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(Some((user, token)))
+}
+
+/// Sets a new password for the user the token belongs to and ends all of their sessions. The token
+/// can only be used once and only before it expires, otherwise [`Error::InvalidResetToken`] is
+/// returned.
+pub async fn reset_password(
+    token: &str,
+    new_password: &str,
+    db: &DatabaseConnection,
+) -> crate::Result<user::Model> {
+    let token_hash = hash_token(token);
+    let password_hash = hash_password(new_password)?;
+
+    let txn = db.begin().await?;
+
+    let Some(entry) = PasswordResetToken::find_by_id(token_hash.clone())
+        .one(&txn)
+        .await?
+    else {
+        return Err(Error::InvalidResetToken);
+    };
+
+    let now = Utc::now();
+    if entry.expires_at <= now {
+        return Err(Error::InvalidResetToken);
+    }
+
+    // Only the first of two concurrent requests with the same token gets to mark it as used
+    let claimed = PasswordResetToken::update_many()
+        .col_expr(password_reset_token::Column::UsedAt, Expr::value(now))
+        .filter(password_reset_token::Column::TokenHash.eq(token_hash))
+        .filter(password_reset_token::Column::UsedAt.is_null())
+        .exec(&txn)
+        .await?
+        .rows_affected;
+
+    if claimed == 0 {
+        return Err(Error::InvalidResetToken);
+    }
+
+    let user = User::find_by_id(entry.user_id)
+        .one(&txn)
+        .await?
+        .ok_or(Error::InvalidResetToken)?;
+
+    let mut user = user.into_active_model();
+    user.password_hash = Set(password_hash);
+    let user = user.update(&txn).await?;
+
+    delete_user_sessions(user.id, &txn).await?;
+
+    txn.commit().await?;
+
+    Ok(user)
+}
+
+/// Removes tokens that expired or were used. Returns the amount of removed tokens.
+pub async fn delete_stale_password_reset_tokens(db: &DatabaseConnection) -> crate::Result<u64> {
+    Ok(PasswordResetToken::delete_many()
+        .filter(
+            password_reset_token::Column::ExpiresAt
+                .lte(Utc::now())
+                .or(password_reset_token::Column::UsedAt.is_not_null()),
+        )
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::{add_user, verify_user};
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn unknown_email_gives_no_token() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        assert!(
+            create_password_reset_token("nobody@meow.de", TimeDelta::hours(1), &db)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test(tokio::test)]
+    async fn token_works_once() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        let (found, first) = create_password_reset_token("meow@meow.de", TimeDelta::hours(1), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, user.id);
+
+        // Requesting another token invalidates the first one
+        let (_, second) = create_password_reset_token("meow@meow.de", TimeDelta::hours(1), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            reset_password(&first, "nyanya", &db).await,
+            Err(Error::InvalidResetToken)
+        ));
+
+        reset_password(&second, "nyanya", &db).await.unwrap();
+        assert!(verify_user("Meow", "nyanya", &db).await.is_some());
+
+        assert!(matches!(
+            reset_password(&second, "purrpurr", &db).await,
+            Err(Error::InvalidResetToken)
+        ));
+        assert!(verify_user("Meow", "nyanya", &db).await.is_some());
+
+        assert_eq!(delete_stale_password_reset_tokens(&db).await.unwrap(), 1);
+    }
+
+    #[test(tokio::test)]
+    async fn expired_token_is_rejected() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        let (_, token) = create_password_reset_token("meow@meow.de", TimeDelta::seconds(-1), &db)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(
+            reset_password(&token, "nyanya", &db).await,
+            Err(Error::InvalidResetToken)
+        ));
+        assert!(verify_user("Meow", "awawa", &db).await.is_some());
+    }
+}
@@ -1,5 +1,48 @@
-use entity::intern::*;
-use sea_orm::prelude::*;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use entity::intern::{role::RoleId, *};
+use sea_orm::{JoinType, QuerySelect, prelude::*};
+use serde::{Deserialize, Serialize};
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+use tracing::warn;
+
+/// All permissions the application checks for. The variant names match the `name` column of the
+/// `permission` table.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Copy,
+    Clone,
+    Serialize,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    IntoStaticStr,
+)]
+pub enum PermissionType {
+    AddUser,
+    DeleteUser,
+    ViewUsers,
+    SignUpForShifts,
+    ManageShifts,
+    JoinAngelTypes,
+    ManageAngelTypes,
+    ManageRoles,
+    ManageLocations,
+    ViewAuditLog,
+    ViewLoginLockouts,
+    EndUserSessions,
+    ManageApiTokens,
+}
+
+impl PermissionType {
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+}
 
 pub async fn get_perm_count(db: &DatabaseConnection) -> crate::Result<u64> {
     Ok(Permission::find().count(db).await?)
@@ -16,10 +59,51 @@ pub async fn get_permission_by_name(
     Ok(result)
 }
 
+/// Returns all permissions that are enabled for the given role. Permissions in the database that
+/// this version doesn't know about are skipped.
+pub async fn get_role_permissions(
+    role_id: RoleId,
+    db: &DatabaseConnection,
+) -> crate::Result<HashSet<PermissionType>> {
+    let names: Vec<String> = Permission::find()
+        .join(
+            JoinType::InnerJoin,
+            permission::Relation::RolePermission.def(),
+        )
+        .filter(role_permission::Column::RoleId.eq(role_id))
+        .filter(role_permission::Column::Enabled.eq(true))
+        .select_only()
+        .column(permission::Column::Name)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(names
+        .iter()
+        .filter_map(|name| {
+            PermissionType::from_str(name)
+                .inspect_err(|_| warn!("Skipping unknown permission {name:?}"))
+                .ok()
+        })
+        .collect())
+}
+
+pub async fn role_has_permission(
+    role_id: RoleId,
+    permission: PermissionType,
+    db: &DatabaseConnection,
+) -> crate::Result<bool> {
+    Ok(get_role_permissions(role_id, db)
+        .await?
+        .contains(&permission))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::role::RoleType;
     use crate::tests::connect_and_migrate_dummy;
+    use strum::IntoEnumIterator;
     use test_log::test;
 
     #[test(tokio::test)]
@@ -28,4 +112,39 @@ mod tests {
         let perm_count = get_perm_count(&db).await.unwrap();
         assert!(perm_count > 0);
     }
+
+    #[test(tokio::test)]
+    async fn every_permission_is_seeded() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        for permission in PermissionType::iter() {
+            assert!(
+                get_permission_by_name(permission.name(), &db)
+                    .await
+                    .unwrap()
+                    .is_some(),
+                "{permission:?} is missing in the database"
+            );
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn default_role_permissions() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let guest = get_role_permissions(RoleType::Guest as u32, &db)
+            .await
+            .unwrap();
+        let user = get_role_permissions(RoleType::User as u32, &db)
+            .await
+            .unwrap();
+        let admin = get_role_permissions(RoleType::Admin as u32, &db)
+            .await
+            .unwrap();
+
+        assert!(guest.is_empty());
+        assert!(user.contains(&PermissionType::SignUpForShifts));
+        assert!(!user.contains(&PermissionType::ManageShifts));
+        assert_eq!(admin.len(), PermissionType::iter().count());
+    }
 }
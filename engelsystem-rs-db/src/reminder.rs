@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+
+use chrono::{TimeDelta, Utc};
+use entity::intern::*;
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{QueryOrder, TransactionTrait, prelude::*};
+
+use crate::Error;
+use crate::mail_outbox::enqueue_mail;
+use crate::notification::emit_notification;
+
+/// The shortest lead time a user can choose
+pub const MIN_LEAD_TIME: TimeDelta = TimeDelta::minutes(5);
+/// The longest lead time a user can choose
+pub const MAX_LEAD_TIME: TimeDelta = TimeDelta::days(7);
+
+/// An angel that should be reminded of a shift now
+#[derive(Debug, Clone)]
+pub struct DueReminder {
+    pub user: user::Model,
+    pub shift: shift::Model,
+}
+
+/// The reminder mail for an angel, queued together with the notification
+#[derive(Debug, Clone)]
+pub struct ReminderMail {
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Returns how the user wants to be reminded of shifts. Users that never changed it get reminders
+/// with the default lead time.
+pub async fn get_reminder_preference(
+    user_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<reminder_preference::Model> {
+    Ok(ReminderPreference::find_by_id(user_id)
+        .one(db)
+        .await?
+        .unwrap_or(reminder_preference::Model {
+            user_id,
+            enabled: true,
+            lead_minutes: None,
+        }))
+}
+
+/// Stores how the user wants to be reminded of shifts. `lead_minutes` of `None` uses the default
+/// lead time.
+pub async fn set_reminder_preference(
+    user_id: Uuid,
+    enabled: bool,
+    lead_minutes: Option<u32>,
+    db: &DatabaseConnection,
+) -> crate::Result<reminder_preference::Model> {
+    if let Some(minutes) = lead_minutes {
+        let lead = TimeDelta::minutes(minutes.into());
+        if lead < MIN_LEAD_TIME || lead > MAX_LEAD_TIME {
+            return Err(Error::InvalidReminderLeadTime);
+        }
+    }
+
+    ReminderPreference::insert(reminder_preference::ActiveModel {
+        user_id: Set(user_id),
+        enabled: Set(enabled),
+        lead_minutes: Set(lead_minutes),
+    })
+    .on_conflict(
+        OnConflict::column(reminder_preference::Column::UserId)
+            .update_columns([
+                reminder_preference::Column::Enabled,
+                reminder_preference::Column::LeadMinutes,
+            ])
+            .to_owned(),
+    )
+    .exec(db)
+    .await?;
+
+    Ok(reminder_preference::Model {
+        user_id,
+        enabled,
+        lead_minutes,
+    })
+}
+
+/// Returns the angels whose shifts start within their lead time and that weren't reminded yet,
+/// ordered by the start of the shift. Shifts that already started are skipped.
+pub async fn get_due_reminders(
+    default_lead: TimeDelta,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<DueReminder>> {
+    let now = Utc::now();
+
+    let shifts: HashMap<Uuid, shift::Model> = Shift::find()
+        .filter(shift::Column::StartsAt.gt(now))
+        .filter(shift::Column::StartsAt.lte(now + default_lead.max(MAX_LEAD_TIME)))
+        .order_by_asc(shift::Column::StartsAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|shift| (shift.id, shift))
+        .collect();
+
+    if shifts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let entries = UserShift::find()
+        .filter(user_shift::Column::ShiftId.is_in(shifts.keys().copied()))
+        .filter(user_shift::Column::RemindedAt.is_null())
+        .all(db)
+        .await?;
+
+    let user_ids: Vec<Uuid> = entries.iter().map(|entry| entry.user_id).collect();
+
+    let preferences: HashMap<Uuid, reminder_preference::Model> = ReminderPreference::find()
+        .filter(reminder_preference::Column::UserId.is_in(user_ids.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|preference| (preference.user_id, preference))
+        .collect();
+
+    let users: HashMap<Uuid, user::Model> = User::find()
+        .filter(user::Column::Id.is_in(user_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+    let mut due: Vec<DueReminder> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let preference = preferences.get(&entry.user_id);
+            if preference.is_some_and(|preference| !preference.enabled) {
+                return None;
+            }
+
+            let lead = preference
+                .and_then(|preference| preference.lead_minutes)
+                .map_or(default_lead, |minutes| TimeDelta::minutes(minutes.into()));
+
+            let shift = &shifts[&entry.shift_id];
+            if shift.starts_at - lead > now {
+                return None;
+            }
+
+            Some(DueReminder {
+                user: users.get(&entry.user_id)?.clone(),
+                shift: shift.clone(),
+            })
+        })
+        .collect();
+
+    due.sort_by_key(|reminder| (reminder.shift.starts_at, reminder.shift.id));
+
+    Ok(due)
+}
+
+/// Marks the angel as reminded of the shift, leaves them a notification with the message and
+/// queues the mail in the outbox, all in one transaction. Returns false without doing anything if
+/// someone else already reminded them, so a reminder is only sent once even across restarts or
+/// with several schedulers.
+pub async fn record_reminder(
+    user_id: Uuid,
+    shift_id: Uuid,
+    message: &str,
+    mail: Option<ReminderMail>,
+    db: &DatabaseConnection,
+) -> crate::Result<bool> {
+    let txn = db.begin().await?;
+
+    let rows = UserShift::update_many()
+        .col_expr(user_shift::Column::RemindedAt, Expr::value(Utc::now()))
+        .filter(user_shift::Column::UserId.eq(user_id))
+        .filter(user_shift::Column::ShiftId.eq(shift_id))
+        .filter(user_shift::Column::RemindedAt.is_null())
+        .exec(&txn)
+        .await?
+        .rows_affected;
+
+    if rows == 0 {
+        return Ok(false);
+    }
+
+    emit_notification(
+        &[user_id],
+        notification::NotificationKind::ShiftReminder,
+        Some(shift_id),
+        message,
+        &txn,
+    )
+    .await?;
+
+    if let Some(mail) = mail {
+        enqueue_mail(mail.recipient, mail.subject, mail.body, false, &txn).await?;
+    }
+
+    txn.commit().await?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::Actor;
+    use crate::mail_outbox::{OutboxFilter, get_outbox_mails};
+    use crate::notification::get_notifications_by_user;
+    use crate::shift::tests::add_dummy_shift;
+    use crate::shift::{OverlapPolicy, ShiftChanges, sign_up_for_shift, update_shift};
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::add_user;
+    use test_log::test;
+
+    #[test(tokio::test)]
+    async fn remind_once_within_lead_time() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let soon = add_dummy_shift(user.id, TimeDelta::minutes(30), 1, &db).await;
+        let later = add_dummy_shift(user.id, TimeDelta::hours(3), 1, &db).await;
+
+        for shift in [&soon, &later] {
+            sign_up_for_shift(shift.id, user.id, OverlapPolicy::Allow, &db)
+                .await
+                .unwrap();
+        }
+
+        let due = get_due_reminders(TimeDelta::hours(1), &db).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].shift.id, soon.id);
+
+        set_reminder_preference(user.id, true, Some(240), &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_due_reminders(TimeDelta::hours(1), &db)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let mail = ReminderMail {
+            recipient: user.email.clone(),
+            subject: "Schichterinnerung".to_string(),
+            body: "Meow".to_string(),
+        };
+        assert!(
+            record_reminder(user.id, soon.id, "Meow", Some(mail.clone()), &db)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !record_reminder(user.id, soon.id, "Meow", Some(mail), &db)
+                .await
+                .unwrap()
+        );
+        let outbox = get_outbox_mails(OutboxFilter::Pending, &db).await.unwrap();
+        assert_eq!(outbox.len(), 1);
+        assert_eq!(outbox[0].recipient, user.email);
+
+        let due = get_due_reminders(TimeDelta::hours(1), &db).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].shift.id, later.id);
+
+        let notifications = get_notifications_by_user(user.id, false, &db)
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(
+            notifications[0].kind,
+            notification::NotificationKind::ShiftReminder
+        );
+
+        // Moving the shift makes the angel due for a reminder about the new time
+        update_shift(
+            soon.id,
+            ShiftChanges {
+                starts_at: Some(soon.starts_at + TimeDelta::minutes(10)),
+                ..Default::default()
+            },
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            get_due_reminders(TimeDelta::hours(1), &db)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        set_reminder_preference(user.id, false, None, &db)
+            .await
+            .unwrap();
+        assert!(
+            get_due_reminders(TimeDelta::hours(1), &db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test(tokio::test)]
+    async fn lead_time_is_bounded() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            set_reminder_preference(user.id, true, Some(1), &db).await,
+            Err(Error::InvalidReminderLeadTime)
+        ));
+        assert!(matches!(
+            set_reminder_preference(user.id, true, Some(60 * 24 * 8), &db).await,
+            Err(Error::InvalidReminderLeadTime)
+        ));
+
+        let preference = get_reminder_preference(user.id, &db).await.unwrap();
+        assert!(preference.enabled);
+        assert_eq!(preference.lead_minutes, None);
+
+        set_reminder_preference(user.id, true, Some(90), &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_reminder_preference(user.id, &db)
+                .await
+                .unwrap()
+                .lead_minutes,
+            Some(90)
+        );
+    }
+}
@@ -1,9 +1,21 @@
+pub use entity::intern::role::RoleId;
+
 use entity::intern::*;
-use sea_orm::prelude::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::sea_query::{IntoCondition, OnConflict};
+use sea_orm::{
+    IntoActiveModel, JoinType, QueryOrder, QuerySelect, SqlErr, TransactionTrait, prelude::*,
+};
 use serde::{Deserialize, Serialize};
 use strum_macros::{EnumString, FromRepr, IntoStaticStr};
-use tracing::error;
 
+use crate::AuditAction;
+use crate::Error;
+use crate::audit::{Actor, record_audit, snapshot};
+use crate::permission::PermissionType;
+
+/// The roles seeded by the initial migration. Further roles can be added at runtime and are only
+/// known by their id.
 #[derive(
     Debug,
     PartialEq,
@@ -27,11 +39,9 @@ pub enum RoleType {
 }
 
 impl RoleType {
-    pub fn from_or_default(value: u32) -> RoleType {
-        Self::from_repr(value).unwrap_or_else(|| {
-            error!("Couldn't get role type. Defaulting to Guest");
-            RoleType::Guest
-        })
+    /// Returns true if `role_id` refers to one of the seeded roles, which can't be deleted
+    pub fn is_builtin(role_id: RoleId) -> bool {
+        Self::from_repr(role_id).is_some()
     }
 
     pub fn is_bypass(&self) -> bool {
@@ -53,10 +63,227 @@ pub async fn get_role_by_name(
         .await?)
 }
 
+fn map_unique_violation(err: DbErr) -> Error {
+    match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => Error::RoleExists,
+        _ => err.into(),
+    }
+}
+
+pub async fn get_all_roles(db: &DatabaseConnection) -> crate::Result<Vec<role::Model>> {
+    Ok(Role::find().order_by_asc(role::Column::Id).all(db).await?)
+}
+
+pub async fn get_role_by_id(
+    role_id: RoleId,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<role::Model>> {
+    Ok(Role::find_by_id(role_id).one(db).await?)
+}
+
+/// Adds a role without any permissions
+pub async fn add_role(
+    name: impl Into<String>,
+    actor: &Actor,
+    db: &DatabaseConnection,
+) -> crate::Result<role::Model> {
+    let txn = db.begin().await?;
+
+    let role = role::ActiveModel {
+        id: NotSet,
+        name: Set(name.into()),
+    }
+    .insert(&txn)
+    .await
+    .map_err(map_unique_violation)?;
+    record_audit(
+        actor,
+        AuditAction::RoleCreated,
+        role.id,
+        None,
+        snapshot(&role),
+        &txn,
+    )
+    .await?;
+
+    txn.commit().await?;
+    Ok(role)
+}
+
+pub async fn rename_role(
+    role_id: RoleId,
+    name: impl Into<String>,
+    actor: &Actor,
+    db: &DatabaseConnection,
+) -> crate::Result<role::Model> {
+    let txn = db.begin().await?;
+
+    let before = Role::find_by_id(role_id)
+        .one(&txn)
+        .await?
+        .ok_or(Error::RoleNotFound)?;
+    let mut role = before.clone().into_active_model();
+    role.name = Set(name.into());
+
+    let role = role.update(&txn).await.map_err(map_unique_violation)?;
+    if role != before {
+        record_audit(
+            actor,
+            AuditAction::RoleRenamed,
+            role_id,
+            snapshot(&before),
+            snapshot(&role),
+            &txn,
+        )
+        .await?;
+    }
+
+    txn.commit().await?;
+    Ok(role)
+}
+
+/// Deletes a role together with its permission entries. The seeded roles and roles that are still
+/// assigned to users can't be deleted.
+pub async fn delete_role(
+    role_id: RoleId,
+    actor: &Actor,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    if RoleType::is_builtin(role_id) {
+        return Err(Error::RoleProtected);
+    }
+
+    let txn = db.begin().await?;
+
+    let role = Role::find_by_id(role_id)
+        .one(&txn)
+        .await?
+        .ok_or(Error::RoleNotFound)?;
+
+    if User::find()
+        .filter(user::Column::RoleId.eq(role_id))
+        .count(&txn)
+        .await?
+        > 0
+    {
+        return Err(Error::RoleInUse);
+    }
+
+    RolePermission::delete_many()
+        .filter(role_permission::Column::RoleId.eq(role_id))
+        .exec(&txn)
+        .await?;
+    record_audit(
+        actor,
+        AuditAction::RoleDeleted,
+        role_id,
+        snapshot(&role),
+        None,
+        &txn,
+    )
+    .await?;
+    role.delete(&txn).await?;
+
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Returns every permission together with whether it's enabled for the given role. Permissions
+/// without a `role_permission` entry count as disabled.
+pub async fn get_role_permission_views(
+    role_id: RoleId,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<role_permission::View>> {
+    if get_role_by_id(role_id, db).await?.is_none() {
+        return Err(Error::RoleNotFound);
+    }
+
+    Ok(Permission::find()
+        .select_only()
+        .column_as(permission::Column::Name, "permission")
+        .column_as(
+            Expr::col((RolePermission, role_permission::Column::Enabled)).if_null(false),
+            "enabled",
+        )
+        .join(
+            JoinType::LeftJoin,
+            permission::Relation::RolePermission
+                .def()
+                .on_condition(move |_, right| {
+                    Expr::col((right, role_permission::Column::RoleId))
+                        .eq(role_id)
+                        .into_condition()
+                }),
+        )
+        .order_by_asc(permission::Column::Id)
+        .into_model::<role_permission::View>()
+        .all(db)
+        .await?)
+}
+
+pub async fn set_role_permission(
+    role_id: RoleId,
+    permission: PermissionType,
+    enabled: bool,
+    actor: &Actor,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    if get_role_by_id(role_id, db).await?.is_none() {
+        return Err(Error::RoleNotFound);
+    }
+
+    let name = permission.name();
+    let permission = Permission::find()
+        .filter(permission::Column::Name.eq(name))
+        .one(db)
+        .await?
+        .ok_or(Error::PermissionNotFound)?;
+
+    let txn = db.begin().await?;
+
+    let was_enabled = RolePermission::find_by_id((role_id, permission.id))
+        .one(&txn)
+        .await?
+        .is_some_and(|entry| entry.enabled);
+
+    RolePermission::insert(role_permission::ActiveModel {
+        role_id: Set(role_id),
+        permission_id: Set(permission.id),
+        enabled: Set(enabled),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            role_permission::Column::RoleId,
+            role_permission::Column::PermissionId,
+        ])
+        .update_column(role_permission::Column::Enabled)
+        .to_owned(),
+    )
+    .exec(&txn)
+    .await?;
+
+    if was_enabled != enabled {
+        record_audit(
+            actor,
+            AuditAction::RolePermissionChanged,
+            role_id,
+            Some(serde_json::json!({ "permission": name, "enabled": was_enabled })),
+            Some(serde_json::json!({ "permission": name, "enabled": enabled })),
+            &txn,
+        )
+        .await?;
+    }
+
+    txn.commit().await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::permission::role_has_permission;
     use crate::tests::connect_and_migrate_dummy;
+    use crate::user::{add_guest, set_role_by_username};
     use test_log::test;
 
     #[test(tokio::test)]
@@ -90,4 +317,82 @@ mod tests {
         assert_eq!(user.name, "User");
         assert_eq!(admin.name, "Administrator");
     }
+
+    #[test(tokio::test)]
+    async fn custom_role_lifecycle() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let role = add_role("Schichtkoordination", &Actor::cli(), &db)
+            .await
+            .unwrap();
+        assert!(!RoleType::is_builtin(role.id));
+        assert!(matches!(
+            add_role("Schichtkoordination", &Actor::cli(), &db).await,
+            Err(Error::RoleExists)
+        ));
+
+        let role = rename_role(role.id, "Teamleitung", &Actor::cli(), &db)
+            .await
+            .unwrap();
+        assert_eq!(role.name, "Teamleitung");
+
+        let views = get_role_permission_views(role.id, &db).await.unwrap();
+        assert!(!views.is_empty());
+        assert!(views.iter().all(|view| !view.enabled));
+
+        set_role_permission(
+            role.id,
+            PermissionType::ManageShifts,
+            true,
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(
+            role_has_permission(role.id, PermissionType::ManageShifts, &db)
+                .await
+                .unwrap()
+        );
+        set_role_permission(
+            role.id,
+            PermissionType::ManageShifts,
+            false,
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(
+            !role_has_permission(role.id, PermissionType::ManageShifts, &db)
+                .await
+                .unwrap()
+        );
+
+        delete_role(role.id, &Actor::cli(), &db).await.unwrap();
+        assert!(get_role_by_id(role.id, &db).await.unwrap().is_none());
+    }
+
+    #[test(tokio::test)]
+    async fn protected_roles() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        assert!(matches!(
+            delete_role(RoleType::Guest as u32, &Actor::cli(), &db).await,
+            Err(Error::RoleProtected)
+        ));
+
+        let role = add_role("Teamleitung", &Actor::cli(), &db).await.unwrap();
+        let user = add_guest("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        set_role_by_username(&user.username, role.id, &Actor::cli(), &db)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            delete_role(role.id, &Actor::cli(), &db).await,
+            Err(Error::RoleInUse)
+        ));
+    }
 }
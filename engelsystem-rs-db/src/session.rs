@@ -5,13 +5,19 @@ use rand::Rng;
 use rand::distr::Alphanumeric;
 use sea_orm::ActiveValue::{NotSet, Set};
 use sea_orm::entity::prelude::*;
-use sea_orm::{DatabaseConnection, EntityTrait, IntoActiveModel};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, IntoActiveModel, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::de::DeserializeOwned;
 use snafu::ResultExt;
 
 use snafu::Snafu;
 use time::{Duration, OffsetDateTime};
 use tracing::debug;
 
+use crate::AuditAction;
+use crate::audit::{Actor, record_audit};
+
 type SessionResult<T, E = SessionError> = std::result::Result<T, E>;
 
 #[derive(Debug, Snafu)]
@@ -30,6 +36,21 @@ pub enum SessionError {
     SessionNotFound,
 }
 
+/// The id of the logged in user, as stored by the API on login
+fn session_user_id(session_state: &HashMap<String, String>) -> Option<Uuid> {
+    session_state_value(session_state, "user_id")
+}
+
+/// A value the API stored in the session state. The state holds each value as a JSON string.
+pub fn session_state_value<T: DeserializeOwned>(
+    session_state: &HashMap<String, String>,
+    key: &str,
+) -> Option<T> {
+    session_state
+        .get(key)
+        .and_then(|value| serde_json::from_str(value).ok())
+}
+
 pub async fn load_session(
     db: &DatabaseConnection,
     session_key: &str,
@@ -58,6 +79,10 @@ pub async fn save_session(
 ) -> SessionResult<String> {
     debug!("Saving session...");
 
+    let user_id = session_user_id(&session_state);
+    let public_id = session_state_value(&session_state, "session_id").unwrap_or_else(Uuid::new_v4);
+    let ip = session_state_value(&session_state, "ip");
+    let user_agent = session_state_value(&session_state, "user_agent");
     let data = serde_json::to_string(&session_state).context(SessionSerializeErr)?;
     let expires_at = time::OffsetDateTime::now_utc() + *ttl;
     let session_key: String = rand::rng()
@@ -71,6 +96,11 @@ pub async fn save_session(
         created_at: NotSet,
         data: Set(data),
         expires_at: Set(expires_at),
+        user_id: Set(user_id),
+        public_id: Set(public_id),
+        last_active_at: Set(Some(chrono::Utc::now())),
+        ip: Set(ip),
+        user_agent: Set(user_agent),
     }
     .insert(db)
     .await?;
@@ -86,6 +116,7 @@ pub async fn update_session(
 ) -> SessionResult<()> {
     debug!("Updating session...");
 
+    let user_id = session_user_id(&session_state);
     let data = serde_json::to_string(&session_state).context(SessionSerializeErr)?;
     let expires_at = OffsetDateTime::now_utc() + *ttl;
 
@@ -97,12 +128,26 @@ pub async fn update_session(
 
     session.data = Set(data);
     session.expires_at = Set(expires_at);
+    session.user_id = Set(user_id);
+    session.last_active_at = Set(Some(chrono::Utc::now()));
+    // Set on login, which can happen in an existing anonymous session
+    if let Some(public_id) = session_state_value(&session_state, "session_id") {
+        session.public_id = Set(public_id);
+    }
+    if let Some(ip) = session_state_value(&session_state, "ip") {
+        session.ip = Set(Some(ip));
+    }
+    if let Some(user_agent) = session_state_value(&session_state, "user_agent") {
+        session.user_agent = Set(Some(user_agent));
+    }
 
     session.save(db).await?;
 
     Ok(())
 }
 
+/// Extends the lifetime of a session that is used without being changed, which also marks it as
+/// active
 pub async fn update_session_ttl(
     db: &DatabaseConnection,
     session_key: &str,
@@ -110,14 +155,22 @@ pub async fn update_session_ttl(
 ) -> SessionResult<()> {
     debug!("Updating session TTL...");
 
-    let mut session = Session::find_by_id(session_key)
-        .one(db)
-        .await?
-        .ok_or(SessionError::SessionNotFound)?
-        .into_active_model();
+    let updated = Session::update_many()
+        .col_expr(
+            session::Column::ExpiresAt,
+            Expr::value(OffsetDateTime::now_utc() + *ttl),
+        )
+        .col_expr(
+            session::Column::LastActiveAt,
+            Expr::value(chrono::Utc::now()),
+        )
+        .filter(session::Column::Id.eq(session_key))
+        .exec(db)
+        .await?;
 
-    session.expires_at = Set(OffsetDateTime::now_utc() + *ttl);
-    session.save(db).await?;
+    if updated.rows_affected == 0 {
+        return Err(SessionError::SessionNotFound);
+    }
 
     Ok(())
 }
@@ -136,3 +189,304 @@ pub async fn delete_session(db: &DatabaseConnection, session_key: &str) -> Sessi
 
     Ok(())
 }
+
+/// Ends all sessions of a user. Returns the amount of ended sessions.
+pub async fn delete_user_sessions<C: ConnectionTrait>(user_id: Uuid, db: &C) -> crate::Result<u64> {
+    debug!("Deleting all sessions of a user...");
+
+    Ok(Session::delete_many()
+        .filter(session::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+/// Returns the sessions of a user that haven't expired yet, most recently used first
+pub async fn get_user_sessions(
+    user_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<session::Model>> {
+    Ok(Session::find()
+        .filter(session::Column::UserId.eq(user_id))
+        .filter(session::Column::ExpiresAt.gt(OffsetDateTime::now_utc()))
+        .order_by_desc(session::Column::LastActiveAt)
+        .order_by_desc(session::Column::CreatedAt)
+        .all(db)
+        .await?)
+}
+
+/// Ends a session of a user by its public id. Sessions of other users count as not found.
+pub async fn delete_user_session(
+    user_id: Uuid,
+    public_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    debug!("Deleting a session of a user...");
+
+    if Session::delete_many()
+        .filter(session::Column::UserId.eq(user_id))
+        .filter(session::Column::PublicId.eq(public_id))
+        .exec(db)
+        .await?
+        .rows_affected
+        == 0
+    {
+        return Err(crate::Error::SessionNotFound);
+    }
+
+    Ok(())
+}
+
+/// Logs a user out everywhere on behalf of someone else, e.g. an administrator. Returns the amount
+/// of ended sessions.
+pub async fn end_user_sessions<C: ConnectionTrait + TransactionTrait>(
+    user_id: Uuid,
+    actor: &Actor,
+    db: &C,
+) -> crate::Result<u64> {
+    let txn = db.begin().await?;
+
+    let ended = delete_user_sessions(user_id, &txn).await?;
+    record_user_sessions_ended(user_id, ended, actor, &txn).await?;
+
+    txn.commit().await?;
+
+    Ok(ended)
+}
+
+/// Records in the audit log that a user was logged out everywhere, for sessions kept outside of
+/// the database. Fails if the user doesn't exist.
+pub async fn record_user_sessions_ended<C: ConnectionTrait>(
+    user_id: Uuid,
+    ended: u64,
+    actor: &Actor,
+    db: &C,
+) -> crate::Result<()> {
+    User::find_by_id(user_id)
+        .one(db)
+        .await?
+        .ok_or(crate::Error::UserNotFound)?;
+
+    record_audit(
+        actor,
+        AuditAction::UserSessionsEnded,
+        user_id,
+        None,
+        Some(serde_json::json!({ "ended_sessions": ended })),
+        db,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes expired sessions in batches of `batch_size`, so the table isn't locked for long. Without
+/// this, sessions that are never loaded again would stay forever. Returns the amount of deleted
+/// sessions.
+pub async fn prune_expired_sessions(
+    batch_size: u64,
+    db: &DatabaseConnection,
+) -> crate::Result<u64> {
+    debug!("Deleting expired sessions...");
+
+    let mut deleted = 0;
+
+    loop {
+        let expired: Vec<String> = Session::find()
+            .select_only()
+            .column(session::Column::Id)
+            .filter(session::Column::ExpiresAt.lt(OffsetDateTime::now_utc()))
+            .limit(batch_size)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        if expired.is_empty() {
+            break;
+        }
+
+        let batch = expired.len() as u64;
+        deleted += Session::delete_many()
+            .filter(session::Column::Id.is_in(expired))
+            .exec(db)
+            .await?
+            .rows_affected;
+
+        if batch < batch_size {
+            break;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// The amount of sessions that haven't expired yet, and how many of them are logged in
+pub async fn get_live_session_count(db: &DatabaseConnection) -> crate::Result<(u64, u64)> {
+    let live = Session::find().filter(session::Column::ExpiresAt.gt(OffsetDateTime::now_utc()));
+
+    Ok((
+        live.clone().count(db).await?,
+        live.filter(session::Column::UserId.is_not_null())
+            .count(db)
+            .await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditQuery, get_audit_entries};
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::add_user;
+    use test_log::test;
+
+    fn login_state(user_id: Uuid, public_id: Uuid) -> HashMap<String, String> {
+        HashMap::from([
+            (
+                "user_id".to_string(),
+                serde_json::to_string(&user_id).unwrap(),
+            ),
+            (
+                "session_id".to_string(),
+                serde_json::to_string(&public_id).unwrap(),
+            ),
+            (
+                "ip".to_string(),
+                serde_json::to_string("192.0.2.1").unwrap(),
+            ),
+            (
+                "user_agent".to_string(),
+                serde_json::to_string("Meowzilla/5.0").unwrap(),
+            ),
+        ])
+    }
+
+    #[test(tokio::test)]
+    async fn list_and_delete_own_sessions() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let meow = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let nyan = add_user("Nyan", "nyan@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        let first = Uuid::new_v4();
+        let key = save_session(&db, login_state(meow.id, first), &Duration::hours(1))
+            .await
+            .unwrap();
+        let second = Uuid::new_v4();
+        save_session(&db, login_state(meow.id, second), &Duration::hours(1))
+            .await
+            .unwrap();
+        save_session(
+            &db,
+            login_state(meow.id, Uuid::new_v4()),
+            &Duration::seconds(-1),
+        )
+        .await
+        .unwrap();
+        // Anonymous sessions belong to nobody
+        save_session(&db, HashMap::new(), &Duration::hours(1))
+            .await
+            .unwrap();
+
+        // Using the first session makes it the most recently active one
+        update_session_ttl(&db, &key, &Duration::hours(1))
+            .await
+            .unwrap();
+
+        let sessions = get_user_sessions(meow.id, &db).await.unwrap();
+        assert_eq!(
+            sessions.iter().map(|s| s.public_id).collect::<Vec<_>>(),
+            [first, second]
+        );
+        assert_eq!(sessions[0].ip.as_deref(), Some("192.0.2.1"));
+        assert_eq!(sessions[0].user_agent.as_deref(), Some("Meowzilla/5.0"));
+
+        assert!(matches!(
+            delete_user_session(nyan.id, first, &db).await,
+            Err(crate::Error::SessionNotFound)
+        ));
+
+        delete_user_session(meow.id, first, &db).await.unwrap();
+        assert!(load_session(&db, &key).await.unwrap().is_none());
+        assert_eq!(get_user_sessions(meow.id, &db).await.unwrap().len(), 1);
+    }
+
+    #[test(tokio::test)]
+    async fn end_sessions_of_other_user() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let meow = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            save_session(
+                &db,
+                login_state(meow.id, Uuid::new_v4()),
+                &Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(
+            end_user_sessions(meow.id, &Actor::cli(), &db)
+                .await
+                .unwrap(),
+            2
+        );
+        assert!(get_user_sessions(meow.id, &db).await.unwrap().is_empty());
+        assert!(matches!(
+            end_user_sessions(Uuid::new_v4(), &Actor::cli(), &db).await,
+            Err(crate::Error::UserNotFound)
+        ));
+
+        let entries = get_audit_entries(
+            &AuditQuery {
+                action: Some(AuditAction::UserSessionsEnded),
+                limit: 10,
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].target_id, meow.id.to_string());
+        assert_eq!(entries[0].after.as_ref().unwrap()["ended_sessions"], 2);
+    }
+
+    #[test(tokio::test)]
+    async fn prune_in_batches() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let meow = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        for _ in 0..5 {
+            save_session(&db, HashMap::new(), &Duration::seconds(-1))
+                .await
+                .unwrap();
+        }
+        save_session(&db, HashMap::new(), &Duration::hours(1))
+            .await
+            .unwrap();
+        save_session(
+            &db,
+            login_state(meow.id, Uuid::new_v4()),
+            &Duration::hours(1),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(get_live_session_count(&db).await.unwrap(), (2, 1));
+        assert_eq!(prune_expired_sessions(2, &db).await.unwrap(), 5);
+        assert_eq!(prune_expired_sessions(2, &db).await.unwrap(), 0);
+        assert_eq!(Session::find().count(&db).await.unwrap(), 2);
+    }
+}
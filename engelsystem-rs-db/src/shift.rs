@@ -1,11 +1,352 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{SecondsFormat, TimeDelta, Utc};
 use entity::intern::*;
-use sea_orm::{prelude::*, JoinType, QueryOrder, QuerySelect};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::sea_query::{Func, LikeExpr, SimpleExpr};
+use sea_orm::{
+    Condition, IntoActiveModel, JoinType, QueryOrder, QuerySelect, Select, TransactionTrait,
+    prelude::*,
+};
+
+use crate::AuditAction;
+use crate::Error;
+use crate::angel_type::is_confirmed_for_angel_type;
+use crate::audit::{Actor, record_audit, snapshot};
+use crate::location::check_location_exists;
+use crate::notification::emit_notification;
+
+/// Position in the shift list, ordered by start time and id. Serialized as `<starts_at>_<id>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShiftCursor {
+    pub starts_at: DateTimeUtc,
+    pub id: Uuid,
+}
+
+impl ShiftCursor {
+    fn of(shift: &shift::View) -> Self {
+        ShiftCursor {
+            starts_at: shift.starts_at,
+            id: shift.id,
+        }
+    }
+}
 
-pub async fn add_shift(
+impl fmt::Display for ShiftCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}_{}",
+            self.starts_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            self.id
+        )
+    }
+}
+
+impl FromStr for ShiftCursor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (starts_at, id) = s.split_once('_').ok_or(Error::InvalidCursor)?;
+
+        Ok(ShiftCursor {
+            starts_at: starts_at.parse().map_err(|_| Error::InvalidCursor)?,
+            id: id.parse().map_err(|_| Error::InvalidCursor)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShiftQuery {
+    /// Only shifts that end after this point in time
+    pub from: Option<DateTimeUtc>,
+    /// Only shifts that start before this point in time
+    pub until: Option<DateTimeUtc>,
+    pub angel_type_id: Option<u32>,
+    pub location_id: Option<u32>,
+    pub free_only: bool,
+    /// Case insensitive search in the name and description
+    pub search: Option<String>,
+    pub after: Option<ShiftCursor>,
+    pub limit: u64,
+}
+
+#[derive(Debug)]
+pub struct ShiftPage {
+    pub shifts: Vec<shift::View>,
+    pub next: Option<ShiftCursor>,
+}
+
+pub async fn add_shift<C: ConnectionTrait + TransactionTrait>(
     shift: shift::ActiveModel,
-    db: &DatabaseConnection,
+    actor: &Actor,
+    db: &C,
 ) -> crate::Result<shift::Model> {
-    Ok(shift.insert(db).await?)
+    if let (Some(starts_at), Some(ends_at)) =
+        (shift.starts_at.try_as_ref(), shift.ends_at.try_as_ref())
+        && ends_at <= starts_at
+    {
+        return Err(Error::InvalidShiftTime);
+    }
+
+    if let Some(location_id) = shift.location_id.try_as_ref() {
+        check_location_exists(*location_id, db).await?;
+    }
+
+    let txn = db.begin().await?;
+    let shift = shift.insert(&txn).await?;
+    record_audit(
+        actor,
+        AuditAction::ShiftCreated,
+        shift.id,
+        None,
+        snapshot(&shift),
+        &txn,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(shift)
+}
+
+/// Changes to a shift. Fields that are `None` stay as they are.
+#[derive(Debug, Clone, Default)]
+pub struct ShiftChanges {
+    pub starts_at: Option<DateTimeUtc>,
+    pub ends_at: Option<DateTimeUtc>,
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub angels_needed: Option<u32>,
+    pub angel_type_id: Option<Option<u32>>,
+    pub location_id: Option<Option<u32>>,
+    pub managed_by: Option<Option<Uuid>>,
+}
+
+fn format_shift_time(time: DateTimeUtc) -> String {
+    time.format("%d.%m.%Y %H:%M UTC").to_string()
+}
+
+async fn get_signed_up_user_ids<C: ConnectionTrait>(
+    shift_id: Uuid,
+    db: &C,
+) -> crate::Result<Vec<Uuid>> {
+    Ok(UserShift::find()
+        .select_only()
+        .column(user_shift::Column::UserId)
+        .filter(user_shift::Column::ShiftId.eq(shift_id))
+        .into_tuple()
+        .all(db)
+        .await?)
+}
+
+/// Edits a shift and notifies every signed up angel about what changed.
+///
+/// Sign-ups are kept as they are, even if the shift now needs fewer angels than are signed up or
+/// the new time overlaps with other shifts of an angel. Such shifts show up as overbooked in the
+/// returned view and the angels are told so in the notification.
+pub async fn update_shift(
+    shift_id: Uuid,
+    changes: ShiftChanges,
+    actor: &Actor,
+    db: &DatabaseConnection,
+) -> crate::Result<shift::View> {
+    let txn = db.begin().await?;
+
+    let shift = Shift::find_by_id(shift_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(Error::ShiftNotFound)?;
+
+    let starts_at = changes.starts_at.unwrap_or(shift.starts_at);
+    let ends_at = changes.ends_at.unwrap_or(shift.ends_at);
+    if ends_at <= starts_at {
+        return Err(Error::InvalidShiftTime);
+    }
+
+    let signed_up = get_signed_up_user_ids(shift_id, &txn).await?;
+    let mut described = Vec::new();
+    let mut active = shift.clone().into_active_model();
+
+    if starts_at != shift.starts_at || ends_at != shift.ends_at {
+        described.push(format!(
+            "neue Zeit {} bis {}",
+            format_shift_time(starts_at),
+            format_shift_time(ends_at)
+        ));
+        active.starts_at = Set(starts_at);
+        active.ends_at = Set(ends_at);
+    }
+
+    if starts_at != shift.starts_at {
+        // Reminders that went out for the old time don't count for the new one
+        UserShift::update_many()
+            .col_expr(
+                user_shift::Column::RemindedAt,
+                Expr::value(None::<DateTimeUtc>),
+            )
+            .filter(user_shift::Column::ShiftId.eq(shift_id))
+            .exec(&txn)
+            .await?;
+    }
+
+    if let Some(name) = changes.name.filter(|name| *name != shift.name) {
+        described.push(format!("neuer Name „{name}“"));
+        active.name = Set(name);
+    }
+
+    if let Some(description) = changes
+        .description
+        .filter(|description| *description != shift.description)
+    {
+        described.push("neue Beschreibung".to_string());
+        active.description = Set(description);
+    }
+
+    if let Some(angels_needed) = changes
+        .angels_needed
+        .filter(|angels_needed| *angels_needed != shift.angels_needed)
+    {
+        described.push(format!("jetzt {angels_needed} benötigte Engel"));
+        active.angels_needed = Set(angels_needed);
+    }
+
+    if let Some(angel_type_id) = changes
+        .angel_type_id
+        .filter(|angel_type_id| *angel_type_id != shift.angel_type_id)
+    {
+        if let Some(id) = angel_type_id
+            && AngelType::find_by_id(id).one(&txn).await?.is_none()
+        {
+            return Err(Error::AngelTypeNotFound);
+        }
+
+        described.push("anderer Engeltyp".to_string());
+        active.angel_type_id = Set(angel_type_id);
+    }
+
+    if let Some(location_id) = changes
+        .location_id
+        .filter(|location_id| *location_id != shift.location_id)
+    {
+        check_location_exists(location_id, &txn).await?;
+
+        described.push("anderer Ort".to_string());
+        active.location_id = Set(location_id);
+    }
+
+    if let Some(managed_by) = changes
+        .managed_by
+        .filter(|managed_by| *managed_by != shift.managed_by)
+    {
+        described.push("andere Schichtleitung".to_string());
+        active.managed_by = Set(managed_by);
+    }
+
+    if !described.is_empty() {
+        let updated = active.update(&txn).await?;
+        record_audit(
+            actor,
+            AuditAction::ShiftUpdated,
+            shift_id,
+            snapshot(&shift),
+            snapshot(&updated),
+            &txn,
+        )
+        .await?;
+
+        let mut message = format!(
+            "Die Schicht „{}“ wurde geändert: {}.",
+            shift.name,
+            described.join(", ")
+        );
+        if signed_up.len() > updated.angels_needed as usize {
+            message.push_str(" Die Schicht ist jetzt überbelegt, du bleibst aber eingetragen.");
+        }
+
+        emit_notification(
+            &signed_up,
+            notification::NotificationKind::ShiftUpdated,
+            Some(shift_id),
+            &message,
+            &txn,
+        )
+        .await?;
+    }
+
+    txn.commit().await?;
+
+    get_shift_view_by_id(shift_id, db)
+        .await?
+        .ok_or(Error::ShiftNotFound)
+}
+
+/// Deletes a shift together with its sign-ups and notifies the signed up angels. Shifts that
+/// already started or were credited to angels are refused, so the angels keep their completion
+/// records, shift time and points.
+pub async fn delete_shift(
+    shift_id: Uuid,
+    actor: &Actor,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    let txn = db.begin().await?;
+
+    let shift = Shift::find_by_id(shift_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(Error::ShiftNotFound)?;
+
+    let completed = ShiftCompletion::find()
+        .filter(shift_completion::Column::ShiftId.eq(shift_id))
+        .count(&txn)
+        .await?
+        > 0;
+    if shift.starts_at <= Utc::now() || completed {
+        return Err(Error::ShiftAlreadyStarted);
+    }
+
+    let signed_up = get_signed_up_user_ids(shift_id, &txn).await?;
+
+    emit_notification(
+        &signed_up,
+        notification::NotificationKind::ShiftCancelled,
+        Some(shift_id),
+        &format!(
+            "Die Schicht „{}“ am {} wurde abgesagt.",
+            shift.name,
+            format_shift_time(shift.starts_at)
+        ),
+        &txn,
+    )
+    .await?;
+
+    UserShift::delete_many()
+        .filter(user_shift::Column::ShiftId.eq(shift_id))
+        .exec(&txn)
+        .await?;
+    record_audit(
+        actor,
+        AuditAction::ShiftDeleted,
+        shift_id,
+        snapshot(&shift),
+        None,
+        &txn,
+    )
+    .await?;
+    shift.delete(&txn).await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_shift_by_id(
+    shift_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<shift::Model>> {
+    Ok(Shift::find_by_id(shift_id).one(db).await?)
 }
 
 pub async fn get_shifts_by_user(
@@ -34,3 +375,828 @@ pub async fn get_shifts_by_user(
 
     Ok(select.all(db).await?)
 }
+
+fn signed_up_expr() -> SimpleExpr {
+    Expr::col((UserShift, user_shift::Column::UserId)).count()
+}
+
+/// Selects shifts together with their amount of signed up angels and free slots
+fn select_shift_views() -> Select<Shift> {
+    let angels_needed = Expr::col((Shift, shift::Column::AngelsNeeded));
+    let free_slots: SimpleExpr = Expr::case(
+        angels_needed.clone().gt(signed_up_expr()),
+        angels_needed.sub(signed_up_expr()),
+    )
+    .finally(0)
+    .into();
+
+    Shift::find()
+        .join_rev(JoinType::LeftJoin, user_shift::Relation::Shift.def())
+        .column_as(signed_up_expr(), "signed_up")
+        .column_as(free_slots, "free_slots")
+        .group_by(shift::Column::Id)
+}
+
+pub async fn get_shift_view_by_id(
+    shift_id: Uuid,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<shift::View>> {
+    Ok(select_shift_views()
+        .filter(shift::Column::Id.eq(shift_id))
+        .into_model::<shift::View>()
+        .one(db)
+        .await?)
+}
+
+pub async fn get_shift_views(
+    query: ShiftQuery,
+    db: &DatabaseConnection,
+) -> crate::Result<ShiftPage> {
+    let mut select = select_shift_views()
+        .order_by_asc(shift::Column::StartsAt)
+        .order_by_asc(shift::Column::Id)
+        .limit(query.limit + 1);
+
+    if let Some(from) = query.from {
+        select = select.filter(shift::Column::EndsAt.gt(from));
+    }
+
+    if let Some(until) = query.until {
+        select = select.filter(shift::Column::StartsAt.lt(until));
+    }
+
+    if let Some(angel_type_id) = query.angel_type_id {
+        select = select.filter(shift::Column::AngelTypeId.eq(angel_type_id));
+    }
+
+    if let Some(location_id) = query.location_id {
+        select = select.filter(shift::Column::LocationId.eq(location_id));
+    }
+
+    if query.free_only {
+        select = select.having(
+            Expr::expr(signed_up_expr()).lt(Expr::col((Shift, shift::Column::AngelsNeeded))),
+        );
+    }
+
+    if let Some(search) = query.search {
+        // The search term is matched literally, so `%` and `_` in it aren't wildcards
+        let escaped = search
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let pattern = LikeExpr::new(format!("%{escaped}%")).escape('\\');
+        select = select.filter(
+            Condition::any()
+                .add(
+                    Expr::expr(Func::lower(Expr::col((Shift, shift::Column::Name))))
+                        .like(pattern.clone()),
+                )
+                .add(
+                    Expr::expr(Func::lower(Expr::col((Shift, shift::Column::Description))))
+                        .like(pattern),
+                ),
+        );
+    }
+
+    if let Some(after) = query.after {
+        select = select.filter(
+            Condition::any()
+                .add(shift::Column::StartsAt.gt(after.starts_at))
+                .add(
+                    Condition::all()
+                        .add(shift::Column::StartsAt.eq(after.starts_at))
+                        .add(shift::Column::Id.gt(after.id)),
+                ),
+        );
+    }
+
+    let mut shifts = select.into_model::<shift::View>().all(db).await?;
+
+    let next = if shifts.len() as u64 > query.limit {
+        shifts.truncate(query.limit as usize);
+        shifts.last().map(ShiftCursor::of)
+    } else {
+        None
+    };
+
+    Ok(ShiftPage { shifts, next })
+}
+
+pub(crate) async fn count_signed_up<C: ConnectionTrait>(
+    shift_id: Uuid,
+    db: &C,
+) -> crate::Result<u64> {
+    Ok(UserShift::find()
+        .filter(user_shift::Column::ShiftId.eq(shift_id))
+        .count(db)
+        .await?)
+}
+
+/// Returns a shift the user is signed up for that overlaps with `shift`, or is less than `buffer`
+/// apart from it.
+pub async fn find_conflicting_shift<C: ConnectionTrait>(
+    user_id: Uuid,
+    shift: &shift::Model,
+    buffer: TimeDelta,
+    db: &C,
+) -> crate::Result<Option<shift::Model>> {
+    Ok(Shift::find()
+        .join_rev(JoinType::InnerJoin, user_shift::Relation::Shift.def())
+        .filter(user_shift::Column::UserId.eq(user_id))
+        .filter(shift::Column::Id.ne(shift.id))
+        .filter(shift::Column::StartsAt.lt(shift.ends_at + buffer))
+        .filter(shift::Column::EndsAt.gt(shift.starts_at - buffer))
+        .order_by_asc(shift::Column::StartsAt)
+        .one(db)
+        .await?)
+}
+
+/// How [`sign_up_for_shift`] treats shifts that overlap with another shift of the user
+#[derive(Debug, Clone, Copy)]
+pub enum OverlapPolicy {
+    /// Refuses shifts that overlap with another shift of the user, or are less than `buffer` apart
+    /// from it
+    Refuse { buffer: TimeDelta },
+    /// Doesn't check for overlaps, for exceptional cases
+    Allow,
+}
+
+/// Signs a user up for a shift, as long as it still has free slots. Shifts that need a specific
+/// angel type only accept confirmed members of that type.
+///
+/// Every way of adding a user to a shift goes through here, so overlaps are checked according to
+/// `overlap` no matter who adds them.
+///
+/// The capacity check and the insert run in the same transaction, with the shift row locked on
+/// backends that support it, so two angels can't both take the last slot.
+pub async fn sign_up_for_shift(
+    shift_id: Uuid,
+    user_id: Uuid,
+    overlap: OverlapPolicy,
+    db: &DatabaseConnection,
+) -> crate::Result<user_shift::Model> {
+    let txn = db.begin().await?;
+
+    let shift = Shift::find_by_id(shift_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(Error::ShiftNotFound)?;
+
+    if UserShift::find_by_id((user_id, shift_id))
+        .one(&txn)
+        .await?
+        .is_some()
+    {
+        return Err(Error::AlreadySignedUp);
+    }
+
+    if let Some(angel_type_id) = shift.angel_type_id
+        && !is_confirmed_for_angel_type(user_id, angel_type_id, &txn).await?
+    {
+        return Err(Error::NotQualified);
+    }
+
+    if let OverlapPolicy::Refuse { buffer } = overlap
+        && let Some(conflict) = find_conflicting_shift(user_id, &shift, buffer, &txn).await?
+    {
+        return Err(Error::ShiftConflict {
+            shift_id: conflict.id,
+            name: conflict.name,
+        });
+    }
+
+    if count_signed_up(shift_id, &txn).await? >= shift.angels_needed as u64 {
+        return Err(Error::ShiftFull);
+    }
+
+    let entry = user_shift::ActiveModel {
+        user_id: Set(user_id),
+        shift_id: Set(shift_id),
+        created_at: NotSet,
+        reminded_at: Set(None),
+    }
+    .insert(&txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(entry)
+}
+
+/// Removes a user from a shift. Signing off is refused once the shift starts in less than `cutoff`.
+pub async fn sign_off_from_shift(
+    shift_id: Uuid,
+    user_id: Uuid,
+    cutoff: TimeDelta,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    let shift = get_shift_by_id(shift_id, db)
+        .await?
+        .ok_or(Error::ShiftNotFound)?;
+
+    let Some(entry) = UserShift::find_by_id((user_id, shift_id)).one(db).await? else {
+        return Err(Error::NotSignedUp);
+    };
+
+    if Utc::now() + cutoff > shift.starts_at {
+        return Err(Error::SignOffCutoffPassed);
+    }
+
+    entry.delete(db).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::CompletionStatus;
+    use crate::completion::{CompletionEntry, CreditRules, complete_shift};
+    use crate::notification::get_notifications_by_user;
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::{add_user, get_user_by_id};
+    use test_log::test;
+
+    pub(crate) async fn add_dummy_shift(
+        created_by: Uuid,
+        starts_in: TimeDelta,
+        angels_needed: u32,
+        db: &DatabaseConnection,
+    ) -> shift::Model {
+        let starts_at = Utc::now() + starts_in;
+
+        add_shift(
+            shift::ActiveModel {
+                created_by: Set(created_by),
+                managed_by: Set(None),
+                starts_at: Set(starts_at),
+                ends_at: Set(starts_at + TimeDelta::hours(2)),
+                name: Set("Bar".to_string()),
+                description: Set(None),
+                angels_needed: Set(angels_needed),
+                angel_type_id: Set(None),
+                ..Default::default()
+            },
+            &Actor::cli(),
+            db,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[test(tokio::test)]
+    async fn sign_up_until_full() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let first = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let second = add_user("Meow2", "meow2@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let shift = add_dummy_shift(first.id, TimeDelta::days(1), 1, &db).await;
+
+        sign_up_for_shift(
+            shift.id,
+            first.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            sign_up_for_shift(
+                shift.id,
+                first.id,
+                OverlapPolicy::Refuse {
+                    buffer: TimeDelta::zero()
+                },
+                &db
+            )
+            .await,
+            Err(Error::AlreadySignedUp)
+        ));
+        assert!(matches!(
+            sign_up_for_shift(
+                shift.id,
+                second.id,
+                OverlapPolicy::Refuse {
+                    buffer: TimeDelta::zero()
+                },
+                &db
+            )
+            .await,
+            Err(Error::ShiftFull)
+        ));
+
+        let shifts = get_shifts_by_user(first.id, None, true, true, &db)
+            .await
+            .unwrap();
+        assert_eq!(shifts.len(), 1);
+        assert_eq!(shifts[0].id, shift.id);
+    }
+
+    fn query(limit: u64) -> ShiftQuery {
+        ShiftQuery {
+            from: None,
+            until: None,
+            angel_type_id: None,
+            location_id: None,
+            free_only: false,
+            search: None,
+            after: None,
+            limit,
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn shift_views_paginate() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        for hours in 1..=5 {
+            add_dummy_shift(user.id, TimeDelta::hours(hours), 1, &db).await;
+        }
+
+        let first = get_shift_views(query(2), &db).await.unwrap();
+        assert_eq!(first.shifts.len(), 2);
+        let cursor = first.next.unwrap();
+        assert_eq!(cursor.to_string().parse::<ShiftCursor>().unwrap(), cursor);
+
+        let second = get_shift_views(
+            ShiftQuery {
+                after: Some(cursor),
+                ..query(2)
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(second.shifts.len(), 2);
+        assert!(second.shifts[0].starts_at > first.shifts[1].starts_at);
+
+        let last = get_shift_views(
+            ShiftQuery {
+                after: second.next,
+                ..query(2)
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(last.shifts.len(), 1);
+        assert_eq!(last.next, None);
+    }
+
+    #[test(tokio::test)]
+    async fn shift_views_count_slots() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let full = add_dummy_shift(user.id, TimeDelta::hours(1), 1, &db).await;
+        let open = add_dummy_shift(user.id, TimeDelta::hours(3), 3, &db).await;
+
+        sign_up_for_shift(
+            full.id,
+            user.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        sign_up_for_shift(
+            open.id,
+            user.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+
+        let view = get_shift_view_by_id(open.id, &db).await.unwrap().unwrap();
+        assert_eq!(view.signed_up, 1);
+        assert_eq!(view.free_slots, 2);
+
+        let free = get_shift_views(
+            ShiftQuery {
+                free_only: true,
+                search: Some("BAR".to_string()),
+                ..query(10)
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(free.shifts.len(), 1);
+        assert_eq!(free.shifts[0].id, open.id);
+
+        let none = get_shift_views(
+            ShiftQuery {
+                search: Some("kitchen".to_string()),
+                ..query(10)
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(none.shifts.is_empty());
+
+        // Wildcards in the search term are matched literally
+        for search in ["%", "_", "\\"] {
+            let none = get_shift_views(
+                ShiftQuery {
+                    search: Some(search.to_string()),
+                    ..query(10)
+                },
+                &db,
+            )
+            .await
+            .unwrap();
+            assert!(none.shifts.is_empty(), "{search:?} matched a shift");
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn sign_up_requires_confirmed_angel_type() {
+        use crate::angel_type::{
+            add_angel_type, confirm_angel_type_membership, request_angel_type_membership,
+        };
+        use sea_orm::IntoActiveModel;
+
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let tech = add_angel_type("Tech", true, &db).await.unwrap();
+
+        let mut shift = add_dummy_shift(user.id, TimeDelta::days(1), 2, &db)
+            .await
+            .into_active_model();
+        shift.angel_type_id = Set(Some(tech.id));
+        let shift = shift.update(&db).await.unwrap();
+
+        assert!(matches!(
+            sign_up_for_shift(
+                shift.id,
+                user.id,
+                OverlapPolicy::Refuse {
+                    buffer: TimeDelta::zero()
+                },
+                &db
+            )
+            .await,
+            Err(Error::NotQualified)
+        ));
+
+        request_angel_type_membership(user.id, tech.id, &db)
+            .await
+            .unwrap();
+        assert!(matches!(
+            sign_up_for_shift(
+                shift.id,
+                user.id,
+                OverlapPolicy::Refuse {
+                    buffer: TimeDelta::zero()
+                },
+                &db
+            )
+            .await,
+            Err(Error::NotQualified)
+        ));
+
+        confirm_angel_type_membership(user.id, tech.id, user.id, &db)
+            .await
+            .unwrap();
+        sign_up_for_shift(
+            shift.id,
+            user.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test(tokio::test)]
+    async fn sign_off_respects_cutoff() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let soon = add_dummy_shift(user.id, TimeDelta::hours(1), 2, &db).await;
+        let later = add_dummy_shift(user.id, TimeDelta::days(2), 2, &db).await;
+
+        sign_up_for_shift(
+            soon.id,
+            user.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+        sign_up_for_shift(
+            later.id,
+            user.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            sign_off_from_shift(soon.id, user.id, TimeDelta::hours(3), &db).await,
+            Err(Error::SignOffCutoffPassed)
+        ));
+        sign_off_from_shift(later.id, user.id, TimeDelta::hours(3), &db)
+            .await
+            .unwrap();
+        assert!(matches!(
+            sign_off_from_shift(later.id, user.id, TimeDelta::hours(3), &db).await,
+            Err(Error::NotSignedUp)
+        ));
+    }
+
+    #[test(tokio::test)]
+    async fn sign_up_detects_overlaps() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let first = add_dummy_shift(user.id, TimeDelta::hours(4), 2, &db).await;
+        let overlapping = add_dummy_shift(user.id, TimeDelta::hours(5), 2, &db).await;
+        let adjacent = add_dummy_shift(user.id, TimeDelta::hours(6), 2, &db).await;
+
+        sign_up_for_shift(
+            first.id,
+            user.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+
+        match sign_up_for_shift(
+            overlapping.id,
+            user.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        {
+            Err(Error::ShiftConflict { shift_id, .. }) => assert_eq!(shift_id, first.id),
+            other => panic!("Expected a shift conflict, got {other:?}"),
+        }
+
+        assert!(matches!(
+            sign_up_for_shift(
+                adjacent.id,
+                user.id,
+                OverlapPolicy::Refuse {
+                    buffer: TimeDelta::minutes(30)
+                },
+                &db
+            )
+            .await,
+            Err(Error::ShiftConflict { .. })
+        ));
+        sign_up_for_shift(
+            adjacent.id,
+            user.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+
+        // Without a buffer the check is skipped, e.g. for admins overriding it
+        sign_up_for_shift(overlapping.id, user.id, OverlapPolicy::Allow, &db)
+            .await
+            .unwrap();
+    }
+
+    #[test(tokio::test)]
+    async fn update_keeps_sign_ups_and_notifies() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let first = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let second = add_user("Meow2", "meow2@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let shift = add_dummy_shift(first.id, TimeDelta::days(1), 2, &db).await;
+
+        for user in [&first, &second] {
+            sign_up_for_shift(
+                shift.id,
+                user.id,
+                OverlapPolicy::Refuse {
+                    buffer: TimeDelta::zero(),
+                },
+                &db,
+            )
+            .await
+            .unwrap();
+        }
+
+        assert!(matches!(
+            update_shift(
+                shift.id,
+                ShiftChanges {
+                    ends_at: Some(shift.starts_at),
+                    ..Default::default()
+                },
+                &Actor::cli(),
+                &db
+            )
+            .await,
+            Err(Error::InvalidShiftTime)
+        ));
+
+        let view = update_shift(
+            shift.id,
+            ShiftChanges {
+                starts_at: Some(shift.starts_at + TimeDelta::hours(1)),
+                ends_at: Some(shift.ends_at + TimeDelta::hours(1)),
+                angels_needed: Some(1),
+                ..Default::default()
+            },
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(view.signed_up, 2);
+        assert_eq!(view.free_slots, 0);
+
+        let updated = get_shift_by_id(shift.id, &db).await.unwrap().unwrap();
+        assert_eq!(updated.sequence, shift.sequence + 1);
+        assert!(updated.updated_at.is_some());
+
+        for user in [&first, &second] {
+            let notifications = get_notifications_by_user(user.id, false, &db)
+                .await
+                .unwrap();
+            assert_eq!(notifications.len(), 1);
+            assert_eq!(
+                notifications[0].kind,
+                notification::NotificationKind::ShiftUpdated
+            );
+            assert_eq!(notifications[0].shift_id, Some(shift.id));
+            assert!(notifications[0].message.contains("überbelegt"));
+        }
+
+        // Nothing changed, so nobody is notified
+        update_shift(
+            shift.id,
+            ShiftChanges {
+                angels_needed: Some(1),
+                ..Default::default()
+            },
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            get_notifications_by_user(first.id, false, &db)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test(tokio::test)]
+    async fn delete_notifies_signed_up_angels() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let shift = add_dummy_shift(user.id, TimeDelta::days(1), 2, &db).await;
+        sign_up_for_shift(
+            shift.id,
+            user.id,
+            OverlapPolicy::Refuse {
+                buffer: TimeDelta::zero(),
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+
+        delete_shift(shift.id, &Actor::cli(), &db).await.unwrap();
+
+        assert!(get_shift_by_id(shift.id, &db).await.unwrap().is_none());
+        assert!(
+            get_shifts_by_user(user.id, None, true, true, &db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let notifications = get_notifications_by_user(user.id, false, &db)
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(
+            notifications[0].kind,
+            notification::NotificationKind::ShiftCancelled
+        );
+        assert_eq!(notifications[0].shift_id, Some(shift.id));
+
+        assert!(matches!(
+            delete_shift(shift.id, &Actor::cli(), &db).await,
+            Err(Error::ShiftNotFound)
+        ));
+    }
+
+    #[test(tokio::test)]
+    async fn delete_keeps_worked_shifts() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let shift = add_dummy_shift(user.id, TimeDelta::hours(-1), 2, &db).await;
+        UserShift::insert(user_shift::ActiveModel {
+            user_id: Set(user.id),
+            shift_id: Set(shift.id),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            delete_shift(shift.id, &Actor::cli(), &db).await,
+            Err(Error::ShiftAlreadyStarted)
+        ));
+
+        complete_shift(
+            shift.id,
+            vec![CompletionEntry {
+                user_id: user.id,
+                status: CompletionStatus::Present,
+                worked_hours: None,
+            }],
+            &Actor::cli(),
+            &CreditRules::default(),
+            &db,
+        )
+        .await
+        .unwrap();
+
+        // Moving the shift into the future doesn't make the credit go away either
+        update_shift(
+            shift.id,
+            ShiftChanges {
+                starts_at: Some(Utc::now() + TimeDelta::days(1)),
+                ends_at: Some(Utc::now() + TimeDelta::days(1) + TimeDelta::hours(2)),
+                ..Default::default()
+            },
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            delete_shift(shift.id, &Actor::cli(), &db).await,
+            Err(Error::ShiftAlreadyStarted)
+        ));
+
+        let user = get_user_by_id(user.id, &db).await.unwrap().unwrap();
+        assert_eq!(user.shift_time, 2 * 3_600);
+        assert!(get_shift_by_id(shift.id, &db).await.unwrap().is_some());
+    }
+}
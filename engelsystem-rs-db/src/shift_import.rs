@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use entity::intern::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{TransactionTrait, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+use crate::audit::Actor;
+use crate::location::get_location_id_by_name;
+use crate::shift::add_shift;
+use crate::user::{get_angel_type_id_by_name, get_user_id_by_name};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ImportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ImportFormat::Csv),
+            "json" => Ok(ImportFormat::Json),
+            _ => Err(Error::UnknownImportFormat {
+                format: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportFormat::Csv => write!(f, "csv"),
+            ImportFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// One shift of an import, in the same shape as a shift added through the API. The manager, angel
+/// type and location are given by name.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShiftImportRow {
+    pub managed_by: Option<String>,
+    pub starts_at: DateTimeUtc,
+    pub ends_at: DateTimeUtc,
+    pub name: String,
+    pub description: Option<String>,
+    pub angels_needed: u32,
+    pub angel_type: Option<String>,
+    pub location: Option<String>,
+}
+
+/// A problem with one row. For CSV files the row is the line in the file, so the header is row 1.
+/// For JSON files it is the position in the array, starting at 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportRowError {
+    pub row: u64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    /// The amount of rows in the file
+    pub rows: u64,
+    /// The amount of shifts that were added. Nothing is added in a dry run or if any row has
+    /// errors.
+    pub imported: u64,
+    pub errors: Vec<ImportRowError>,
+}
+
+fn parse_csv(data: &[u8]) -> crate::Result<Vec<(u64, Result<ShiftImportRow, String>)>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|e| Error::InvalidImportFile {
+            reason: e.to_string(),
+        })?
+        .clone();
+
+    Ok(reader
+        .records()
+        .enumerate()
+        .map(|(index, record)| match record {
+            Ok(record) => {
+                let row = record.position().map_or(index as u64 + 2, |pos| pos.line());
+                let parsed = record
+                    .deserialize::<ShiftImportRow>(Some(&headers))
+                    .map_err(|e| e.to_string());
+                (row, parsed)
+            }
+            Err(e) => {
+                let row = e.position().map_or(index as u64 + 2, |pos| pos.line());
+                (row, Err(e.to_string()))
+            }
+        })
+        .collect())
+}
+
+fn parse_json(data: &[u8]) -> crate::Result<Vec<(u64, Result<ShiftImportRow, String>)>> {
+    let values: Vec<serde_json::Value> =
+        serde_json::from_slice(data).map_err(|e| Error::InvalidImportFile {
+            reason: e.to_string(),
+        })?;
+
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            (
+                index as u64 + 1,
+                serde_json::from_value(value).map_err(|e| e.to_string()),
+            )
+        })
+        .collect())
+}
+
+/// Imports many shifts at once. Every row is validated and angel types and managers are looked up
+/// by name. If any row has errors, or in a dry run, nothing is added and the report lists every
+/// error. Otherwise all shifts are added in one transaction.
+pub async fn import_shifts(
+    data: &[u8],
+    format: ImportFormat,
+    created_by: Uuid,
+    dry_run: bool,
+    actor: &Actor,
+    db: &DatabaseConnection,
+) -> crate::Result<ImportReport> {
+    let rows = match format {
+        ImportFormat::Csv => parse_csv(data)?,
+        ImportFormat::Json => parse_json(data)?,
+    };
+
+    let mut report = ImportReport {
+        rows: rows.len() as u64,
+        ..Default::default()
+    };
+    let mut shifts = Vec::with_capacity(rows.len());
+    let mut users: HashMap<String, Option<Uuid>> = HashMap::new();
+    let mut angel_types: HashMap<String, Option<u32>> = HashMap::new();
+    let mut locations: HashMap<String, Option<u32>> = HashMap::new();
+
+    for (row, parsed) in rows {
+        let mut error = |message: String| report.errors.push(ImportRowError { row, message });
+
+        let shift = match parsed {
+            Ok(shift) => shift,
+            Err(message) => {
+                error(message);
+                continue;
+            }
+        };
+
+        let mut valid = true;
+
+        if shift.name.trim().is_empty() {
+            error("The name is empty".to_string());
+            valid = false;
+        }
+
+        if shift.ends_at <= shift.starts_at {
+            error("The shift has to end after it starts".to_string());
+            valid = false;
+        }
+
+        let managed_by = match &shift.managed_by {
+            Some(name) => {
+                if !users.contains_key(name) {
+                    users.insert(name.clone(), get_user_id_by_name(name, db).await?);
+                }
+
+                let id = users[name];
+                if id.is_none() {
+                    error(format!("There's no user with the username {name:?}"));
+                    valid = false;
+                }
+                id
+            }
+            None => None,
+        };
+
+        let angel_type_id = match &shift.angel_type {
+            Some(name) => {
+                if !angel_types.contains_key(name) {
+                    angel_types.insert(name.clone(), get_angel_type_id_by_name(name, db).await?);
+                }
+
+                let id = angel_types[name];
+                if id.is_none() {
+                    error(format!("There's no angel type with the name {name:?}"));
+                    valid = false;
+                }
+                id
+            }
+            None => None,
+        };
+
+        let location_id = match &shift.location {
+            Some(name) => {
+                if !locations.contains_key(name) {
+                    locations.insert(name.clone(), get_location_id_by_name(name, db).await?);
+                }
+
+                let id = locations[name];
+                if id.is_none() {
+                    error(format!("There's no location with the name {name:?}"));
+                    valid = false;
+                }
+                id
+            }
+            None => None,
+        };
+
+        if valid {
+            shifts.push(shift::ActiveModel {
+                id: NotSet,
+                created_at: NotSet,
+                created_by: Set(created_by),
+                managed_by: Set(managed_by),
+                starts_at: Set(shift.starts_at),
+                ends_at: Set(shift.ends_at),
+                name: Set(shift.name),
+                description: Set(shift.description),
+                angels_needed: Set(shift.angels_needed),
+                angel_type_id: Set(angel_type_id),
+                location_id: Set(location_id),
+                sequence: NotSet,
+                updated_at: NotSet,
+            });
+        }
+    }
+
+    if dry_run || !report.errors.is_empty() {
+        return Ok(report);
+    }
+
+    let txn = db.begin().await?;
+    for shift in shifts {
+        add_shift(shift, actor, &txn).await?;
+        report.imported += 1;
+    }
+    txn.commit().await?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::angel_type::add_angel_type;
+    use crate::location::add_location;
+    use crate::shift::{ShiftQuery, get_shift_views};
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::add_user;
+    use test_log::test;
+
+    async fn count_shifts(db: &DatabaseConnection) -> usize {
+        get_shift_views(
+            ShiftQuery {
+                from: None,
+                until: None,
+                angel_type_id: None,
+                location_id: None,
+                free_only: false,
+                search: None,
+                after: None,
+                limit: 100,
+            },
+            db,
+        )
+        .await
+        .unwrap()
+        .shifts
+        .len()
+    }
+
+    #[test(tokio::test)]
+    async fn import_csv_reports_row_errors() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        add_angel_type("Bar".to_string(), false, &db).await.unwrap();
+        add_location(
+            location::ActiveModel {
+                name: Set("Stage".to_string()),
+                ..Default::default()
+            },
+            &db,
+        )
+        .await
+        .unwrap();
+
+        let csv = "\
+name,starts_at,ends_at,angels_needed,description,angel_type,managed_by,location
+Bar 1,2026-12-01T10:00:00Z,2026-12-01T12:00:00Z,2,,Bar,Meow,Stage
+Bar 2,2026-12-01T12:00:00Z,2026-12-01T11:00:00Z,2,,Bar,,
+Bar 3,2026-12-01T14:00:00Z,2026-12-01T16:00:00Z,2,,Kitchen,,
+Bar 4,yesterday,2026-12-01T18:00:00Z,2,,,,
+Bar 5,2026-12-01T18:00:00Z,2026-12-01T20:00:00Z,2,,,,Backstage
+";
+
+        let report = import_shifts(
+            csv.as_bytes(),
+            ImportFormat::Csv,
+            user.id,
+            true,
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.rows, 5);
+        assert_eq!(report.imported, 0);
+        assert_eq!(
+            report.errors.iter().map(|e| e.row).collect::<Vec<_>>(),
+            vec![3, 4, 5, 6]
+        );
+
+        // With errors, the real run doesn't add anything either
+        let report = import_shifts(
+            csv.as_bytes(),
+            ImportFormat::Csv,
+            user.id,
+            false,
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.imported, 0);
+        assert_eq!(count_shifts(&db).await, 0);
+    }
+
+    #[test(tokio::test)]
+    async fn import_json() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        let json = r#"[
+            {"name": "Bar 1", "starts_at": "2026-12-01T10:00:00Z", "ends_at": "2026-12-01T12:00:00Z", "angels_needed": 2},
+            {"name": "Bar 2", "starts_at": "2026-12-01T12:00:00Z", "ends_at": "2026-12-01T14:00:00Z", "angels_needed": 1, "managed_by": "Meow"}
+        ]"#;
+
+        let report = import_shifts(
+            json.as_bytes(),
+            ImportFormat::Json,
+            user.id,
+            true,
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(report.errors.is_empty());
+        assert_eq!(count_shifts(&db).await, 0);
+
+        let report = import_shifts(
+            json.as_bytes(),
+            ImportFormat::Json,
+            user.id,
+            false,
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.imported, 2);
+        assert_eq!(count_shifts(&db).await, 2);
+
+        assert!(matches!(
+            import_shifts(b"{}", ImportFormat::Json, user.id, true, &Actor::cli(), &db).await,
+            Err(Error::InvalidImportFile { .. })
+        ));
+    }
+}
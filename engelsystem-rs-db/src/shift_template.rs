@@ -0,0 +1,472 @@
+use std::collections::HashSet;
+
+use chrono::{FixedOffset, TimeDelta, TimeZone, Utc};
+use entity::intern::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{IntoActiveModel, QueryOrder, TransactionTrait, prelude::*};
+
+use crate::AuditAction;
+use crate::Error;
+use crate::audit::{Actor, record_audit, snapshot};
+use crate::location::check_location_exists;
+use crate::shift::{add_shift, count_signed_up};
+
+/// The longest date range a template can be expanded over at once
+pub const MAX_EXPANSION_DAYS: i64 = 366;
+
+/// Changes to a shift template. Fields that are `None` stay as they are.
+#[derive(Debug, Clone, Default)]
+pub struct ShiftTemplateChanges {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub angels_needed: Option<u32>,
+    pub angel_type_id: Option<Option<u32>>,
+    pub location_id: Option<Option<u32>>,
+    pub managed_by: Option<Option<Uuid>>,
+    pub starts_on: Option<ChronoDate>,
+    pub ends_on: Option<Option<ChronoDate>>,
+    pub interval_days: Option<u32>,
+    pub start_time: Option<ChronoTime>,
+    pub duration_minutes: Option<u32>,
+}
+
+/// How the generated shifts were changed after a template edit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TemplateSync {
+    /// Shifts that were changed to match the template
+    pub updated: u64,
+    /// Shifts that were deleted, because the template no longer occurs on their day
+    pub removed: u64,
+}
+
+/// Parses the UTC offset of the event from whole hours, e.g. `"2"` or `"-5"`. The start times of
+/// templates are in this offset.
+pub fn parse_utc_offset(hours: &str) -> Option<FixedOffset> {
+    let hours: i32 = hours.trim().parse().ok()?;
+    FixedOffset::east_opt(hours.checked_mul(3_600)?)
+}
+
+/// Checks the recurrence of a template before it is written. Templates without a recurrence are
+/// refused as well.
+fn validate(template: &shift_template::ActiveModel) -> crate::Result<()> {
+    let (Some(starts_on), Some(ends_on), Some(interval_days), Some(duration_minutes)) = (
+        template.starts_on.try_as_ref(),
+        template.ends_on.try_as_ref(),
+        template.interval_days.try_as_ref(),
+        template.duration_minutes.try_as_ref(),
+    ) else {
+        return Err(Error::InvalidRecurrence);
+    };
+
+    if *interval_days == 0
+        || *duration_minutes == 0
+        || ends_on.is_some_and(|ends_on| ends_on < *starts_on)
+    {
+        return Err(Error::InvalidRecurrence);
+    }
+
+    Ok(())
+}
+
+/// Start and end of the shift the template generates on the given day
+fn occurrence_times(
+    template: &shift_template::Model,
+    day: ChronoDate,
+    utc_offset: FixedOffset,
+) -> (DateTimeUtc, DateTimeUtc) {
+    let starts_at = utc_offset
+        .from_local_datetime(&day.and_time(template.start_time))
+        .single()
+        .expect("Times in a fixed offset are never ambiguous")
+        .with_timezone(&Utc);
+
+    (
+        starts_at,
+        starts_at + TimeDelta::minutes(template.duration_minutes as i64),
+    )
+}
+
+pub async fn get_all_shift_templates(
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<shift_template::Model>> {
+    Ok(ShiftTemplate::find()
+        .order_by_asc(shift_template::Column::Name)
+        .all(db)
+        .await?)
+}
+
+pub async fn get_shift_template_by_id(
+    template_id: u32,
+    db: &DatabaseConnection,
+) -> crate::Result<Option<shift_template::Model>> {
+    Ok(ShiftTemplate::find_by_id(template_id).one(db).await?)
+}
+
+pub async fn add_shift_template(
+    template: shift_template::ActiveModel,
+    db: &DatabaseConnection,
+) -> crate::Result<shift_template::Model> {
+    validate(&template)?;
+    if let Some(location_id) = template.location_id.try_as_ref() {
+        check_location_exists(*location_id, db).await?;
+    }
+
+    Ok(template.insert(db).await?)
+}
+
+/// Edits a template and pushes the changes to the shifts generated from it that haven't started
+/// yet and that nobody signed up for. Shifts with sign-ups are left alone.
+///
+/// If the template no longer occurs on the day of such a shift, the shift is deleted.
+pub async fn update_shift_template(
+    template_id: u32,
+    changes: ShiftTemplateChanges,
+    utc_offset: FixedOffset,
+    actor: &Actor,
+    db: &DatabaseConnection,
+) -> crate::Result<(shift_template::Model, TemplateSync)> {
+    let txn = db.begin().await?;
+
+    let template = ShiftTemplate::find_by_id(template_id)
+        .one(&txn)
+        .await?
+        .ok_or(Error::ShiftTemplateNotFound)?;
+
+    let mut active = template.clone().into_active_model();
+
+    if let Some(name) = changes.name {
+        active.name.set_if_not_equals(name);
+    }
+    if let Some(description) = changes.description {
+        active.description.set_if_not_equals(description);
+    }
+    if let Some(angels_needed) = changes.angels_needed {
+        active.angels_needed.set_if_not_equals(angels_needed);
+    }
+    if let Some(angel_type_id) = changes.angel_type_id {
+        if let Some(id) = angel_type_id
+            && AngelType::find_by_id(id).one(&txn).await?.is_none()
+        {
+            return Err(Error::AngelTypeNotFound);
+        }
+        active.angel_type_id.set_if_not_equals(angel_type_id);
+    }
+    if let Some(location_id) = changes.location_id {
+        check_location_exists(location_id, &txn).await?;
+        active.location_id.set_if_not_equals(location_id);
+    }
+    if let Some(managed_by) = changes.managed_by {
+        active.managed_by.set_if_not_equals(managed_by);
+    }
+    if let Some(starts_on) = changes.starts_on {
+        active.starts_on.set_if_not_equals(starts_on);
+    }
+    if let Some(ends_on) = changes.ends_on {
+        active.ends_on.set_if_not_equals(ends_on);
+    }
+    if let Some(interval_days) = changes.interval_days {
+        active.interval_days.set_if_not_equals(interval_days);
+    }
+    if let Some(start_time) = changes.start_time {
+        active.start_time.set_if_not_equals(start_time);
+    }
+    if let Some(duration_minutes) = changes.duration_minutes {
+        active.duration_minutes.set_if_not_equals(duration_minutes);
+    }
+
+    if !active.is_changed() {
+        return Ok((template, TemplateSync::default()));
+    }
+
+    validate(&active)?;
+    let template = active.update(&txn).await?;
+
+    let mut sync = TemplateSync::default();
+    let instances = ShiftTemplateInstance::find()
+        .filter(shift_template_instance::Column::TemplateId.eq(template_id))
+        .find_also_related(Shift)
+        .all(&txn)
+        .await?;
+
+    for (instance, shift) in instances {
+        let Some(shift) = shift else { continue };
+
+        if shift.starts_at <= Utc::now() || count_signed_up(shift.id, &txn).await? > 0 {
+            continue;
+        }
+
+        if !template.occurs_on(instance.occurs_on) {
+            record_audit(
+                actor,
+                AuditAction::ShiftDeleted,
+                shift.id,
+                snapshot(&shift),
+                None,
+                &txn,
+            )
+            .await?;
+            shift.delete(&txn).await?;
+            sync.removed += 1;
+            continue;
+        }
+
+        let (starts_at, ends_at) = occurrence_times(&template, instance.occurs_on, utc_offset);
+        let before = snapshot(&shift);
+        let mut shift = shift.into_active_model();
+        shift.starts_at.set_if_not_equals(starts_at);
+        shift.ends_at.set_if_not_equals(ends_at);
+        shift.name.set_if_not_equals(template.name.clone());
+        shift
+            .description
+            .set_if_not_equals(template.description.clone());
+        shift
+            .angels_needed
+            .set_if_not_equals(template.angels_needed);
+        shift
+            .angel_type_id
+            .set_if_not_equals(template.angel_type_id);
+        shift.location_id.set_if_not_equals(template.location_id);
+        shift.managed_by.set_if_not_equals(template.managed_by);
+
+        if shift.is_changed() {
+            let shift = shift.update(&txn).await?;
+            record_audit(
+                actor,
+                AuditAction::ShiftUpdated,
+                shift.id,
+                before,
+                snapshot(&shift),
+                &txn,
+            )
+            .await?;
+            sync.updated += 1;
+        }
+    }
+
+    txn.commit().await?;
+
+    Ok((template, sync))
+}
+
+/// Deletes a template. The shifts generated from it are kept.
+pub async fn delete_shift_template(template_id: u32, db: &DatabaseConnection) -> crate::Result<()> {
+    if ShiftTemplate::delete_by_id(template_id)
+        .exec(db)
+        .await?
+        .rows_affected
+        == 0
+    {
+        return Err(Error::ShiftTemplateNotFound);
+    }
+
+    Ok(())
+}
+
+/// Generates the shifts of a template for every day from `from` to `until`, both included. Days
+/// that already have a shift from this template are skipped, so overlapping ranges can be
+/// expanded again. Returns the new shifts.
+pub async fn expand_shift_template(
+    template_id: u32,
+    from: ChronoDate,
+    until: ChronoDate,
+    utc_offset: FixedOffset,
+    actor: &Actor,
+    db: &DatabaseConnection,
+) -> crate::Result<Vec<shift::Model>> {
+    let days = (until - from).num_days();
+    if !(0..MAX_EXPANSION_DAYS).contains(&days) {
+        return Err(Error::InvalidDateRange);
+    }
+
+    let txn = db.begin().await?;
+
+    let template = ShiftTemplate::find_by_id(template_id)
+        .one(&txn)
+        .await?
+        .ok_or(Error::ShiftTemplateNotFound)?;
+
+    let existing: HashSet<ChronoDate> = ShiftTemplateInstance::find()
+        .filter(shift_template_instance::Column::TemplateId.eq(template_id))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|instance| instance.occurs_on)
+        .collect();
+
+    let mut shifts = Vec::new();
+
+    for day in from.iter_days().take(days as usize + 1) {
+        if !template.occurs_on(day) || existing.contains(&day) {
+            continue;
+        }
+
+        let (starts_at, ends_at) = occurrence_times(&template, day, utc_offset);
+        let shift = add_shift(
+            shift::ActiveModel {
+                id: NotSet,
+                created_at: NotSet,
+                created_by: Set(template.created_by),
+                managed_by: Set(template.managed_by),
+                starts_at: Set(starts_at),
+                ends_at: Set(ends_at),
+                name: Set(template.name.clone()),
+                description: Set(template.description.clone()),
+                angels_needed: Set(template.angels_needed),
+                angel_type_id: Set(template.angel_type_id),
+                location_id: Set(template.location_id),
+                sequence: NotSet,
+                updated_at: NotSet,
+            },
+            actor,
+            &txn,
+        )
+        .await?;
+
+        shift_template_instance::ActiveModel {
+            shift_id: Set(shift.id),
+            template_id: Set(template_id),
+            occurs_on: Set(day),
+        }
+        .insert(&txn)
+        .await?;
+
+        shifts.push(shift);
+    }
+
+    txn.commit().await?;
+
+    Ok(shifts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shift::{OverlapPolicy, get_shift_by_id, sign_up_for_shift};
+    use crate::tests::connect_and_migrate_dummy;
+    use crate::user::add_user;
+    use chrono::{NaiveDate, NaiveTime};
+    use test_log::test;
+
+    fn day(day: u32) -> ChronoDate {
+        NaiveDate::from_ymd_opt(2030, 7, day).unwrap()
+    }
+
+    async fn add_dummy_template(
+        created_by: Uuid,
+        db: &DatabaseConnection,
+    ) -> shift_template::Model {
+        add_shift_template(
+            shift_template::ActiveModel {
+                created_by: Set(created_by),
+                managed_by: Set(None),
+                name: Set("Bar".to_string()),
+                description: Set(None),
+                angels_needed: Set(4),
+                angel_type_id: Set(None),
+                starts_on: Set(day(1)),
+                ends_on: Set(None),
+                interval_days: Set(1),
+                start_time: Set(NaiveTime::from_hms_opt(18, 0, 0).unwrap()),
+                duration_minutes: Set(240),
+                ..Default::default()
+            },
+            db,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[test(tokio::test)]
+    async fn expand_template() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let template = add_dummy_template(user.id, &db).await;
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+
+        let shifts = expand_shift_template(template.id, day(1), day(3), offset, &Actor::cli(), &db)
+            .await
+            .unwrap();
+        assert_eq!(shifts.len(), 3);
+        assert_eq!(
+            shifts[0].starts_at.to_rfc3339(),
+            "2030-07-01T16:00:00+00:00"
+        );
+        assert_eq!(shifts[0].ends_at.to_rfc3339(), "2030-07-01T20:00:00+00:00");
+
+        // Days that already have a shift are skipped
+        let shifts = expand_shift_template(template.id, day(3), day(4), offset, &Actor::cli(), &db)
+            .await
+            .unwrap();
+        assert_eq!(shifts.len(), 1);
+
+        assert!(matches!(
+            expand_shift_template(template.id, day(4), day(3), offset, &Actor::cli(), &db).await,
+            Err(Error::InvalidDateRange)
+        ));
+    }
+
+    #[test(tokio::test)]
+    async fn template_edits_skip_shifts_with_sign_ups() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let template = add_dummy_template(user.id, &db).await;
+        let offset = FixedOffset::east_opt(0).unwrap();
+
+        let shifts = expand_shift_template(template.id, day(1), day(4), offset, &Actor::cli(), &db)
+            .await
+            .unwrap();
+        sign_up_for_shift(shifts[0].id, user.id, OverlapPolicy::Allow, &db)
+            .await
+            .unwrap();
+
+        let (_, sync) = update_shift_template(
+            template.id,
+            ShiftTemplateChanges {
+                name: Some("Cocktailbar".to_string()),
+                interval_days: Some(2),
+                ..Default::default()
+            },
+            offset,
+            &Actor::cli(),
+            &db,
+        )
+        .await
+        .unwrap();
+
+        // Day 1 has a sign-up, day 2 and 4 no longer occur with an interval of two days
+        assert_eq!(
+            sync,
+            TemplateSync {
+                updated: 1,
+                removed: 2
+            }
+        );
+
+        let signed_up = get_shift_by_id(shifts[0].id, &db).await.unwrap().unwrap();
+        assert_eq!(signed_up.name, "Bar");
+        let updated = get_shift_by_id(shifts[2].id, &db).await.unwrap().unwrap();
+        assert_eq!(updated.name, "Cocktailbar");
+        assert!(get_shift_by_id(shifts[1].id, &db).await.unwrap().is_none());
+
+        assert!(matches!(
+            update_shift_template(
+                template.id,
+                ShiftTemplateChanges {
+                    duration_minutes: Some(0),
+                    ..Default::default()
+                },
+                offset,
+                &Actor::cli(),
+                &db,
+            )
+            .await,
+            Err(Error::InvalidRecurrence)
+        ));
+    }
+}
@@ -0,0 +1,18 @@
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use sha2::{Digest, Sha256};
+
+/// Generates a random secret for use in URLs or headers
+pub fn generate_token() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect()
+}
+
+/// Hashes a token for storage. Tokens are random and long, so a fast hash without salt is enough,
+/// and it allows looking tokens up by their hash.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
@@ -5,12 +5,18 @@ use argon2::password_hash::rand_core::OsRng;
 use argon2::{Argon2, password_hash::SaltString};
 use argon2::{PasswordHash, PasswordVerifier};
 use entity::public::{self};
-use sea_orm::{ActiveValue::*, IntoActiveModel, Iterable, QuerySelect, prelude::*};
+use sea_orm::{
+    ActiveValue::*, IntoActiveModel, Iterable, QuerySelect, TransactionTrait, prelude::*,
+};
 use tracing::error;
 
+use crate::AuditAction;
 use crate::Error;
+use crate::audit::{Actor, record_audit};
+use crate::notification::emit_notification;
 use crate::role::RoleType;
-use entity::intern::*;
+use crate::session::delete_user_sessions;
+use entity::intern::{role::RoleId, *};
 
 pub async fn get_all_guests(db: &DatabaseConnection) -> crate::Result<Vec<user::Model>> {
     Ok(User::find()
@@ -64,6 +70,17 @@ pub async fn get_user_by_id(
     Ok(User::find_by_id(uid).one(db).await?)
 }
 
+/// The current role of a user. Read on every authenticated request, so role changes take effect
+/// immediately instead of on the next login.
+pub async fn get_user_role_id(uid: Uuid, db: &DatabaseConnection) -> crate::Result<Option<RoleId>> {
+    Ok(User::find_by_id(uid)
+        .select_only()
+        .column(user::Column::RoleId)
+        .into_tuple()
+        .one(db)
+        .await?)
+}
+
 pub async fn get_user_by_name(
     name: &str,
     db: &DatabaseConnection,
@@ -112,13 +129,18 @@ pub async fn update_user(
     let mut user = user.into_active_model();
 
     for col in user::Column::iter() {
-        if let Set(new) = changes.get(col) {
-            if user.get(col).into_value().as_ref() != Some(&new) {
-                user.set(col, new);
-            }
+        if let Set(new) = changes.get(col)
+            && user.get(col).into_value().as_ref() != Some(&new)
+        {
+            user.set(col, new);
         }
     }
 
+    // A new email has to be verified again
+    if user.email.is_set() {
+        user.email_verified_at = Set(None);
+    }
+
     if user.is_changed() {
         Ok(Some(user.update(db).await?))
     } else {
@@ -192,6 +214,46 @@ pub async fn verify_user(
     None
 }
 
+/// Fails with [`Error::WrongPassword`] if the password isn't the one of the user
+pub async fn check_password(
+    uid: Uuid,
+    plain_password: &str,
+    db: &DatabaseConnection,
+) -> crate::Result<user::Model> {
+    let user = get_user_by_id(uid, db).await?.ok_or(Error::UserNotFound)?;
+
+    if !verify_password(plain_password, &user.password_hash) {
+        return Err(Error::WrongPassword);
+    }
+
+    Ok(user)
+}
+
+/// Changes the password of a user after checking the current one. All sessions of the user are
+/// ended, the caller has to start a new one if it should stay logged in.
+pub async fn change_password(
+    uid: Uuid,
+    current_password: &str,
+    new_password: &str,
+    db: &DatabaseConnection,
+) -> crate::Result<()> {
+    let user = check_password(uid, current_password, db).await?;
+
+    let password_hash = hash_password(new_password)?;
+
+    let txn = db.begin().await?;
+
+    let mut user = user.into_active_model();
+    user.password_hash = Set(password_hash);
+    user.update(&txn).await?;
+
+    delete_user_sessions(uid, &txn).await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
 pub async fn add_generic_user(
     username: impl Into<String>,
     email: impl Into<String>,
@@ -267,9 +329,10 @@ pub async fn add_admin(
 pub async fn get_role_by_username(
     username: &str,
     db: &DatabaseConnection,
-) -> crate::Result<RoleType> {
-    let Some(user) = User::find()
+) -> crate::Result<role::Model> {
+    let Some((_, Some(role))) = User::find()
         .filter(user::Column::Username.eq(username))
+        .find_also_related(Role)
         .one(db)
         .await?
     else {
@@ -278,13 +341,13 @@ pub async fn get_role_by_username(
         });
     };
 
-    Ok(RoleType::from_repr(user.role_id)
-        .expect("FIXME: Expand the logic to be able to handle custom roles"))
+    Ok(role)
 }
 
 pub async fn set_role_by_username(
     username: &str,
-    role: RoleType,
+    role_id: RoleId,
+    actor: &Actor,
     db: &DatabaseConnection,
 ) -> crate::Result<user::Model> {
     let Some(user) = User::find()
@@ -297,10 +360,48 @@ pub async fn set_role_by_username(
         });
     };
 
-    let mut user = user.into_active_model();
-    user.role_id = Set(role as u32);
+    let role = Role::find_by_id(role_id)
+        .one(db)
+        .await?
+        .ok_or(Error::RoleNotFound)?;
 
-    Ok(user.update(db).await?)
+    if user.role_id == role_id {
+        return Ok(user);
+    }
+
+    let txn = db.begin().await?;
+
+    let previous = Role::find_by_id(user.role_id).one(&txn).await?;
+    let before = serde_json::json!({
+        "role_id": user.role_id,
+        "role": previous.map(|role| role.name),
+    });
+    let mut user = user.into_active_model();
+    user.role_id = Set(role_id);
+    let user = user.update(&txn).await?;
+
+    record_audit(
+        actor,
+        AuditAction::UserRoleChanged,
+        user.id,
+        Some(before),
+        Some(serde_json::json!({ "role_id": role_id, "role": role.name })),
+        &txn,
+    )
+    .await?;
+
+    emit_notification(
+        &[user.id],
+        notification::NotificationKind::RoleChanged,
+        None,
+        &format!("Deine Rolle wurde zu „{}“ geändert.", role.name),
+        &txn,
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(user)
 }
 
 #[cfg(test)]
@@ -332,7 +433,7 @@ mod tests {
         let user = add_guest("Meow", "meow@meow.de", "awawa", &db)
             .await
             .unwrap();
-        let user2 = add_guest("Meow2", "meow@meow.de", "awawa", &db)
+        let user2 = add_guest("Meow2", "meow2@meow.de", "awawa", &db)
             .await
             .unwrap();
 
@@ -342,4 +443,88 @@ mod tests {
         assert!(all_users.contains(&user2));
         assert_eq!(all_users.len(), 2);
     }
+
+    #[test(tokio::test)]
+    async fn custom_role_by_username() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        add_guest("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let role = role::ActiveModel {
+            id: NotSet,
+            name: Set("Schichtleitung".to_string()),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        User::update_many()
+            .col_expr(user::Column::RoleId, Expr::value(role.id))
+            .filter(user::Column::Username.eq("Meow"))
+            .exec(&db)
+            .await
+            .unwrap();
+
+        assert_eq!(get_role_by_username("Meow", &db).await.unwrap(), role);
+    }
+
+    #[test(tokio::test)]
+    async fn change_password_ends_sessions() {
+        use crate::session::{load_session, save_session};
+        use std::collections::HashMap;
+
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_guest("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        let other = add_guest("Meow2", "meow2@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+
+        let ttl = time::Duration::hours(1);
+        let login = |uid: Uuid| {
+            HashMap::from([("user_id".to_string(), serde_json::to_string(&uid).unwrap())])
+        };
+        let session = save_session(&db, login(user.id), &ttl).await.unwrap();
+        let other_session = save_session(&db, login(other.id), &ttl).await.unwrap();
+
+        assert!(matches!(
+            change_password(user.id, "wrong", "new password", &db).await,
+            Err(Error::WrongPassword)
+        ));
+        assert!(load_session(&db, &session).await.unwrap().is_some());
+
+        change_password(user.id, "awawa", "new password", &db)
+            .await
+            .unwrap();
+
+        assert!(load_session(&db, &session).await.unwrap().is_none());
+        assert!(load_session(&db, &other_session).await.unwrap().is_some());
+        assert!(verify_user("Meow", "new password", &db).await.is_some());
+    }
+
+    #[test(tokio::test)]
+    async fn role_change_is_visible_at_once() {
+        let db = connect_and_migrate_dummy().await.unwrap();
+
+        let user = add_user("Meow", "meow@meow.de", "awawa", &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_user_role_id(user.id, &db).await.unwrap(),
+            Some(RoleType::User as RoleId)
+        );
+
+        set_role_by_username("Meow", RoleType::Guest as RoleId, &Actor::cli(), &db)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_user_role_id(user.id, &db).await.unwrap(),
+            Some(RoleType::Guest as RoleId)
+        );
+
+        assert_eq!(get_user_role_id(Uuid::new_v4(), &db).await.unwrap(), None);
+    }
 }
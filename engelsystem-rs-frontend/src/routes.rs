@@ -1,17 +1,32 @@
 mod landing;
 mod login;
 mod logout;
+mod notifications;
+mod password_reset;
 mod register;
 mod settings;
 mod users;
+mod verify_email;
 mod welcome;
 
 pub use landing::landing_page;
 pub use login::{login_page, request_login};
 pub use logout::request_logout;
+pub use notifications::{notification_list, read_all_notifications, read_notification};
+pub use password_reset::{
+    confirm_password_reset, password_reset_confirm_page, password_reset_page,
+    request_password_reset,
+};
 pub use register::{register_page, request_register};
+pub use settings::create_token;
+pub use settings::end_all_sessions;
+pub use settings::end_session;
+pub use settings::resend_verification_mail;
+pub use settings::revoke_token;
 pub use settings::settings_page;
+pub use settings::update_reminders;
 pub use settings::update_settings;
 pub use users::user_list;
 pub use users::view_user;
+pub use verify_email::verify_email_page;
 pub use welcome::welcome_page;
@@ -1,5 +1,5 @@
 use actix_web::{
-    HttpResponse, Responder, get,
+    HttpRequest, HttpResponse, Responder, get,
     http::header::{self},
     post,
     web::{self, Data, Form},
@@ -19,6 +19,7 @@ use crate::{
 #[derive(Deserialize)]
 struct LoginPageData {
     created: Option<bool>,
+    reset: Option<bool>,
 }
 
 #[get("/login")]
@@ -33,25 +34,30 @@ pub async fn login_page(
             .finish());
     }
 
-    let rendered = render_template!(&templates, "login.html", session, [ "created" => &data.created.unwrap_or(false) ])?;
+    let rendered = render_template!(&templates, "login.html", session, [ "created" => &data.created.unwrap_or(false), "reset" => &data.reset.unwrap_or(false) ])?;
 
     Ok(HttpResponse::Ok().html(rendered))
 }
 
 #[post("/login")]
 pub async fn request_login(
+    req: HttpRequest,
     templates: Data<Tera>,
     client: Data<reqwest::Client>,
     Form(body): Form<serde_json::Value>,
     session: PublicSession,
 ) -> crate::Result<impl Responder> {
     const LOGIN_URL: &str = "http://127.0.0.1:8081/login";
-    let response = client
-        .post(LOGIN_URL)
-        .json(&body)
-        .send()
-        .await
-        .context(BackendErr)?;
+    let mut request = client.post(LOGIN_URL).json(&body);
+    // The API counts failed logins per address, so it needs the visitor's and not ours
+    if let Some(peer) = req.peer_addr() {
+        request = request.header("x-forwarded-for", peer.ip().to_string());
+    }
+    // Shown in the list of sessions, so the user can recognize this login
+    if let Some(user_agent) = req.headers().get(header::USER_AGENT) {
+        request = request.header(reqwest::header::USER_AGENT, user_agent.as_bytes());
+    }
+    let response = request.send().await.context(BackendErr)?;
 
     if response.status().is_success() {
         let session_id =
@@ -67,6 +73,13 @@ pub async fn request_login(
             .finish());
     }
 
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let error = response.text().await.context(BackendErr)?;
+        let rendered = render_template!(&templates, "login.html", session, [ "errors" => &error ])?;
+
+        return Ok(HttpResponse::TooManyRequests().html(rendered));
+    }
+
     let rendered = render_template!(&templates, "login.html", session, [ "error" => &true ])?;
 
     Ok(HttpResponse::Unauthorized().html(rendered))
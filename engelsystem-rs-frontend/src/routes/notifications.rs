@@ -0,0 +1,74 @@
+use actix_web::{
+    HttpResponse, Responder, get,
+    http::header,
+    post,
+    web::{self, Data, Html},
+};
+use snafu::ResultExt;
+use tera::Tera;
+
+use crate::{
+    generated::BackendErr,
+    render_template,
+    session::{RequestSessionExt, Session},
+};
+
+const NOTIFICATIONS_URL: &str = "http://127.0.0.1:8081/notifications";
+
+#[get("/notifications")]
+pub async fn notification_list(
+    templates: Data<Tera>,
+    client: Data<reqwest::Client>,
+    session: Session,
+) -> crate::Result<impl Responder> {
+    let notifications: serde_json::Value = client
+        .get(format!("{NOTIFICATIONS_URL}/"))
+        .add_session(&session)
+        .send()
+        .await
+        .context(BackendErr)?
+        .json()
+        .await
+        .context(BackendErr)?;
+
+    let rendered = render_template!(&templates, "notifications.html", session, [
+        "notifications" => &notifications
+    ])?;
+
+    Ok(Html::new(rendered))
+}
+
+#[post("/notifications/{notification_id}/read")]
+pub async fn read_notification(
+    client: Data<reqwest::Client>,
+    notification_id: web::Path<String>,
+    session: Session,
+) -> crate::Result<impl Responder> {
+    client
+        .post(format!("{NOTIFICATIONS_URL}/{notification_id}/read"))
+        .add_session(&session)
+        .send()
+        .await
+        .context(BackendErr)?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/notifications"))
+        .finish())
+}
+
+#[post("/notifications/read")]
+pub async fn read_all_notifications(
+    client: Data<reqwest::Client>,
+    session: Session,
+) -> crate::Result<impl Responder> {
+    client
+        .post(format!("{NOTIFICATIONS_URL}/read"))
+        .add_session(&session)
+        .send()
+        .await
+        .context(BackendErr)?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/notifications"))
+        .finish())
+}
@@ -0,0 +1,104 @@
+use actix_web::{
+    HttpResponse, Responder, get,
+    http::header::{self},
+    post,
+    web::{Data, Form, Query},
+};
+use serde::Deserialize;
+use snafu::ResultExt;
+use tera::Tera;
+
+use crate::{
+    generated::BackendErr, render_template, session::PublicSession,
+    utils::response_ext::ActixResponseExt,
+};
+
+#[derive(Deserialize)]
+struct ConfirmPageData {
+    token: Option<String>,
+}
+
+#[get("/password-reset")]
+pub async fn password_reset_page(
+    templates: Data<Tera>,
+    session: PublicSession,
+) -> crate::Result<HttpResponse> {
+    if session.exists() {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/welcome"))
+            .finish());
+    }
+
+    let rendered = render_template!(&templates, "password_reset.html", session, [])?;
+
+    Ok(HttpResponse::Ok().html(rendered))
+}
+
+#[post("/password-reset")]
+pub async fn request_password_reset(
+    templates: Data<Tera>,
+    client: Data<reqwest::Client>,
+    Form(body): Form<serde_json::Value>,
+    session: PublicSession,
+) -> crate::Result<impl Responder> {
+    const REQUEST_URL: &str = "http://127.0.0.1:8081/password-reset/request";
+    let response = client
+        .post(REQUEST_URL)
+        .json(&body)
+        .send()
+        .await
+        .context(BackendErr)?;
+
+    if response.status().is_success() {
+        let rendered =
+            render_template!(&templates, "password_reset.html", session, [ "requested" => &true ])?;
+        return Ok(HttpResponse::Ok().html(rendered));
+    }
+
+    let error = response.text().await.context(BackendErr)?;
+
+    let rendered =
+        render_template!(&templates, "password_reset.html", session, [ "errors" => &error ])?;
+
+    Ok(HttpResponse::BadRequest().html(rendered))
+}
+
+#[get("/password-reset/confirm")]
+pub async fn password_reset_confirm_page(
+    Query(data): Query<ConfirmPageData>,
+    templates: Data<Tera>,
+    session: PublicSession,
+) -> crate::Result<HttpResponse> {
+    let rendered = render_template!(&templates, "password_reset_confirm.html", session, [ "token" => &data.token.unwrap_or_default() ])?;
+
+    Ok(HttpResponse::Ok().html(rendered))
+}
+
+#[post("/password-reset/confirm")]
+pub async fn confirm_password_reset(
+    templates: Data<Tera>,
+    client: Data<reqwest::Client>,
+    Form(body): Form<serde_json::Value>,
+    session: PublicSession,
+) -> crate::Result<impl Responder> {
+    const CONFIRM_URL: &str = "http://127.0.0.1:8081/password-reset/confirm";
+    let response = client
+        .post(CONFIRM_URL)
+        .json(&body)
+        .send()
+        .await
+        .context(BackendErr)?;
+
+    if response.status().is_success() {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/login?reset=true"))
+            .finish());
+    }
+
+    let error = response.text().await.context(BackendErr)?;
+    let token = body["token"].as_str().unwrap_or_default();
+
+    let rendered = render_template!(&templates, "password_reset_confirm.html", session, [ "token" => &token, "errors" => &error ])?;
+
+    Ok(HttpResponse::BadRequest().html(rendered))
+}
@@ -2,7 +2,7 @@ use actix_web::{
     HttpResponse, Responder, get,
     http::header,
     post,
-    web::{Data, Form, Html, Query},
+    web::{Data, Form, Html, Path, Query},
 };
 use engelsystem_rs_db::UserView;
 use serde::{Deserialize, Serialize};
@@ -10,23 +10,53 @@ use tera::Tera;
 
 use crate::{
     render_template,
-    session::{RequestSessionExt, Session},
+    session::{RequestSessionExt, ResponseCookieExt, Session},
+    utils::response_ext::ActixResponseExt,
 };
 
+const SESSIONS_URL: &str = "http://127.0.0.1:8081/me/sessions";
+const TOKENS_URL: &str = "http://127.0.0.1:8081/me/tokens";
+
 #[derive(Debug, Deserialize)]
 pub struct SettingsUpdateStatus {
     success: Option<bool>,
     error: Option<String>,
+    verification_sent: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SettingsUpdateRequest {
     username: String,
     email: String,
+    current_password: Option<String>,
     password: Option<String>,
     confirm_password: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReminderPreference {
+    enabled: bool,
+    lead_minutes: Option<u32>,
+    default_lead_minutes: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReminderUpdateForm {
+    /// Only sent by the browser if the checkbox is ticked
+    enabled: Option<String>,
+    /// Empty for the default lead time
+    lead_minutes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenCreateForm {
+    name: String,
+    /// Permission names, separated by commas or spaces
+    scopes: String,
+    /// The last day the token is valid, empty for tokens that don't expire
+    expires_on: Option<String>,
+}
+
 #[get("/settings")]
 pub async fn settings_page(
     templates: Data<Tera>,
@@ -43,10 +73,38 @@ pub async fn settings_page(
         .json()
         .await?;
 
+    const REMINDERS_URL: &str = "http://127.0.0.1:8081/me/reminders";
+    let reminders: ReminderPreference = client
+        .get(REMINDERS_URL)
+        .add_session(&session)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    let sessions: serde_json::Value = client
+        .get(SESSIONS_URL)
+        .add_session(&session)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    let tokens: serde_json::Value = client
+        .get(TOKENS_URL)
+        .add_session(&session)
+        .send()
+        .await?
+        .json()
+        .await?;
+
     if update_status.success.is_some() {
         Ok(Html::new(
             render_template!(&templates, "settings_updated.html", session, [
                 "user" => &user,
+                "reminders" => &reminders,
+                "sessions" => &sessions,
+                "tokens" => &tokens,
                 "success" => &update_status.success,
                 "error" => &update_status.error
             ])?,
@@ -54,7 +112,11 @@ pub async fn settings_page(
     } else {
         Ok(Html::new(
             render_template!(&templates, "settings.html", session, [
-                "user" => &user
+                "user" => &user,
+                "reminders" => &reminders,
+                "sessions" => &sessions,
+                "tokens" => &tokens,
+                "verification_sent" => &update_status.verification_sent.unwrap_or(false)
             ])?,
         ))
     }
@@ -75,7 +137,14 @@ pub async fn update_settings(
         .await?;
 
     if response.status().is_success() {
-        Ok(HttpResponse::SeeOther()
+        let mut redirect = HttpResponse::SeeOther();
+
+        // A password change ends all sessions, the current one continues under a new id
+        if let Some(session_id) = response.cookie("session-id") {
+            redirect.session_cookie(session_id.value());
+        }
+
+        Ok(redirect
             .append_header((header::LOCATION, "/settings?success=true"))
             .finish())
     } else {
@@ -88,3 +157,178 @@ pub async fn update_settings(
             .finish())
     }
 }
+
+#[post("/settings/verify-email")]
+pub async fn resend_verification_mail(
+    client: Data<reqwest::Client>,
+    session: Session,
+) -> crate::Result<impl Responder> {
+    const RESEND_URL: &str = "http://127.0.0.1:8081/me/email-verification";
+    let response = client.post(RESEND_URL).add_session(&session).send().await?;
+
+    let location = if response.status().is_success() {
+        "/settings?verification_sent=true".to_string()
+    } else {
+        let error = response.text().await?;
+        format!("/settings?success=false&error={error}")
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, location))
+        .finish())
+}
+
+#[post("/settings/reminders")]
+pub async fn update_reminders(
+    client: Data<reqwest::Client>,
+    session: Session,
+    Form(form): Form<ReminderUpdateForm>,
+) -> crate::Result<impl Responder> {
+    const REMINDERS_URL: &str = "http://127.0.0.1:8081/me/reminders";
+    let lead_minutes = form
+        .lead_minutes
+        .filter(|minutes| !minutes.trim().is_empty())
+        .and_then(|minutes| minutes.trim().parse::<u32>().ok());
+
+    let response = client
+        .put(REMINDERS_URL)
+        .add_session(&session)
+        .json(&serde_json::json!({
+            "enabled": form.enabled.is_some(),
+            "lead_minutes": lead_minutes,
+        }))
+        .send()
+        .await?;
+
+    let location = if response.status().is_success() {
+        "/settings?success=true".to_string()
+    } else {
+        let error = response.text().await?;
+        format!("/settings?success=false&error={error}")
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, location))
+        .finish())
+}
+
+#[post("/settings/sessions/{session_id}/end")]
+pub async fn end_session(
+    client: Data<reqwest::Client>,
+    session: Session,
+    session_id: Path<String>,
+) -> crate::Result<impl Responder> {
+    let response = client
+        .delete(format!("{SESSIONS_URL}/{session_id}"))
+        .add_session(&session)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let error = response.text().await?;
+        return Ok(HttpResponse::SeeOther()
+            .append_header((
+                header::LOCATION,
+                format!("/settings?success=false&error={error}"),
+            ))
+            .finish());
+    }
+
+    // The backend removes the cookie if the current session was ended
+    if response.cookie("session-id").is_some() {
+        return Ok(HttpResponse::SeeOther()
+            .expire_session()
+            .append_header((header::LOCATION, "/login"))
+            .finish());
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/settings"))
+        .finish())
+}
+
+#[post("/settings/sessions/end")]
+pub async fn end_all_sessions(
+    client: Data<reqwest::Client>,
+    session: Session,
+) -> crate::Result<impl Responder> {
+    client
+        .delete(SESSIONS_URL)
+        .add_session(&session)
+        .send()
+        .await?;
+
+    Ok(HttpResponse::SeeOther()
+        .expire_session()
+        .append_header((header::LOCATION, "/login"))
+        .finish())
+}
+
+#[post("/settings/tokens")]
+pub async fn create_token(
+    templates: Data<Tera>,
+    client: Data<reqwest::Client>,
+    session: Session,
+    Form(form): Form<TokenCreateForm>,
+) -> crate::Result<HttpResponse> {
+    let scopes: Vec<&str> = form
+        .scopes
+        .split([',', ' '])
+        .filter(|scope| !scope.is_empty())
+        .collect();
+    let expires_at = form
+        .expires_on
+        .filter(|day| !day.trim().is_empty())
+        .map(|day| format!("{}T23:59:59Z", day.trim()));
+
+    let response = client
+        .post(TOKENS_URL)
+        .add_session(&session)
+        .json(&serde_json::json!({
+            "name": form.name,
+            "scopes": scopes,
+            "expires_at": expires_at,
+        }))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let error = response.text().await?;
+        return Ok(HttpResponse::SeeOther()
+            .append_header((
+                header::LOCATION,
+                format!("/settings?success=false&error={error}"),
+            ))
+            .finish());
+    }
+
+    // The token is only shown on this page, so it never ends up in a URL
+    let token: serde_json::Value = response.json().await?;
+    let rendered =
+        render_template!(&templates, "token_created.html", session, [ "token" => &token ])?;
+    Ok(HttpResponse::Ok().html(rendered))
+}
+
+#[post("/settings/tokens/{token_id}/revoke")]
+pub async fn revoke_token(
+    client: Data<reqwest::Client>,
+    session: Session,
+    token_id: Path<String>,
+) -> crate::Result<impl Responder> {
+    let response = client
+        .delete(format!("{TOKENS_URL}/{token_id}"))
+        .add_session(&session)
+        .send()
+        .await?;
+
+    let location = if response.status().is_success() {
+        "/settings".to_string()
+    } else {
+        let error = response.text().await?;
+        format!("/settings?success=false&error={error}")
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, location))
+        .finish())
+}
@@ -0,0 +1,47 @@
+use actix_web::{
+    HttpResponse, get,
+    web::{Data, Query},
+};
+use serde::Deserialize;
+use serde_json::json;
+use snafu::ResultExt;
+use tera::Tera;
+
+use crate::{
+    generated::BackendErr, render_template, session::PublicSession,
+    utils::response_ext::ActixResponseExt,
+};
+
+#[derive(Deserialize)]
+struct VerifyEmailData {
+    token: Option<String>,
+}
+
+/// The target of the link in verification mails
+#[get("/verify-email")]
+pub async fn verify_email_page(
+    Query(data): Query<VerifyEmailData>,
+    templates: Data<Tera>,
+    client: Data<reqwest::Client>,
+    session: PublicSession,
+) -> crate::Result<HttpResponse> {
+    const CONFIRM_URL: &str = "http://127.0.0.1:8081/email-verification/confirm";
+    let response = client
+        .post(CONFIRM_URL)
+        .json(&json!({ "token": data.token.unwrap_or_default() }))
+        .send()
+        .await
+        .context(BackendErr)?;
+
+    if response.status().is_success() {
+        let rendered = render_template!(&templates, "verify_email.html", session, [])?;
+        return Ok(HttpResponse::Ok().html(rendered));
+    }
+
+    let error = response.text().await.context(BackendErr)?;
+
+    let rendered =
+        render_template!(&templates, "verify_email.html", session, [ "error" => &error ])?;
+
+    Ok(HttpResponse::BadRequest().html(rendered))
+}
@@ -1,9 +1,6 @@
-use std::str::FromStr;
-
 use actix_web::{get, web::Data, HttpResponse, Responder};
-use engelsystem_rs_db::{role::RoleType, Shift, UserView};
+use engelsystem_rs_db::{Shift, UserView};
 use tera::Tera;
-use tracing::error;
 
 use crate::{
     render_template,
@@ -44,13 +41,17 @@ async fn welcome_page(
 
     let next_shift = future_shifts.first();
 
-    let is_admin = RoleType::from_str(&user.role)
-        .as_ref()
-        .map(RoleType::is_bypass)
-        .unwrap_or_else(|e| {
-            error!("Failed to convert user view role name to RoleType: {e}");
-            false
-        });
+    // Roles can be renamed, so admins are recognized by being able to manage roles
+    const PERMISSIONS_URL: &str = "http://127.0.0.1:8081/me/permissions";
+    let permissions: Vec<String> = client
+        .get(PERMISSIONS_URL)
+        .add_session(&session)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let is_admin = permissions.iter().any(|name| name == "ManageRoles");
 
     Ok(HttpResponse::Ok()
         .html(render_template!(
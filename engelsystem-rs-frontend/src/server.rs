@@ -23,7 +23,22 @@ pub async fn run_server() -> crate::Result<()> {
         debug!("loaded: {template}");
     }
     let shared_templates = Data::new(templates);
-    let shared_client = Data::new(reqwest::Client::new());
+    // Tells the API that changes come from the web frontend, for its audit log
+    let mut default_headers = reqwest::header::HeaderMap::new();
+    default_headers.insert(
+        "x-engelsystem-client",
+        reqwest::header::HeaderValue::from_static("web"),
+    );
+    let shared_client = match reqwest::Client::builder()
+        .default_headers(default_headers)
+        .build()
+    {
+        Ok(client) => Data::new(client),
+        Err(e) => {
+            tracing::error!("Couldn't create the HTTP client: {e}");
+            std::process::exit(1);
+        }
+    };
 
     HttpServer::new(move || {
         App::new()
@@ -35,11 +50,25 @@ pub async fn run_server() -> crate::Result<()> {
             .service(request_register)
             .service(request_login)
             .service(request_logout)
+            .service(password_reset_page)
+            .service(request_password_reset)
+            .service(password_reset_confirm_page)
+            .service(confirm_password_reset)
             .service(welcome_page)
             .service(user_list)
             .service(view_user)
             .service(settings_page)
             .service(update_settings)
+            .service(end_all_sessions)
+            .service(end_session)
+            .service(resend_verification_mail)
+            .service(update_reminders)
+            .service(create_token)
+            .service(revoke_token)
+            .service(verify_email_page)
+            .service(notification_list)
+            .service(read_all_notifications)
+            .service(read_notification)
             .service(Files::new("/static", "assets"))
     })
     .bind((Ipv4Addr::UNSPECIFIED, 8080))
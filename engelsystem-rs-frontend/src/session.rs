@@ -1,7 +1,8 @@
 use std::{future::ready, marker::PhantomData, pin::Pin};
 
-use actix_web::{FromRequest, HttpRequest, dev::Payload};
+use actix_web::{FromRequest, HttpRequest, dev::Payload, web::Data};
 use reqwest::RequestBuilder;
+use serde::Deserialize;
 
 use crate::{Error, templates::BaseData};
 
@@ -12,6 +13,8 @@ pub type PublicSession = Session<Public>;
 
 pub struct Session<A = Authenticated> {
     session_id: Option<String>,
+    /// Fetches the unread notifications for the navbar, only when a page is rendered
+    client: Option<Data<reqwest::Client>>,
 
     _accessibility: PhantomData<A>,
 }
@@ -20,13 +23,16 @@ impl Session<Public> {
     fn new_opt(session_id: Option<String>) -> Self {
         Session {
             session_id,
+            client: None,
 
             _accessibility: PhantomData,
         }
     }
 
     pub fn upgrade(self) -> Option<Session<Authenticated>> {
-        Some(Session::new(self.session_id?))
+        let mut session = Session::new(self.session_id?);
+        session.client = self.client;
+        Some(session)
     }
 }
 
@@ -34,13 +40,52 @@ impl<A> Session<A> {
     fn new(session_id: String) -> Self {
         Session {
             session_id: Some(session_id),
+            client: None,
 
             _accessibility: PhantomData,
         }
     }
 
-    pub fn base_data<'a>(&self, org: &'a str) -> BaseData<'a> {
-        BaseData::new(org, self.session_id.is_some())
+    pub async fn base_data<'a>(&self, org: &'a str) -> BaseData<'a> {
+        BaseData::new(
+            org,
+            self.session_id.is_some(),
+            self.fetch_unread_notifications().await.unwrap_or(0),
+        )
+    }
+
+    fn with_client(mut self, client: Option<Data<reqwest::Client>>) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Asks the backend how many unread notifications the user has. Pages still render without
+    /// the badge if that fails.
+    async fn fetch_unread_notifications(&self) -> Option<u64> {
+        const UNREAD_URL: &str = "http://127.0.0.1:8081/notifications/unread_count";
+
+        #[derive(Deserialize)]
+        struct UnreadCount {
+            count: u64,
+        }
+
+        let (Some(client), Some(cookie)) = (&self.client, self.cookie_opt()) else {
+            return None;
+        };
+
+        let response = client
+            .get(UNREAD_URL)
+            .header(reqwest::header::COOKIE, cookie)
+            .send()
+            .await
+            .ok()
+            .filter(|response| response.status().is_success())?;
+
+        response
+            .json::<UnreadCount>()
+            .await
+            .ok()
+            .map(|unread| unread.count)
     }
 
     pub fn exists(&self) -> bool {
@@ -71,7 +116,9 @@ impl FromRequest for Session<Public> {
     type Future = Pin<Box<dyn Future<Output = Result<Self, Error>>>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
-        Box::pin(ready(Ok(req.into_optional_session())))
+        let client = req.app_data::<Data<reqwest::Client>>().cloned();
+
+        Box::pin(ready(Ok(req.into_optional_session().with_client(client))))
     }
 }
 
@@ -81,7 +128,12 @@ impl FromRequest for Session<Authenticated> {
     type Future = Pin<Box<dyn Future<Output = Result<Self, Error>>>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
-        Box::pin(ready(req.into_authenticated_session()))
+        let client = req.app_data::<Data<reqwest::Client>>().cloned();
+
+        Box::pin(ready(
+            req.into_authenticated_session()
+                .map(|session| session.with_client(client)),
+        ))
     }
 }
 
@@ -3,15 +3,21 @@ use tera::Context;
 pub struct BaseData<'a> {
     org: &'a str,
     logged_in: bool,
+    unread_notifications: u64,
 }
 
 impl<'a> BaseData<'a> {
-    pub fn new(org: &'a str, logged_in: bool) -> Self {
-        Self { org, logged_in }
+    pub fn new(org: &'a str, logged_in: bool, unread_notifications: u64) -> Self {
+        Self {
+            org,
+            logged_in,
+            unread_notifications,
+        }
     }
 
     pub fn insert(self, ctx: &mut Context) {
         ctx.insert("org", &self.org);
         ctx.insert("logged_in", &self.logged_in);
+        ctx.insert("unread_notifications", &self.unread_notifications);
     }
 }
@@ -27,7 +27,7 @@ macro_rules! render_template {
      [ $( $data_name:expr => $data_val:expr ),* ]
     ) => {{
         let mut context = ::tera::Context::new();
-        $session.base_data("Real Org").insert(&mut context);
+        $session.base_data("Real Org").await.insert(&mut context);
 
         $(
             context.insert($data_name, $data_val);